@@ -2,6 +2,13 @@
 
 document.addEventListener("DOMContentLoaded", () => {
   const $ = (sel) => document.querySelector(sel);
+
+  // Each device keeps its own undo history on the server
+  let clientId = localStorage.getItem("hearthglow_client_id");
+  if (!clientId) {
+    clientId = `${Date.now().toString(36)}-${Math.random().toString(36).slice(2)}`;
+    localStorage.setItem("hearthglow_client_id", clientId);
+  }
   const tabLanding = $("#tab_landing");
   const tabStars = $("#tab_stars");
   const tabAdmin = $("#tab_admin");
@@ -24,6 +31,8 @@ document.addEventListener("DOMContentLoaded", () => {
   const eventTitleInput = $("#event_title");
   const eventStartInput = $("#event_start");
   const eventEndInput = $("#event_end");
+  const eventRepeatSelect = $("#event_repeat");
+  const eventImportInput = $("#event_import");
   const eventAddBtn = $("#event_add");
   const eventResult = $("#event_result");
   const eventFormPanel = $("#event_form_panel");
@@ -34,22 +43,23 @@ document.addEventListener("DOMContentLoaded", () => {
   let currentPersonId = null;
   let currentTab = "landing";
   let calendarInstance = null;
-  const chartCooldownMs = 30000;
+  // chart id -> time the server will accept the next star; the server
+  // enforces the cooldown, this only mirrors it on the buttons
   const chartCooldowns = new Map();
 
   function canAddStar(chartId) {
-    const last = chartCooldowns.get(chartId) || 0;
-    return Date.now() - last >= chartCooldownMs;
+    return Date.now() >= (chartCooldowns.get(chartId) || 0);
   }
 
-  function startCooldown(chartId) {
-    chartCooldowns.set(chartId, Date.now());
+  function startCooldown(chartId, seconds) {
+    if (!seconds || seconds <= 0) return;
+    chartCooldowns.set(chartId, Date.now() + seconds * 1000);
   }
 
   function applyCooldownToButton(btn, chartId) {
     if (!btn) return;
-    const last = chartCooldowns.get(chartId) || 0;
-    const remaining = Math.max(0, chartCooldownMs - (Date.now() - last));
+    const until = chartCooldowns.get(chartId) || 0;
+    const remaining = Math.max(0, until - Date.now());
     if (remaining > 0) {
       btn.disabled = true;
       const seconds = Math.ceil(remaining / 1000);
@@ -138,16 +148,16 @@ document.addEventListener("DOMContentLoaded", () => {
         meta.className = "chart-meta";
         meta.innerHTML = `<strong>${escapeHtml(p.first_name)} ${escapeHtml(p.last_name)}</strong><span class='small'>id: ${p.id}</span>`;
         const del = document.createElement("button");
-        del.textContent = "Delete Person";
+        del.textContent = "Archive Person";
         del.addEventListener("click", async () => {
-          if (!confirm(`Delete ${p.first_name}? This removes their charts.`))
+          if (!confirm(`Archive ${p.first_name}? This hides their charts.`))
             return;
           const r = await fetch(`/admin/people/${p.id}`, { method: "DELETE" });
           if (r.ok) {
             li.remove();
             await loadLanding();
             await loadAdmin();
-          } else alert("Failed to delete person");
+          } else alert("Failed to archive person");
         });
         li.appendChild(meta);
         li.appendChild(del);
@@ -193,15 +203,15 @@ document.addEventListener("DOMContentLoaded", () => {
           } else alert("Save failed");
         });
         const delBtn = document.createElement("button");
-        delBtn.textContent = "Delete Chart";
+        delBtn.textContent = "Archive Chart";
         delBtn.addEventListener("click", async () => {
-          if (!confirm("Delete this chart?")) return;
+          if (!confirm("Archive this chart?")) return;
           const r = await fetch(`/admin/stars/${s.id}`, { method: "DELETE" });
           if (r.ok) {
             li.remove();
             await loadLanding();
             await loadAdmin();
-          } else alert("Delete failed");
+          } else alert("Archive failed");
         });
         controls.appendChild(countInput);
         controls.appendChild(totalInput);
@@ -398,6 +408,10 @@ document.addEventListener("DOMContentLoaded", () => {
       <p><strong>Start:</strong> ${escapeHtml(startStr)}</p>
       <p><strong>End:</strong> ${escapeHtml(endStr)}</p>
     `;
+    const rrule = event.extendedProps && event.extendedProps.rrule;
+    if (rrule) {
+      body.innerHTML += `<p><strong>Repeats:</strong> ${escapeHtml(rrule)}</p>`;
+    }
     
     modal.classList.remove("hidden");
   }
@@ -436,7 +450,7 @@ document.addEventListener("DOMContentLoaded", () => {
           count.className = "star-count";
           count.textContent = `${c.star_count} / ${c.star_total} stars`;
 
-          if (c.star_count >= c.star_total) {
+          if (c.status === "completed") {
             li.classList.add("star-complete");
           }
 
@@ -446,6 +460,18 @@ document.addEventListener("DOMContentLoaded", () => {
           btn.dataset.defaultLabel = "Add a Star ⭐";
           applyCooldownToButton(btn, c.id);
           if (btn.disabled) scheduleCooldownTicker(btn, c.id);
+          // quest charts stay locked until the charts they require are done
+          if (c.status === "locked") {
+            li.classList.add("star-locked");
+            btn.disabled = true;
+            btn.textContent = "🔒 Locked";
+          }
+          // time boxed charts close once their due date passes
+          if (c.status === "expired" || c.status === "failed") {
+            li.classList.add("star-locked");
+            btn.disabled = true;
+            btn.textContent = "⌛ Time's up";
+          }
           btn.addEventListener("click", async (ev) => {
             if (!canAddStar(c.id)) {
               applyCooldownToButton(btn, c.id);
@@ -453,12 +479,29 @@ document.addEventListener("DOMContentLoaded", () => {
               return;
             }
             try {
-              await incrementChart(c.id, 1);
-              startCooldown(c.id);
+              const result = await incrementChart(c.id, 1);
+              startCooldown(c.id, result.cooldown_seconds);
               applyCooldownToButton(btn, c.id);
               scheduleCooldownTicker(btn, c.id);
-              showConfettiAtElement(ev.target);
+              if (result.approval_id) {
+                alert("Star sent to a grown-up for approval!");
+              } else if (result.completed) {
+                // the server only reports completion once, so this is the big moment
+                for (let i = 0; i < 4; i++) {
+                  setTimeout(() => showConfettiAtElement(ev.target), i * 250);
+                }
+              } else if (result.bonus) {
+                // one burst per star the bonus turned this click into
+                for (let i = 0; i < result.bonus.multiplier; i++) {
+                  setTimeout(() => showConfettiAtElement(ev.target), i * 250);
+                }
+              } else {
+                showConfettiAtElement(ev.target);
+              }
             } catch (err) {
+              startCooldown(c.id, err.retryAfter);
+              applyCooldownToButton(btn, c.id);
+              scheduleCooldownTicker(btn, c.id);
               console.error("increment failed", err);
             }
             await loadStarsFocus();
@@ -510,9 +553,12 @@ document.addEventListener("DOMContentLoaded", () => {
           start,
           end,
         };
+        if (eventRepeatSelect && eventRepeatSelect.value) {
+          payload.rrule = eventRepeatSelect.value;
+        }
         const res = await fetch("/calendar/events", {
           method: "POST",
-          headers: { "Content-Type": "application/json" },
+          headers: { "Content-Type": "application/json", "X-Client-Id": clientId },
           body: JSON.stringify(payload),
         });
         if (!res.ok) throw new Error(await res.text());
@@ -520,6 +566,7 @@ document.addEventListener("DOMContentLoaded", () => {
         eventTitleInput.value = "";
         eventStartInput.value = "";
         eventEndInput.value = "";
+        if (eventRepeatSelect) eventRepeatSelect.value = "";
         if (eventResult) eventResult.textContent = "Event added!";
         // Refresh calendar to show new event
         if (calendarInstance) {
@@ -541,6 +588,41 @@ document.addEventListener("DOMContentLoaded", () => {
     });
   }
 
+  if (eventImportInput) {
+    eventImportInput.addEventListener("change", async () => {
+      const file = eventImportInput.files && eventImportInput.files[0];
+      const personId = eventPersonSelect ? parseInt(eventPersonSelect.value) : 0;
+      if (!file) return;
+      if (!personId) {
+        if (eventResult) eventResult.textContent = "Pick a person to import the events for.";
+        eventImportInput.value = "";
+        return;
+      }
+
+      if (eventResult) eventResult.textContent = "Importing...";
+      try {
+        const res = await fetch(`/calendar/import?person_id=${personId}`, {
+          method: "POST",
+          headers: { "Content-Type": "text/calendar" },
+          body: await file.text(),
+        });
+        if (!res.ok) throw new Error(await res.text());
+        const report = await res.json();
+        if (eventResult) {
+          eventResult.textContent = `Imported: ${report.created.length} new, ${report.updated.length} updated, ${report.skipped.length} skipped`;
+        }
+        if (calendarInstance) {
+          const eventsRes = await fetch("/calendar/events");
+          if (eventsRes.ok) calendarInstance.setOption("events", await eventsRes.json());
+        }
+      } catch (err) {
+        if (eventResult) eventResult.textContent = `Error: ${err.toString()}`;
+      } finally {
+        eventImportInput.value = "";
+      }
+    });
+  }
+
   createPersonBtn.addEventListener("click", async () => {
     const first = document.getElementById("first_name").value.trim();
     const last = document.getElementById("last_name").value.trim();
@@ -626,7 +708,7 @@ document.addEventListener("DOMContentLoaded", () => {
           const meta = document.createElement("div");
           meta.className = "chart-meta";
           meta.innerHTML = `<strong>${escapeHtml(c.name)}</strong><span class='small'>${escapeHtml(c.description)}</span><span class='small'>${c.star_count} / ${c.star_total}</span>`;
-          if (c.star_count >= c.star_total) {
+          if (c.status === "completed") {
             li.classList.add("chart-complete");
             meta.innerHTML += `<span class='badge complete'>Complete</span>`;
           }
@@ -636,14 +718,29 @@ document.addEventListener("DOMContentLoaded", () => {
           btn.dataset.defaultLabel = "⭐ +1";
           applyCooldownToButton(btn, c.id);
           if (btn.disabled) scheduleCooldownTicker(btn, c.id);
+          if (c.status === "locked") {
+            btn.disabled = true;
+            btn.textContent = "🔒 Locked";
+            meta.innerHTML += `<span class='badge'>Locked</span>`;
+          }
+          if (c.status === "expired" || c.status === "failed") {
+            btn.disabled = true;
+            btn.textContent = "⌛ Time's up";
+            meta.innerHTML += `<span class='badge'>${c.status === "failed" ? "Failed" : "Expired"}</span>`;
+          }
           btn.addEventListener("click", async () => {
             if (!canAddStar(c.id)) {
               applyCooldownToButton(btn, c.id);
               scheduleCooldownTicker(btn, c.id);
               return;
             }
-            await incrementChart(c.id, 1);
-            startCooldown(c.id);
+            try {
+              const result = await incrementChart(c.id, 1);
+              startCooldown(c.id, result.cooldown_seconds);
+            } catch (err) {
+              startCooldown(c.id, err.retryAfter);
+              console.error("increment failed", err);
+            }
             applyCooldownToButton(btn, c.id);
             scheduleCooldownTicker(btn, c.id);
             await loadPerson(body.first_name);
@@ -672,10 +769,19 @@ document.addEventListener("DOMContentLoaded", () => {
   async function incrementChart(id, delta) {
     const res = await fetch(`/stars/${id}/increment`, {
       method: "POST",
-      headers: { "Content-Type": "application/json" },
+      headers: { "Content-Type": "application/json", "X-Client-Id": clientId },
       body: JSON.stringify({ delta }),
     });
-    if (!res.ok) throw new Error(await res.text());
+    if (!res.ok) {
+      const err = new Error(await res.text());
+      // 429 carries the number of seconds until the chart accepts stars again
+      if (res.status === 429) {
+        err.retryAfter = parseInt(res.headers.get("Retry-After") || "0", 10);
+      }
+      throw err;
+    }
+    // 202 means the star is waiting for a parent to approve it
+    return await res.json();
   }
 
   function showConfettiAtElement(el) {
@@ -1,12 +1,13 @@
 use crate::data::configuration::Configuration;
 use crate::data::dbconnector::{HGDBConnection, SQLConnector};
 use crate::server;
-use clap::Parser;
-use log::{debug, info};
-use sea_orm::sea_query::raw_sql::seaql::debug;
+use crate::server::types::InstantiateTemplateRequest;
+use clap::{ArgGroup, Parser};
+use log::{debug, error, info};
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
+#[command(group(ArgGroup::new("for_person").args(["instantiate_template", "import_ics"])))]
 struct Args {
     #[arg(short, long, default_value_t = true)]
     server: bool,
@@ -14,6 +15,21 @@ struct Args {
     port: u16,
     #[arg(short, long, default_value_t = String::from("configuration.toml"))]
     configuration_path: String,
+    /// Create charts from this template and exit instead of serving
+    #[arg(long)]
+    instantiate_template: Option<String>,
+    /// Person to instantiate the template or import the events for
+    #[arg(long, requires = "for_person", conflicts_with = "children_of")]
+    person: Option<String>,
+    /// Instantiate the template for every child of this parent
+    #[arg(long, requires = "instantiate_template")]
+    children_of: Option<String>,
+    /// Push the current template settings to its charts and exit
+    #[arg(long)]
+    propagate_template: Option<String>,
+    /// Import the events of this .ics file for --person and exit
+    #[arg(long, requires = "person", conflicts_with = "instantiate_template")]
+    import_ics: Option<String>,
 }
 
 pub async fn run() {
@@ -28,7 +44,7 @@ pub async fn run() {
     let config = Configuration::load(args.configuration_path).unwrap();
     debug!("Loaded configuration: {config:?}");
     // Check the database connection
-    let mut db_connector = SQLConnector::new(&config.database.path);
+    let mut db_connector = SQLConnector::new(&config.database.path).with_configuration(&config);
     db_connector.connect().await.unwrap();
     db_connector.check().await.unwrap();
     // Has database been initialised ?
@@ -38,6 +54,80 @@ pub async fn run() {
         info!("Database is not initialized, performing initialization");
         db_connector.initialize(&config).await.unwrap();
     }
+    if let Some(key) = &args.instantiate_template {
+        let Some(first_name) = args.person.as_ref().or(args.children_of.as_ref()) else {
+            error!("--instantiate-template needs --person or --children-of");
+            return;
+        };
+        let Some(person) = db_connector.get_person(first_name).await.unwrap() else {
+            error!("No person named {}", first_name);
+            return;
+        };
+        let request = if args.person.is_some() {
+            InstantiateTemplateRequest {
+                person_id: Some(person.id),
+                parent_id: None,
+            }
+        } else {
+            InstantiateTemplateRequest {
+                person_id: None,
+                parent_id: Some(person.id),
+            }
+        };
+        match db_connector.instantiate_template(key, &request).await {
+            Ok(resp) => info!(
+                "Created star charts {:?}, skipped people {:?}",
+                resp.star_chart_ids, resp.skipped_person_ids
+            ),
+            Err(e) => error!("Failed to instantiate template {}: {}", key, e),
+        }
+        return;
+    }
+    if let Some(key) = &args.propagate_template {
+        match db_connector.propagate_template(key).await {
+            Ok(resp) => info!("Updated star charts {:?}", resp.star_chart_ids),
+            Err(e) => error!("Failed to propagate template {}: {}", key, e),
+        }
+        return;
+    }
+    if let Some(path) = &args.import_ics {
+        let first_name = args.person.as_deref().unwrap_or_default();
+        let Some(person) = db_connector.get_person(first_name).await.unwrap() else {
+            error!("No person named {}", first_name);
+            return;
+        };
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) => {
+                error!("Failed to read {}: {}", path, e);
+                return;
+            }
+        };
+        match db_connector.import_calendar(person.id, &text).await {
+            Ok(resp) => {
+                info!(
+                    "Imported {}: {} created, {} updated, {} skipped",
+                    path,
+                    resp.created.len(),
+                    resp.updated.len(),
+                    resp.skipped.len()
+                );
+                for skipped in resp.skipped.iter().filter(|s| s.reason != "unchanged") {
+                    info!(
+                        "Skipped {}: {}",
+                        skipped
+                            .title
+                            .as_deref()
+                            .or(skipped.uid.as_deref())
+                            .unwrap_or("event"),
+                        skipped.reason
+                    );
+                }
+            }
+            Err(e) => error!("Failed to import {}: {}", path, e),
+        }
+        return;
+    }
     // Start the server
     server::run(config, db_connector, args.port).await;
 }
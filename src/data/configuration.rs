@@ -1,17 +1,44 @@
+use crate::entity::star_charts::Recurrence;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 #[derive(Deserialize, Serialize, Default, Debug, Clone)]
 pub struct Configuration {
     pub database: DatabaseConfig,
     pub family: HashMap<String, Family>,
+    #[serde(default)]
+    pub limits: LimitsConfig,
+    #[serde(default)]
+    pub achievements: HashMap<String, AchievementConfig>,
+    #[serde(default)]
+    pub templates: HashMap<String, TemplateConfig>,
+    #[serde(default)]
+    pub chores: ChoresConfig,
+    #[serde(default)]
+    pub levels: LevelsConfig,
+    #[serde(default)]
+    pub archive: ArchiveConfig,
+    #[serde(default)]
+    pub transfers: TransfersConfig,
+    #[serde(default)]
+    pub deadlines: DeadlinesConfig,
+    #[serde(default)]
+    pub calendar: CalendarConfig,
 }
 
 impl Configuration {
     pub fn load(configuration_path: String) -> Result<Self, Box<dyn std::error::Error>> {
         let config = std::fs::read_to_string(&configuration_path)?;
         let cfg: Self = toml::from_str(&config)?;
+        LimitsConfig::check(cfg.limits.cooldown_seconds, cfg.limits.daily_cap)?;
         Ok(cfg)
     }
+
+    /// The family member whose PIN this is
+    pub fn member_with_pin(&self, pin: &str) -> Option<&Family> {
+        self.family
+            .values()
+            .find(|f| f.pin.as_deref().is_some_and(|p| !p.is_empty() && p == pin))
+    }
 }
 
 #[derive(Deserialize, Serialize, Default, Debug, Clone)]
@@ -19,6 +46,140 @@ pub struct DatabaseConfig {
     pub path: String,
 }
 
+/// Defaults for star increments, individual charts can override them
+#[derive(Deserialize, Serialize, Default, Debug, Clone)]
+pub struct LimitsConfig {
+    /// Minimum seconds between two increments of the same chart
+    pub cooldown_seconds: Option<i32>,
+    /// Maximum stars a chart can gain per day
+    pub daily_cap: Option<i32>,
+}
+
+impl LimitsConfig {
+    /// Both limits start at one, leaving one out is how to have none
+    pub fn check(cooldown_seconds: Option<i32>, daily_cap: Option<i32>) -> Result<(), String> {
+        if cooldown_seconds.is_some_and(|c| c < 1) {
+            return Err("cooldown_seconds must be at least 1".to_string());
+        }
+        if daily_cap.is_some_and(|c| c < 1) {
+            return Err("daily_cap must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct LevelsConfig {
+    /// Lifetime XP needed to reach level 2, 3, 4 and so on. Levels past the
+    /// end of the list each cost the last step again.
+    pub thresholds: Vec<i32>,
+}
+
+impl Default for LevelsConfig {
+    fn default() -> Self {
+        LevelsConfig {
+            thresholds: vec![10, 25, 50, 100, 175, 275, 400],
+        }
+    }
+}
+
+impl LevelsConfig {
+    /// The level for this much XP, with the XP at which it started and the
+    /// XP needed for the next one
+    pub fn level_for(&self, xp: i32) -> (i32, i32, i32) {
+        let mut level = 1;
+        let mut start = 0;
+        for threshold in &self.thresholds {
+            if xp < *threshold {
+                return (level, start, *threshold);
+            }
+            level += 1;
+            start = *threshold;
+        }
+
+        let step = match self.thresholds.as_slice() {
+            [.., before, last] => last - before,
+            [only] => *only,
+            [] => 10,
+        }
+        .max(1);
+        let extra = (xp - start) / step;
+        let start = start + extra * step;
+        (level + extra, start, start + step)
+    }
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone)]
+pub struct ChoresConfig {
+    /// How many days of assignments the scheduler keeps generated, 7 if unset
+    pub days_ahead: Option<u32>,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone)]
+pub struct TransfersConfig {
+    /// Transfers asked for by anyone but a parent wait for a parent to approve
+    #[serde(default)]
+    pub requires_approval: bool,
+    /// Most stars a single transfer can move
+    pub max_stars: Option<i32>,
+    /// Most stars one person can give away per day
+    pub daily_limit: Option<i32>,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone)]
+pub struct DeadlinesConfig {
+    /// How many days ahead a deadline counts as due soon, 3 if unset
+    pub due_soon_days: Option<u32>,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone)]
+pub struct CalendarConfig {
+    /// IANA name of the zone event times are entered in, such as
+    /// "Europe/London"; the server won't start with a name it doesn't know.
+    /// Without it feeds leave times floating and calendar apps show them in
+    /// the phone's own zone.
+    pub timezone: Option<chrono_tz::Tz>,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Clone)]
+pub struct ArchiveConfig {
+    /// Days archived people and charts are kept before being purged, 30 if
+    /// unset
+    pub retention_days: Option<u32>,
+}
+
+/// A badge a person unlocks once their progress reaches the threshold
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct AchievementConfig {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub kind: AchievementKind,
+    pub threshold: i32,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AchievementKind {
+    /// Stars earned across all charts
+    TotalStars,
+    /// Best streak on any single chart
+    Streak,
+    /// Charts (or recurring periods) that reached their goal
+    ChartsCompleted,
+}
+
+/// A star chart that can be created for people on demand
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct TemplateConfig {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub star_total: i32,
+    pub color: Option<String>,
+    pub recurrence: Option<Recurrence>,
+}
+
 #[derive(Deserialize, Serialize, Default, Debug, Clone)]
 pub struct Family {
     pub first_name: String,
@@ -26,4 +187,41 @@ pub struct Family {
     pub age: u32,
     #[serde(default)]
     pub children: Vec<String>,
+    /// Sent in the X-Pin header to act as this person. Names in request
+    /// bodies aren't trusted, so parents need one to approve anything.
+    pub pin: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn levels(thresholds: &[i32]) -> LevelsConfig {
+        LevelsConfig {
+            thresholds: thresholds.to_vec(),
+        }
+    }
+
+    #[test]
+    fn level_for_follows_thresholds() {
+        let levels = levels(&[10, 25, 50]);
+        assert_eq!(levels.level_for(0), (1, 0, 10));
+        assert_eq!(levels.level_for(9), (1, 0, 10));
+        assert_eq!(levels.level_for(10), (2, 10, 25));
+        assert_eq!(levels.level_for(49), (3, 25, 50));
+    }
+
+    #[test]
+    fn level_for_repeats_the_last_step() {
+        let levels = levels(&[10, 25, 50]);
+        assert_eq!(levels.level_for(50), (4, 50, 75));
+        assert_eq!(levels.level_for(74), (4, 50, 75));
+        assert_eq!(levels.level_for(130), (7, 125, 150));
+    }
+
+    #[test]
+    fn level_for_short_or_empty_thresholds() {
+        assert_eq!(levels(&[5]).level_for(12), (3, 10, 15));
+        assert_eq!(levels(&[]).level_for(25), (3, 20, 30));
+    }
 }
@@ -1,11 +1,18 @@
-use crate::data::configuration::Configuration;
+use crate::data::configuration::{AchievementKind, Configuration, LimitsConfig};
+use crate::data::errors::HGError;
+use crate::entity::star_charts::{ChartStatus, DeadlinePolicy, OverflowPolicy};
+use crate::entity::star_events::StarEventKind;
+use crate::entity::undo_entries::UndoAction;
 use crate::server::types::*;
 use anyhow::Context;
+use log::warn;
+use sea_orm::sea_query::Expr;
 use sea_orm::{
-    ActiveModelTrait, ActiveValue::Set, ColumnTrait, Database, DatabaseConnection, EntityTrait,
-    QueryFilter,
+    ActiveEnum, ActiveModelTrait, ActiveValue::Set, ColumnTrait, ConnectionTrait, Database,
+    DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, TransactionTrait,
 };
 use chrono::Utc;
+use std::collections::HashMap;
 
 pub(crate) trait HGDBConnection {
     async fn connect(&mut self) -> Result<(), anyhow::Error>;
@@ -30,25 +37,48 @@ pub(crate) trait HGDBConnection {
         &self,
         star_chart_id: i32,
     ) -> Result<Option<GetStarChartResponse>, anyhow::Error>;
-    async fn get_star_charts(&self) -> Result<Vec<GetStarChartResponse>, anyhow::Error>;
+    async fn get_star_charts(
+        &self,
+        query: &ListStarChartsQuery,
+    ) -> Result<Vec<GetStarChartResponse>, anyhow::Error>;
     async fn update_star_chart(
         &self,
         star_chart_id: i32,
         star_chart: &UpdateStarChartRequest,
+        expected_version: Option<i32>,
+        client: Option<&str>,
     ) -> Result<UpdateStarChartResponse, anyhow::Error>;
     async fn delete_star_chart(&self, star_chart_id: i32) -> Result<(), anyhow::Error>;
     async fn delete_person(&self, person_id: i32) -> Result<(), anyhow::Error>;
-    async fn get_all_people(&self) -> Result<Vec<PersonListItem>, anyhow::Error>;
+    async fn archive_star_chart(&self, star_chart_id: i32) -> Result<(), anyhow::Error>;
+    async fn restore_star_chart(&self, star_chart_id: i32) -> Result<(), anyhow::Error>;
+    async fn archive_person(&self, person_id: i32) -> Result<(), anyhow::Error>;
+    async fn restore_person(&self, person_id: i32) -> Result<(), anyhow::Error>;
+    async fn get_all_people(
+        &self,
+        include_archived: bool,
+    ) -> Result<Vec<PersonListItem>, anyhow::Error>;
     async fn increment_star_chart(
         &self,
         star_chart_id: i32,
-        delta: i32,
-    ) -> Result<UpdateStarChartResponse, anyhow::Error>;
+        increment: &IncrementStarChartRequest,
+        client: Option<&str>,
+        caller: Option<&Caller>,
+    ) -> Result<IncrementStarChartResponse, anyhow::Error>;
+    async fn get_star_chart_history(
+        &self,
+        star_chart_id: i32,
+    ) -> Result<Option<Vec<StarEventResponse>>, anyhow::Error>;
+    async fn get_person_history(
+        &self,
+        first_name: &str,
+    ) -> Result<Option<Vec<StarEventResponse>>, anyhow::Error>;
 }
 
 pub struct SQLConnector {
     path: String,
     database_connection: Option<DatabaseConnection>,
+    configuration: Configuration,
 }
 
 impl SQLConnector {
@@ -56,21 +86,100 @@ impl SQLConnector {
         SQLConnector {
             path: path.to_string(),
             database_connection: None,
+            configuration: Configuration::default(),
         }
     }
+
+    /// Use the loaded configuration for policies such as increment limits
+    pub fn with_configuration(mut self, configuration: &Configuration) -> Self {
+        self.configuration = configuration.clone();
+        self
+    }
 }
 
 impl HGDBConnection for SQLConnector {
     async fn connect(&mut self) -> Result<(), anyhow::Error> {
         let db =
             Database::connect(format!("sqlite://{}/db.sqlite?mode=rwc", self.path.clone())).await?;
+        // Each level is reached once; concurrent increments used to record
+        // some twice, which the unique key would now refuse
+        drop_duplicates(&db, "level_ups", r#""person_id", "level""#).await?;
+        // Same for badges, which are unlocked after the change that earned them
+        drop_duplicates(&db, "achievements", r#""person_id", "badge_key""#).await?;
         db.get_schema_builder()
             .register(crate::entity::calendar_events::Entity)
+            .register(crate::entity::calendar_changes::Entity)
             .register(crate::entity::people::Entity)
             .register(crate::entity::person_parent::Entity)
             .register(crate::entity::star_charts::Entity)
+            .register(crate::entity::star_events::Entity)
+            .register(crate::entity::rewards::Entity)
+            .register(crate::entity::reward_redemptions::Entity)
+            .register(crate::entity::star_chart_periods::Entity)
+            .register(crate::entity::star_approvals::Entity)
+            .register(crate::entity::star_chart_prerequisites::Entity)
+            .register(crate::entity::achievements::Entity)
+            .register(crate::entity::allowance_entries::Entity)
+            .register(crate::entity::chores::Entity)
+            .register(crate::entity::chore_assignments::Entity)
+            .register(crate::entity::bounties::Entity)
+            .register(crate::entity::level_ups::Entity)
+            .register(crate::entity::goals::Entity)
+            .register(crate::entity::goal_contributions::Entity)
+            .register(crate::entity::undo_entries::Entity)
+            .register(crate::entity::star_transfers::Entity)
+            .register(crate::entity::bonus_windows::Entity)
+            .register(crate::entity::schema_migrations::Entity)
             .sync(&db)
             .await?;
+        // XP arrived after star events; once, give everyone the stars they
+        // earned before it existed. Edits, resets, spending and gifts never
+        // earned any.
+        run_migration(
+            &db,
+            "xp_backfill",
+            r#"UPDATE "people" SET "xp" = (
+                SELECT MAX(COALESCE(SUM("star_events"."delta"), 0), 0) FROM "star_events"
+                JOIN "star_charts" ON "star_charts"."id" = "star_events"."star_chart_id"
+                WHERE "star_charts"."person_id" = "people"."id"
+                    AND COALESCE("star_events"."note", '') NOT IN ('manual adjustment', 'period reset')
+                    AND COALESCE("star_events"."note", '') NOT LIKE 'template % updated'
+                    AND COALESCE("star_events"."note", '') NOT LIKE 'redeemed %'
+                    AND COALESCE("star_events"."note", '') NOT LIKE 'gift %'
+            ) WHERE "xp" = 0"#,
+        )
+        .await?;
+        // Archiving used to be a chart status; it is only archived_at now, so
+        // those charts can be restored and purged like any other
+        run_migration(
+            &db,
+            "archived_status",
+            r#"UPDATE "star_charts" SET
+                "archived_at" = COALESCE("archived_at", "updated_at"),
+                "status" = CASE WHEN "completed_at" IS NULL THEN 'active' ELSE 'completed' END
+            WHERE "status" = 'archived'"#,
+        )
+        .await?;
+        // Events used to be told apart by their notes; give the old ones the
+        // kind their note implies, the rest stay increments
+        run_migration(
+            &db,
+            "star_event_kinds",
+            r#"UPDATE "star_events" SET "kind" = CASE
+                WHEN "note" = 'manual adjustment' OR "note" LIKE 'template % updated'
+                    THEN 'adjustment'
+                WHEN "note" = 'period reset' THEN 'reset'
+                WHEN "note" LIKE 'redeemed %' THEN 'redemption'
+                WHEN "note" LIKE 'gift %' THEN 'gift'
+                WHEN "note" LIKE 'bounty %' THEN 'bounty'
+                WHEN "note" LIKE 'goal %' OR "note" LIKE 'back from goal %' THEN 'goal'
+                WHEN "note" IN ('undo', 'redo') THEN 'undo'
+                ELSE 'increment'
+            END"#,
+        )
+        .await?;
+        // The configured timezone changes how every event is written out
+        touch_calendar(&db).await?;
         self.database_connection = Some(db);
         Ok(())
     }
@@ -126,29 +235,24 @@ impl HGDBConnection for SQLConnector {
                 .await?
             {
                 for child_name in &member.children {
-                    match crate::entity::people::Entity::find()
+                    if let Some(child) = crate::entity::people::Entity::find()
                         .filter(crate::entity::people::Column::FirstName.eq(child_name.clone()))
                         .one(db)
                         .await?
                     {
-                        Some(child) => {
-                            let exists = crate::entity::person_parent::Entity::find()
-                                .filter(
-                                    crate::entity::person_parent::Column::ParentId.eq(parent.id),
-                                )
-                                .filter(crate::entity::person_parent::Column::ChildId.eq(child.id))
-                                .one(db)
-                                .await?;
-                            if exists.is_none() {
-                                crate::entity::helpers::add_parent_child(db, parent.id, child.id)
-                                    .await
-                                    .context(format!(
-                                        "failed to add parent-child link {} -> {}",
-                                        parent.id, child.id
-                                    ))?;
-                            }
+                        let exists = crate::entity::person_parent::Entity::find()
+                            .filter(crate::entity::person_parent::Column::ParentId.eq(parent.id))
+                            .filter(crate::entity::person_parent::Column::ChildId.eq(child.id))
+                            .one(db)
+                            .await?;
+                        if exists.is_none() {
+                            crate::entity::helpers::add_parent_child(db, parent.id, child.id)
+                                .await
+                                .context(format!(
+                                    "failed to add parent-child link {} -> {}",
+                                    parent.id, child.id
+                                ))?;
                         }
-                        None => {}
                     }
                 }
             }
@@ -163,51 +267,63 @@ impl HGDBConnection for SQLConnector {
     ) -> Result<Option<GetPersonResponse>, anyhow::Error> {
         let person: Option<crate::entity::people::Model> = crate::entity::people::Entity::find()
             .filter(crate::entity::people::Column::FirstName.eq(first_name))
+            .filter(crate::entity::people::Column::ArchivedAt.is_null())
             .one(self.database_connection.as_ref().unwrap())
             .await?;
         let db = self.database_connection.as_ref().unwrap();
         if let Some(p) = person {
-            let kids_models = match crate::entity::helpers::children_of(db, p.id).await {
-                Ok(k) => k,
-                Err(_) => Vec::new(),
-            };
+            let kids_models = crate::entity::helpers::children_of(db, p.id)
+                .await
+                .unwrap_or_default();
 
             let found_children = kids_models
                 .into_iter()
-                .map(|k| GetPersonResponse {
-                    id: k.id,
-                    first_name: k.first_name,
-                    last_name: k.last_name,
-                    children: Vec::new(),
-                    star_charts: Vec::new(),
+                .filter(|k| k.archived_at.is_none())
+                .map(|k| {
+                    let (level, next_level_xp, level_progress) = self.level_progress(k.xp);
+                    GetPersonResponse {
+                        id: k.id,
+                        first_name: k.first_name,
+                        last_name: k.last_name,
+                        children: Vec::new(),
+                        star_charts: Vec::new(),
+                        badges: Vec::new(),
+                        level,
+                        xp: k.xp,
+                        next_level_xp,
+                        level_progress,
+                    }
                 })
                 .collect();
 
             let charts = crate::entity::star_charts::Entity::find()
                 .filter(crate::entity::star_charts::Column::PersonId.eq(p.id))
+                .filter(crate::entity::star_charts::Column::ArchivedAt.is_null())
                 .all(db)
                 .await?;
 
+            let streaks = load_streaks(db, &charts).await?;
             let found_charts = charts
                 .into_iter()
-                .map(|c| GetStarChartResponse {
-                    id: c.id,
-                    name: c.chart_type,
-                    description: c.chart_key,
-                    star_count: c.star_count,
-                    star_total: c.star_total,
-                    color: c.color.clone(),
-                    person_first_name: p.first_name.clone(),
-                    person_last_name: p.last_name.clone(),
+                .map(|c| {
+                    star_chart_response(c, p.first_name.clone(), p.last_name.clone(), &streaks)
                 })
                 .collect();
 
+            let badges = self.earned_badges(p.id).await?;
+            let (level, next_level_xp, level_progress) = self.level_progress(p.xp);
+
             Ok(Some(GetPersonResponse {
                 id: p.id,
                 first_name: p.first_name,
                 last_name: p.last_name,
                 children: found_children,
                 star_charts: found_charts,
+                badges,
+                level,
+                xp: p.xp,
+                next_level_xp,
+                level_progress,
             }))
         } else {
             Ok(None)
@@ -216,6 +332,7 @@ impl HGDBConnection for SQLConnector {
 
     async fn get_people(&self) -> Result<Vec<String>, anyhow::Error> {
         let people = crate::entity::people::Entity::find()
+            .filter(crate::entity::people::Column::ArchivedAt.is_null())
             .all(self.database_connection.as_ref().unwrap())
             .await?;
 
@@ -265,57 +382,36 @@ impl HGDBConnection for SQLConnector {
         &self,
         star_chart: &CreateStarChartRequest,
     ) -> Result<CreateStarChartResponse, anyhow::Error> {
-        let star_chart = star_chart.to_owned();
         let db = self.database_connection.as_ref().unwrap();
-        let person = crate::entity::people::Entity::find_by_id(star_chart.person_id)
-            .one(db)
-            .await?;
-        anyhow::ensure!(
-            person.is_some(),
-            "person {} not found",
-            star_chart.person_id
-        );
-
-        let now = chrono::Utc::now();
-        // determine chart color: use provided or pick from palette
-        let palette = [
-            "#ff8a65", "#ffd54f", "#81c784", "#64b5f6", "#e57373", "#ba68c8", "#4db6ac",
-            "#ffb74d", "#aed581", "#90caf9",
-        ];
-        let chosen_color = if let Some(c) = star_chart.color.clone() {
-            c
-        } else {
-            let idx = (Utc::now().timestamp_millis() as usize) % palette.len();
-            palette[idx].to_string()
-        };
-
-        let new_star_chart = crate::entity::star_charts::ActiveModel {
-            person_id: Set(star_chart.person_id),
-            chart_type: Set(star_chart.name.clone()),
-            chart_key: Set(star_chart.description.clone()),
-            star_total: Set(star_chart.star_total),
-            star_count: Set(star_chart.star_count),
-            color: Set(Some(chosen_color)),
-            created_at: Set(now),
-            updated_at: Set(now),
-            ..Default::default()
-        };
-
-        let result = crate::entity::star_charts::Entity::insert(new_star_chart)
-            .exec(db)
-            .await?;
+        let id = insert_star_chart(db, star_chart, None).await?;
 
-        Ok(CreateStarChartResponse {
-            id: result.last_insert_id,
-        })
+        Ok(CreateStarChartResponse { id })
     }
 
-    async fn get_star_charts(&self) -> Result<Vec<GetStarChartResponse>, anyhow::Error> {
-        let charts = crate::entity::star_charts::Entity::find()
+    async fn get_star_charts(
+        &self,
+        query: &ListStarChartsQuery,
+    ) -> Result<Vec<GetStarChartResponse>, anyhow::Error> {
+        use crate::entity::star_charts;
+
+        let mut select = star_charts::Entity::find();
+        if !query.include_archived {
+            select = select.filter(star_charts::Column::ArchivedAt.is_null());
+        }
+        if let Some(days) = query.due_within_days {
+            let until = Utc::now() + chrono::Duration::days(i64::from(days));
+            select = select
+                .filter(star_charts::Column::Status.eq(ChartStatus::Active))
+                .filter(star_charts::Column::DueAt.gt(Utc::now()))
+                .filter(star_charts::Column::DueAt.lte(until))
+                .order_by_asc(star_charts::Column::DueAt);
+        }
+        let charts = select
             .all(self.database_connection.as_ref().unwrap())
             .await?;
 
         let db = self.database_connection.as_ref().unwrap();
+        let streaks = load_streaks(db, &charts).await?;
         let mut results: Vec<GetStarChartResponse> = Vec::new();
         for c in charts.into_iter() {
             let person = crate::entity::people::Entity::find_by_id(c.person_id)
@@ -325,16 +421,7 @@ impl HGDBConnection for SQLConnector {
                 Some(p) => (p.first_name, p.last_name),
                 None => ("".to_string(), "".to_string()),
             };
-            results.push(GetStarChartResponse {
-                id: c.id,
-                name: c.chart_type.clone(),
-                description: c.chart_key.clone(),
-                star_count: c.star_count,
-                star_total: c.star_total,
-                color: c.color.clone(),
-                person_first_name: pf,
-                person_last_name: pl,
-            });
+            results.push(star_chart_response(c, pf, pl, &streaks));
         }
 
         Ok(results)
@@ -343,9 +430,14 @@ impl HGDBConnection for SQLConnector {
     async fn delete_star_chart(&self, star_chart_id: i32) -> Result<(), anyhow::Error> {
         use crate::entity::star_charts;
         let db = self.database_connection.as_ref().unwrap();
+        let txn = db.begin().await?;
+        // The links go with the chart, so find what it held back first
+        let dependents = dependent_charts(&txn, star_chart_id).await?;
         let _res = star_charts::Entity::delete_by_id(star_chart_id)
-            .exec(db)
+            .exec(&txn)
             .await?;
+        unlock_charts(&txn, &dependents).await?;
+        txn.commit().await?;
         Ok(())
     }
 
@@ -368,18 +460,158 @@ impl HGDBConnection for SQLConnector {
             .await?;
 
         let _ = people::Entity::delete_by_id(person_id).exec(db).await?;
+        // Their calendar events went with them
+        touch_calendar(db).await?;
+        Ok(())
+    }
+
+    async fn archive_star_chart(&self, star_chart_id: i32) -> Result<(), anyhow::Error> {
+        use crate::entity::star_charts;
+        let db = self.database_connection.as_ref().unwrap();
+
+        let chart = star_charts::Entity::find_by_id(star_chart_id)
+            .one(db)
+            .await?
+            .ok_or_else(|| HGError::NotFound(format!("star chart {} not found", star_chart_id)))?;
+        if chart.archived_at.is_some() {
+            return Ok(());
+        }
+        let txn = db.begin().await?;
+        star_charts::ActiveModel {
+            id: Set(chart.id),
+            archived_at: Set(Some(Utc::now())),
+            ..Default::default()
+        }
+        .update(&txn)
+        .await?;
+        // An archived requirement no longer holds its dependents back
+        unlock_quests(&txn, chart.id).await?;
+        txn.commit().await?;
+        Ok(())
+    }
+
+    async fn restore_star_chart(&self, star_chart_id: i32) -> Result<(), anyhow::Error> {
+        use crate::entity::{people, star_charts};
+        let db = self.database_connection.as_ref().unwrap();
+
+        let chart = star_charts::Entity::find_by_id(star_chart_id)
+            .one(db)
+            .await?
+            .ok_or_else(|| HGError::NotFound(format!("star chart {} not found", star_chart_id)))?;
+        let owner_archived = people::Entity::find_by_id(chart.person_id)
+            .one(db)
+            .await?
+            .is_some_and(|p| p.archived_at.is_some());
+        if owner_archived {
+            return Err(HGError::Conflict(format!(
+                "the owner of star chart {} is archived, restore them first",
+                star_chart_id
+            ))
+            .into());
+        }
+        let txn = db.begin().await?;
+        star_charts::ActiveModel {
+            id: Set(chart.id),
+            archived_at: Set(None),
+            ..Default::default()
+        }
+        .update(&txn)
+        .await?;
+        relock_quests(&txn, chart.id).await?;
+        txn.commit().await?;
+        Ok(())
+    }
+
+    /// Archive a person together with their live charts. The charts share
+    /// the person's timestamp so restoring brings back exactly those.
+    async fn archive_person(&self, person_id: i32) -> Result<(), anyhow::Error> {
+        use crate::entity::{people, star_charts};
+        let db = self.database_connection.as_ref().unwrap();
+
+        let person = people::Entity::find_by_id(person_id)
+            .one(db)
+            .await?
+            .ok_or_else(|| HGError::NotFound(format!("person {} not found", person_id)))?;
+        if person.archived_at.is_some() {
+            return Ok(());
+        }
+
+        let now = Utc::now();
+        let txn = db.begin().await?;
+        star_charts::Entity::update_many()
+            .col_expr(star_charts::Column::ArchivedAt, Expr::value(now))
+            .filter(star_charts::Column::PersonId.eq(person_id))
+            .filter(star_charts::Column::ArchivedAt.is_null())
+            .exec(&txn)
+            .await?;
+        people::ActiveModel {
+            id: Set(person_id),
+            archived_at: Set(Some(now)),
+            ..Default::default()
+        }
+        .update(&txn)
+        .await?;
+        txn.commit().await?;
+        Ok(())
+    }
+
+    async fn restore_person(&self, person_id: i32) -> Result<(), anyhow::Error> {
+        use crate::entity::{people, star_charts};
+        let db = self.database_connection.as_ref().unwrap();
+
+        let person = people::Entity::find_by_id(person_id)
+            .one(db)
+            .await?
+            .ok_or_else(|| HGError::NotFound(format!("person {} not found", person_id)))?;
+        let Some(archived_at) = person.archived_at else {
+            return Ok(());
+        };
+
+        let txn = db.begin().await?;
+        star_charts::Entity::update_many()
+            .col_expr(
+                star_charts::Column::ArchivedAt,
+                Expr::value(Option::<chrono::DateTime<Utc>>::None),
+            )
+            .filter(star_charts::Column::PersonId.eq(person_id))
+            .filter(star_charts::Column::ArchivedAt.eq(archived_at))
+            .exec(&txn)
+            .await?;
+        people::ActiveModel {
+            id: Set(person_id),
+            archived_at: Set(None),
+            ..Default::default()
+        }
+        .update(&txn)
+        .await?;
+        txn.commit().await?;
         Ok(())
     }
 
-    async fn get_all_people(&self) -> Result<Vec<PersonListItem>, anyhow::Error> {
+    async fn get_all_people(
+        &self,
+        include_archived: bool,
+    ) -> Result<Vec<PersonListItem>, anyhow::Error> {
         let db = self.database_connection.as_ref().unwrap();
-        let people = crate::entity::people::Entity::find().all(db).await?;
+        let mut select = crate::entity::people::Entity::find();
+        if !include_archived {
+            select = select.filter(crate::entity::people::Column::ArchivedAt.is_null());
+        }
+        let people = select.all(db).await?;
         let results = people
             .into_iter()
-            .map(|p| PersonListItem {
-                id: p.id,
-                first_name: p.first_name,
-                last_name: p.last_name,
+            .map(|p| {
+                let (level, next_level_xp, level_progress) = self.level_progress(p.xp);
+                PersonListItem {
+                    id: p.id,
+                    first_name: p.first_name,
+                    last_name: p.last_name,
+                    level,
+                    xp: p.xp,
+                    next_level_xp,
+                    level_progress,
+                    archived_at: p.archived_at.map(|d| d.to_rfc3339()),
+                }
             })
             .collect();
         Ok(results)
@@ -402,16 +634,8 @@ impl HGDBConnection for SQLConnector {
                 Some(p) => (p.first_name, p.last_name),
                 None => ("".to_string(), "".to_string()),
             };
-            Ok(Some(GetStarChartResponse {
-                id: c.id,
-                name: c.chart_type,
-                description: c.chart_key,
-                star_count: c.star_count,
-                star_total: c.star_total,
-                color: c.color.clone(),
-                person_first_name: pf,
-                person_last_name: pl,
-            }))
+            let streaks = load_streaks(db, std::slice::from_ref(&c)).await?;
+            Ok(Some(star_chart_response(c, pf, pl, &streaks)))
         } else {
             Ok(None)
         }
@@ -421,131 +645,5454 @@ impl HGDBConnection for SQLConnector {
         &self,
         star_chart_id: i32,
         star_chart: &UpdateStarChartRequest,
+        expected_version: Option<i32>,
+        client: Option<&str>,
     ) -> Result<UpdateStarChartResponse, anyhow::Error> {
-        use crate::entity::star_charts;
+        use crate::entity::{star_chart_periods, star_chart_prerequisites, star_charts};
 
         let db = self.database_connection.as_ref().unwrap();
+        let txn = db.begin().await?;
 
         let existing = star_charts::Entity::find_by_id(star_chart_id)
-            .one(db)
+            .one(&txn)
             .await?;
-        anyhow::ensure!(existing.is_some(), "star chart {} not found", star_chart_id);
+        let Some(existing_model) = existing else {
+            return Err(
+                HGError::NotFound(format!("star chart {} not found", star_chart_id)).into(),
+            );
+        };
+        // Without If-Match the edit applies to whatever version we just read
+        let version = expected_version.unwrap_or(existing_model.version);
+        if version != existing_model.version {
+            return Err(HGError::VersionMismatch(format!(
+                "star chart {} is at version {}, not {}",
+                star_chart_id, existing_model.version, version
+            ))
+            .into());
+        }
+        let now = Utc::now();
+        // Switching a recurring chart to another rhythm, or to none, closes
+        // the period it is in, just like the period ending would
+        let closes_period = existing_model.recurrence.is_some()
+            && star_chart
+                .recurrence
+                .is_some_and(|r| r != existing_model.recurrence);
+        if closes_period {
+            star_chart_periods::ActiveModel {
+                star_chart_id: Set(existing_model.id),
+                period_start: Set(existing_model
+                    .period_started_at
+                    .unwrap_or(existing_model.created_at)),
+                period_end: Set(now),
+                star_count: Set(existing_model.star_count),
+                star_total: Set(existing_model.star_total),
+                ..Default::default()
+            }
+            .insert(&txn)
+            .await?;
+            if existing_model.star_count != 0 {
+                crate::entity::helpers::record_star_event(
+                    &txn,
+                    existing_model.id,
+                    -existing_model.star_count,
+                    StarEventKind::Reset,
+                    Some("period reset".to_string()),
+                    None,
+                )
+                .await?;
+            }
+        }
+        let previous_count = if closes_period {
+            0
+        } else {
+            existing_model.star_count
+        };
+        let star_total = star_chart.star_total.unwrap_or(existing_model.star_total);
+        let overflow_policy = star_chart
+            .overflow_policy
+            .unwrap_or(existing_model.overflow_policy);
+        let star_count =
+            overflow_policy.bound(star_chart.star_count.unwrap_or(previous_count), star_total);
+        let status = match star_chart.status {
+            // A quest only opens once the charts it requires are completed
+            Some(ChartStatus::Active) if existing_model.status == ChartStatus::Locked => {
+                let requires: Vec<i32> = star_chart_prerequisites::Entity::find()
+                    .filter(star_chart_prerequisites::Column::StarChartId.eq(existing_model.id))
+                    .all(&txn)
+                    .await?
+                    .into_iter()
+                    .map(|l| l.requires_id)
+                    .collect();
+                if !prerequisites_met(&txn, existing_model.person_id, None, &requires).await? {
+                    return Err(HGError::Conflict(format!(
+                        "{} is locked until the charts it requires are completed",
+                        existing_model.chart_type
+                    ))
+                    .into());
+                }
+                ChartStatus::Active
+            }
+            // Reactivating lets set_star_count work out whether it is complete
+            Some(ChartStatus::Active) if !existing_model.status.takes_stars() => {
+                ChartStatus::Active
+            }
+            _ => existing_model.status,
+        };
 
-        let mut am: star_charts::ActiveModel = existing.unwrap().into();
+        let before = existing_model.clone();
+        let mut am: star_charts::ActiveModel = existing_model.into();
         am.chart_type = Set(star_chart.name.clone());
         am.chart_key = Set(star_chart.description.clone());
-        if let Some(sc) = star_chart.star_count {
-            am.star_count = Set(sc);
+        am.star_total = Set(star_total);
+        am.overflow_policy = Set(overflow_policy);
+        am.status = Set(status);
+        let completed = set_star_count(&mut am, &status, star_count, star_total);
+        if let Some(recurrence) = star_chart.recurrence.filter(|r| *r != before.recurrence) {
+            am.recurrence = Set(recurrence);
+            am.period_started_at = Set(match recurrence {
+                // The closed period ended just now, the new one takes over
+                Some(_) if closes_period => Some(now),
+                Some(r) => Some(r.period_start(now)),
+                None => None,
+            });
+        }
+        if let Some(ra) = star_chart.requires_approval {
+            am.requires_approval = Set(ra);
         }
-        if let Some(st) = star_chart.star_total {
-            am.star_total = Set(st);
+        LimitsConfig::check(star_chart.cooldown_seconds, star_chart.daily_cap)
+            .map_err(HGError::Invalid)?;
+        if let Some(cs) = star_chart.cooldown_seconds {
+            am.cooldown_seconds = Set(Some(cs));
+        }
+        if let Some(dc) = star_chart.daily_cap {
+            am.daily_cap = Set(Some(dc));
+        }
+        if let Some(cents) = star_chart.cents_per_star {
+            am.cents_per_star = Set(Some(cents));
+        }
+        let starts_at = match star_chart.starts_at.as_deref() {
+            Some("") => None,
+            Some(s) => Some(parse_deadline(s, false)?),
+            None => before.starts_at,
+        };
+        let due_at = match star_chart.due_at.as_deref() {
+            Some("") => None,
+            Some(s) => Some(parse_deadline(s, true)?),
+            None => before.due_at,
+        };
+        check_deadlines(starts_at, due_at)?;
+        // Reopened with its old deadline it would only expire again
+        if matches!(before.status, ChartStatus::Expired | ChartStatus::Failed)
+            && status == ChartStatus::Active
+            && due_at.is_some_and(|d| d <= Utc::now())
+        {
+            return Err(HGError::Invalid(format!(
+                "reopening {} needs a due_at in the future, or an empty one to clear it",
+                before.chart_type
+            ))
+            .into());
+        }
+        am.starts_at = Set(starts_at);
+        am.due_at = Set(due_at);
+        if let Some(policy) = star_chart.deadline_policy {
+            am.deadline_policy = Set(policy);
         }
 
-        let res = am.update(db).await?;
+        let res = save_star_chart(&txn, am, version).await?;
+
+        // A manual edit of the count is still a change to the ledgers
+        if res.star_count != previous_count {
+            crate::entity::helpers::record_star_event(
+                &txn,
+                res.id,
+                res.star_count - previous_count,
+                StarEventKind::Adjustment,
+                Some("manual adjustment".to_string()),
+                None,
+            )
+            .await?;
+            credit_allowance(&txn, &res, res.star_count - previous_count, None).await?;
+        }
+        if completed {
+            unlock_quests(&txn, res.id).await?;
+        }
+        record_undo(
+            &txn,
+            client,
+            UndoAction::UpdateChart,
+            res.id,
+            None,
+            Some(&before),
+            Some(&res),
+        )
+        .await?;
+        txn.commit().await?;
+        self.refresh_progress(res.person_id).await;
 
-        Ok(UpdateStarChartResponse { id: res.id })
+        Ok(UpdateStarChartResponse {
+            id: res.id,
+            version: res.version,
+        })
     }
 
     async fn increment_star_chart(
         &self,
         star_chart_id: i32,
-        delta: i32,
-    ) -> Result<UpdateStarChartResponse, anyhow::Error> {
-        self.increment_star_chart_internal(star_chart_id, delta)
+        increment: &IncrementStarChartRequest,
+        client: Option<&str>,
+        caller: Option<&Caller>,
+    ) -> Result<IncrementStarChartResponse, anyhow::Error> {
+        self.increment_star_chart_internal(star_chart_id, increment, client, caller)
             .await
     }
-}
 
-impl SQLConnector {
-    pub async fn increment_star_chart_internal(
+    async fn get_star_chart_history(
         &self,
         star_chart_id: i32,
-        delta: i32,
-    ) -> Result<UpdateStarChartResponse, anyhow::Error> {
-        use crate::entity::star_charts;
+    ) -> Result<Option<Vec<StarEventResponse>>, anyhow::Error> {
+        use crate::entity::{star_charts, star_events};
 
         let db = self.database_connection.as_ref().unwrap();
-        let existing = star_charts::Entity::find_by_id(star_chart_id)
+        let chart = star_charts::Entity::find_by_id(star_chart_id)
             .one(db)
             .await?;
-        anyhow::ensure!(existing.is_some(), "star chart {} not found", star_chart_id);
+        let Some(chart) = chart else {
+            return Ok(None);
+        };
 
-        let existing_model = existing.unwrap();
-        let mut am: star_charts::ActiveModel = existing_model.clone().into();
-        let new_count = existing_model.star_count + delta;
-        am.star_count = sea_orm::ActiveValue::Set(new_count);
+        let events = star_events::Entity::find()
+            .filter(star_events::Column::StarChartId.eq(chart.id))
+            .order_by_desc(star_events::Column::CreatedAt)
+            .all(db)
+            .await?;
 
-        let res = am.update(db).await?;
-        Ok(UpdateStarChartResponse { id: res.id })
+        Ok(Some(
+            events
+                .into_iter()
+                .map(|e| StarEventResponse {
+                    id: e.id,
+                    star_chart_id: e.star_chart_id,
+                    chart_name: chart.chart_type.clone(),
+                    delta: e.delta,
+                    kind: e.kind,
+                    note: e.note,
+                    actor: e.actor,
+                    created_at: e.created_at.to_rfc3339(),
+                })
+                .collect(),
+        ))
     }
-}
 
-impl SQLConnector {
-    pub async fn list_calendar_people(
+    async fn get_person_history(
         &self,
-    ) -> Result<Vec<crate::server::types::CalendarPersonResponse>, anyhow::Error> {
-        let db = self.database_connection.as_ref().unwrap();
-        let people = crate::entity::people::Entity::find().all(db).await?;
+        first_name: &str,
+    ) -> Result<Option<Vec<StarEventResponse>>, anyhow::Error> {
+        use crate::entity::{people, star_charts, star_events};
 
-        // Default color palette in case a person doesn't have a stored color
-        let default_palette = [
-            "#FF6B9D", "#4ECDC4", "#FFD93D", "#95E1D3", "#F38181", "#AA96DA", "#FCBAD3", "#A8E6CF",
-            "#FFD700", "#87CEEB", "#FF8C42", "#C77DFF",
-        ];
+        let db = self.database_connection.as_ref().unwrap();
+        let person = people::Entity::find()
+            .filter(people::Column::FirstName.eq(first_name))
+            .one(db)
+            .await?;
+        let Some(person) = person else {
+            return Ok(None);
+        };
 
-        let items = people
+        let charts: HashMap<i32, String> = star_charts::Entity::find()
+            .filter(star_charts::Column::PersonId.eq(person.id))
+            .all(db)
+            .await?
             .into_iter()
-            .map(|p| {
-                // Use stored color or fall back to palette based on ID
-                let color = p.calendar_color.clone().unwrap_or_else(|| {
-                    default_palette[(p.id as usize) % default_palette.len()].to_string()
-                });
-
-                // Make titles very clear for young children
-                let title = format!("📅 {}'s Calendar", p.first_name);
-                crate::server::types::CalendarPersonResponse {
-                    id: p.id,
-                    title,
-                    event_background_color: Some(color),
-                    event_text_color: Some("#000000".to_string()),
-                }
-            })
+            .map(|c| (c.id, c.chart_type))
             .collect();
-        Ok(items)
-    }
 
-    pub async fn list_calendar_events(
-        &self,
-    ) -> Result<Vec<crate::server::types::CalendarEventResponse>, anyhow::Error> {
-        let db = self.database_connection.as_ref().unwrap();
-        let events = crate::entity::calendar_events::Entity::find()
+        let events = star_events::Entity::find()
+            .filter(star_events::Column::StarChartId.is_in(charts.keys().copied()))
+            .order_by_desc(star_events::Column::CreatedAt)
             .all(db)
             .await?;
-        Ok(events
-            .into_iter()
-            .map(|event| crate::server::types::CalendarEventResponse {
-                id: event.id,
-                title: event.title,
-                start: event.start_time,
-                end: event.end_time,
-                resource_id: event.person_id,
-            })
-            .collect())
+
+        Ok(Some(
+            events
+                .into_iter()
+                .map(|e| StarEventResponse {
+                    id: e.id,
+                    star_chart_id: e.star_chart_id,
+                    chart_name: charts.get(&e.star_chart_id).cloned().unwrap_or_default(),
+                    delta: e.delta,
+                    kind: e.kind,
+                    note: e.note,
+                    actor: e.actor,
+                    created_at: e.created_at.to_rfc3339(),
+                })
+                .collect(),
+        ))
     }
+}
 
-    pub async fn create_calendar_event(
+impl SQLConnector {
+    pub async fn increment_star_chart_internal(
         &self,
-        payload: &crate::server::types::CreateCalendarEventRequest,
-    ) -> Result<crate::server::types::CreateCalendarEventResponse, anyhow::Error> {
+        star_chart_id: i32,
+        increment: &IncrementStarChartRequest,
+        client: Option<&str>,
+        caller: Option<&Caller>,
+    ) -> Result<IncrementStarChartResponse, anyhow::Error> {
+        // Another writer may get in between our read and the guarded update,
+        // in which case start again from a fresh read
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            match self
+                .try_increment_star_chart(star_chart_id, increment, client, caller)
+                .await
+            {
+                Err(e)
+                    if attempts < 3
+                        && matches!(e.downcast_ref(), Some(HGError::VersionMismatch(_))) => {}
+                result => return result,
+            }
+        }
+    }
+
+    async fn try_increment_star_chart(
+        &self,
+        star_chart_id: i32,
+        increment: &IncrementStarChartRequest,
+        client: Option<&str>,
+        caller: Option<&Caller>,
+    ) -> Result<IncrementStarChartResponse, anyhow::Error> {
+        use crate::entity::star_approvals::{self, ApprovalStatus};
+        use crate::entity::star_charts;
+
         let db = self.database_connection.as_ref().unwrap();
-        let event = crate::entity::calendar_events::ActiveModel {
-            person_id: Set(payload.person_id),
-            title: Set(payload.title.clone()),
-            start_time: Set(payload.start.clone()),
-            end_time: Set(payload.end.clone()),
-            ..Default::default()
+        // The count and its ledger entry are written together or not at all
+        let txn = db.begin().await?;
+        let existing = star_charts::Entity::find_by_id(star_chart_id)
+            .one(&txn)
+            .await?;
+        let Some(chart) = existing else {
+            return Err(
+                HGError::NotFound(format!("star chart {} not found", star_chart_id)).into(),
+            );
         };
-        let result = event.insert(db).await?;
-        Ok(crate::server::types::CreateCalendarEventResponse { id: result.id })
+        if chart.archived_at.is_some() {
+            return Err(HGError::Conflict(format!("{} is archived", chart.chart_type)).into());
+        }
+        if !chart.status.takes_stars() {
+            return Err(HGError::Conflict(format!(
+                "{} is {}",
+                chart.chart_type,
+                chart.status.to_value()
+            ))
+            .into());
+        }
+
+        // Only stars earned are multiplied, never ones taken back
+        let bonus = if increment.delta > 0 {
+            active_bonus(&txn, &chart).await?
+        } else {
+            None
+        };
+        let delta = increment
+            .delta
+            .checked_mul(bonus.as_ref().map_or(1, |b| b.multiplier))
+            .ok_or_else(|| HGError::Invalid(format!("delta {} is too large", increment.delta)))?;
+        let bonus = bonus.map(|b| AppliedBonus {
+            bonus_window_id: b.id,
+            name: b.name,
+            multiplier: b.multiplier,
+            base_delta: increment.delta,
+        });
+
+        let cooldown_seconds = chart
+            .cooldown_seconds
+            .or(self.configuration.limits.cooldown_seconds);
+        if delta > 0 {
+            let daily_cap = chart.daily_cap.or(self.configuration.limits.daily_cap);
+            check_increment_limits(&txn, &chart, delta, cooldown_seconds, daily_cap).await?;
+        }
+
+        // The body's actor is only a label, a known caller's name wins
+        let actor = caller
+            .map(|c| c.first_name.clone())
+            .or_else(|| increment.actor.clone());
+        if chart.requires_approval && !caller.is_some_and(|c| c.is_parent) {
+            let approval = star_approvals::ActiveModel {
+                star_chart_id: Set(chart.id),
+                delta: Set(delta),
+                note: Set(increment.note.clone()),
+                actor: Set(actor),
+                status: Set(ApprovalStatus::Pending),
+                created_at: Set(Utc::now()),
+                ..Default::default()
+            }
+            .insert(&txn)
+            .await?;
+            txn.commit().await?;
+
+            return Ok(IncrementStarChartResponse {
+                id: chart.id,
+                star_count: chart.star_count,
+                approval_id: Some(approval.id),
+                cooldown_seconds,
+                completed: false,
+                unlocked: Vec::new(),
+                bonus,
+            });
+        }
+
+        let before = chart.clone();
+        let (res, completed) = apply_increment(
+            &txn,
+            chart,
+            delta,
+            increment.note.clone(),
+            actor,
+            StarEventKind::Increment,
+        )
+        .await?;
+        let unlocked = if completed {
+            unlock_quests(&txn, res.id).await?
+        } else {
+            Vec::new()
+        };
+        let applied = res.star_count - before.star_count;
+        if applied != 0 {
+            record_undo(
+                &txn,
+                client,
+                UndoAction::Increment,
+                res.id,
+                Some(applied),
+                Some(&before),
+                Some(&res),
+            )
+            .await?;
+        }
+        txn.commit().await?;
+        self.refresh_progress(res.person_id).await;
+
+        Ok(IncrementStarChartResponse {
+            id: res.id,
+            star_count: res.star_count,
+            approval_id: None,
+            cooldown_seconds,
+            completed,
+            unlocked,
+            bonus,
+        })
+    }
+}
+
+/// Reject an increment that comes too soon after the last star on the chart,
+/// or that would take today's stars past the daily cap. Stars still waiting
+/// for a parent's approval count too, so they can't flood the queue.
+async fn check_increment_limits<C: ConnectionTrait>(
+    db: &C,
+    chart: &crate::entity::star_charts::Model,
+    delta: i32,
+    cooldown_seconds: Option<i32>,
+    daily_cap: Option<i32>,
+) -> Result<(), anyhow::Error> {
+    use crate::entity::star_approvals::{self, ApprovalStatus};
+    use crate::entity::star_charts::Recurrence;
+    use crate::entity::star_events;
+
+    let now = Utc::now();
+    if let Some(cooldown) = cooldown_seconds {
+        let last_event = star_events::Entity::find()
+            .filter(star_events::Column::StarChartId.eq(chart.id))
+            .filter(star_events::Column::Kind.eq(StarEventKind::Increment))
+            .filter(star_events::Column::Delta.gt(0))
+            .order_by_desc(star_events::Column::CreatedAt)
+            .one(db)
+            .await?
+            .map(|e| e.created_at);
+        let last_pending = star_approvals::Entity::find()
+            .filter(star_approvals::Column::StarChartId.eq(chart.id))
+            .filter(star_approvals::Column::Status.eq(ApprovalStatus::Pending))
+            .filter(star_approvals::Column::Delta.gt(0))
+            .order_by_desc(star_approvals::Column::CreatedAt)
+            .one(db)
+            .await?
+            .map(|a| a.created_at);
+        if let Some(last) = last_event.max(last_pending) {
+            let elapsed = (now - last).num_seconds();
+            if elapsed < cooldown as i64 {
+                let retry_after_secs = cooldown as i64 - elapsed;
+                return Err(HGError::RateLimited {
+                    message: format!(
+                        "{} is cooling down, try again in {}s",
+                        chart.chart_type, retry_after_secs
+                    ),
+                    retry_after_secs,
+                }
+                .into());
+            }
+        }
+    }
+
+    if let Some(cap) = daily_cap {
+        let today = Recurrence::Daily.period_start(now);
+        let earned: i64 = star_events::Entity::find()
+            .filter(star_events::Column::StarChartId.eq(chart.id))
+            .filter(star_events::Column::Kind.eq(StarEventKind::Increment))
+            .filter(star_events::Column::Delta.gt(0))
+            .filter(star_events::Column::CreatedAt.gte(today))
+            .all(db)
+            .await?
+            .iter()
+            .map(|e| i64::from(e.delta))
+            .sum();
+        let pending: i64 = star_approvals::Entity::find()
+            .filter(star_approvals::Column::StarChartId.eq(chart.id))
+            .filter(star_approvals::Column::Status.eq(ApprovalStatus::Pending))
+            .filter(star_approvals::Column::Delta.gt(0))
+            .filter(star_approvals::Column::CreatedAt.gte(today))
+            .all(db)
+            .await?
+            .iter()
+            .map(|a| i64::from(a.delta))
+            .sum();
+        if earned + pending + i64::from(delta) > i64::from(cap) {
+            let tomorrow = Recurrence::Daily.next_period_start(today);
+            return Err(HGError::RateLimited {
+                message: format!(
+                    "{} has reached its limit of {} stars for today",
+                    chart.chart_type, cap
+                ),
+                retry_after_secs: (tomorrow - now).num_seconds().max(1),
+            }
+            .into());
+        }
+    }
+
+    Ok(())
+}
+
+/// Move the calendar feed's Last-Modified on, after any change to its events
+async fn touch_calendar<C: ConnectionTrait>(db: &C) -> Result<(), anyhow::Error> {
+    use crate::entity::calendar_changes;
+    use sea_orm::sea_query::OnConflict;
+
+    calendar_changes::Entity::insert(calendar_changes::ActiveModel {
+        id: Set(1),
+        changed_at: Set(Utc::now()),
+    })
+    .on_conflict(
+        OnConflict::column(calendar_changes::Column::Id)
+            .update_column(calendar_changes::Column::ChangedAt)
+            .to_owned(),
+    )
+    .exec(db)
+    .await?;
+    Ok(())
+}
+
+/// Run a one-off data migration unless this database has already had it
+async fn run_migration(
+    db: &DatabaseConnection,
+    name: &str,
+    sql: &str,
+) -> Result<(), anyhow::Error> {
+    use crate::entity::schema_migrations;
+
+    let txn = db.begin().await?;
+    if schema_migrations::Entity::find_by_id(name)
+        .one(&txn)
+        .await?
+        .is_some()
+    {
+        return Ok(());
+    }
+    txn.execute_unprepared(sql)
+        .await
+        .with_context(|| format!("migration {} failed", name))?;
+    schema_migrations::ActiveModel {
+        name: Set(name.to_string()),
+        applied_at: Set(Utc::now()),
+    }
+    .insert(&txn)
+    .await?;
+    txn.commit().await?;
+    Ok(())
+}
+
+/// Keep only the oldest row of each group sharing `columns`, so the schema
+/// sync can add a unique key over them to a table that predates it
+async fn drop_duplicates(
+    db: &DatabaseConnection,
+    table: &str,
+    columns: &str,
+) -> Result<(), anyhow::Error> {
+    use sea_orm::{DbBackend, Statement};
+
+    let exists = db
+        .query_one_raw(Statement::from_string(
+            DbBackend::Sqlite,
+            format!(
+                r#"SELECT 1 FROM "sqlite_master" WHERE "type" = 'table' AND "name" = '{table}'"#
+            ),
+        ))
+        .await?
+        .is_some();
+    if exists {
+        db.execute_unprepared(&format!(
+            r#"DELETE FROM "{table}" WHERE "id" NOT IN (
+                SELECT MIN("id") FROM "{table}" GROUP BY {columns}
+            )"#
+        ))
+        .await?;
+    }
+    Ok(())
+}
+
+/// Refuse to hand out a reward the chart's overflow policy would cut short
+fn ensure_room(chart: &crate::entity::star_charts::Model, reward: i32) -> Result<(), HGError> {
+    let room = chart
+        .overflow_policy
+        .add_stars(chart.star_count, reward, chart.star_total)
+        - chart.star_count;
+    if room < reward {
+        return Err(HGError::Conflict(format!(
+            "{} only has room for {} more stars",
+            chart.chart_type, room
+        )));
+    }
+    Ok(())
+}
+
+/// Add `delta` to a chart, within its overflow policy, and record the change
+/// actually applied in the ledger. Callers own the transaction so the change
+/// can be combined with their own writes. Returns the updated chart and
+/// whether this increment completed it. The `kind` is recorded with the
+/// change and decides whether it counts for XP and allowance.
+async fn apply_increment<C: ConnectionTrait>(
+    db: &C,
+    chart: crate::entity::star_charts::Model,
+    delta: i32,
+    note: Option<String>,
+    actor: Option<String>,
+    kind: StarEventKind,
+) -> Result<(crate::entity::star_charts::Model, bool), anyhow::Error> {
+    use crate::entity::star_charts;
+    use sea_orm::sea_query::ExprTrait;
+
+    if chart.archived_at.is_some() {
+        return Err(HGError::Conflict(format!("{} is archived", chart.chart_type)).into());
+    }
+    if !chart.status.takes_stars() {
+        return Err(HGError::Conflict(format!(
+            "{} is {}",
+            chart.chart_type,
+            chart.status.to_value()
+        ))
+        .into());
+    }
+    let now = Utc::now();
+    if let Some(starts_at) = chart.starts_at
+        && starts_at > now
+    {
+        return Err(HGError::Conflict(format!(
+            "{} starts at {}",
+            chart.chart_type,
+            starts_at.to_rfc3339()
+        ))
+        .into());
+    }
+    // The deadline task may not have caught up with it yet
+    if let Some(due_at) = chart.due_at
+        && due_at <= now
+        && chart.status == ChartStatus::Active
+    {
+        return Err(HGError::Conflict(format!("{} is past its due date", chart.chart_type)).into());
+    }
+
+    let new_count = chart
+        .overflow_policy
+        .add_stars(chart.star_count, delta, chart.star_total);
+    let applied = new_count - chart.star_count;
+    if applied == 0 {
+        return Ok((chart, false));
+    }
+
+    let id = chart.id;
+    let version = chart.version;
+    let status = chart.status;
+    let star_total = chart.star_total;
+    let mut am: star_charts::ActiveModel = chart.into();
+    let completed = set_star_count(&mut am, &status, new_count, star_total);
+    // The count itself is added in SQL rather than written back
+    am.star_count = sea_orm::ActiveValue::NotSet;
+
+    let result = star_charts::Entity::update_many()
+        .set(am)
+        .col_expr(
+            star_charts::Column::StarCount,
+            Expr::col(star_charts::Column::StarCount).add(applied),
+        )
+        .col_expr(
+            star_charts::Column::Version,
+            Expr::col(star_charts::Column::Version).add(1),
+        )
+        .filter(star_charts::Column::Id.eq(id))
+        .filter(star_charts::Column::Version.eq(version))
+        .exec(db)
+        .await?;
+    if result.rows_affected == 0 {
+        return Err(HGError::VersionMismatch(format!("star chart {} changed", id)).into());
+    }
+
+    let res = star_charts::Entity::find_by_id(id)
+        .one(db)
+        .await?
+        .context("star chart vanished during update")?;
+    crate::entity::helpers::record_star_event(db, res.id, applied, kind, note, actor.clone())
+        .await?;
+    if !kind.earned() {
+        return Ok((res, completed));
+    }
+    credit_allowance(db, &res, applied, actor).await?;
+    // Stars taken back off were never really earned
+    if applied > 0 {
+        crate::entity::helpers::add_xp(db, res.person_id, applied).await?;
+    } else {
+        crate::entity::helpers::remove_xp(db, res.person_id, -applied).await?;
+    }
+    Ok((res, completed))
+}
+
+/// Turn stars earned on a chart into pocket money, using the chart's rate or
+/// else the person's. Taking stars back off reverses the credit, as far as
+/// the balance goes: money already paid out stays paid.
+async fn credit_allowance<C: ConnectionTrait>(
+    db: &C,
+    chart: &crate::entity::star_charts::Model,
+    stars: i32,
+    actor: Option<String>,
+) -> Result<(), anyhow::Error> {
+    use crate::entity::allowance_entries::{self, EntryKind};
+
+    let cents_per_star = match chart.cents_per_star {
+        Some(cents) => Some(cents),
+        None => crate::entity::people::Entity::find_by_id(chart.person_id)
+            .one(db)
+            .await?
+            .and_then(|p| p.cents_per_star),
+    };
+    let Some(cents_per_star) = cents_per_star.filter(|c| *c > 0) else {
+        return Ok(());
+    };
+    let mut amount_cents = i64::from(stars) * i64::from(cents_per_star);
+    if amount_cents < 0 {
+        let balance_cents = allowance_balance(db, chart.person_id).await?;
+        amount_cents = std::cmp::max(amount_cents, -std::cmp::max(balance_cents, 0));
+        if amount_cents == 0 {
+            return Ok(());
+        }
+    }
+
+    allowance_entries::ActiveModel {
+        person_id: Set(chart.person_id),
+        kind: Set(EntryKind::Credit),
+        amount_cents: Set(amount_cents),
+        star_chart_id: Set(Some(chart.id)),
+        stars: Set(Some(stars)),
+        note: Set(Some(chart.chart_type.clone())),
+        actor: Set(actor),
+        created_at: Set(Utc::now()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+    Ok(())
+}
+
+/// Sum of a person's allowance entries, in cents
+async fn allowance_balance<C: ConnectionTrait>(
+    db: &C,
+    person_id: i32,
+) -> Result<i64, anyhow::Error> {
+    use crate::entity::allowance_entries;
+    use sea_orm::QuerySelect;
+
+    let balance: Option<i64> = allowance_entries::Entity::find()
+        .select_only()
+        .column_as(Expr::cust(r#"COALESCE(SUM("amount_cents"), 0)"#), "balance")
+        .filter(allowance_entries::Column::PersonId.eq(person_id))
+        .into_tuple()
+        .one(db)
+        .await?;
+    Ok(balance.unwrap_or_default())
+}
+
+async fn insert_star_chart<C: ConnectionTrait>(
+    db: &C,
+    star_chart: &CreateStarChartRequest,
+    template_key: Option<String>,
+) -> Result<i32, anyhow::Error> {
+    let person = crate::entity::people::Entity::find_by_id(star_chart.person_id)
+        .one(db)
+        .await?;
+    anyhow::ensure!(
+        person.is_some(),
+        "person {} not found",
+        star_chart.person_id
+    );
+
+    let locked = !prerequisites_met(db, star_chart.person_id, None, &star_chart.requires).await?;
+    let starts_at = star_chart
+        .starts_at
+        .as_deref()
+        .map(|s| parse_deadline(s, false))
+        .transpose()?;
+    let due_at = star_chart
+        .due_at
+        .as_deref()
+        .map(|s| parse_deadline(s, true))
+        .transpose()?;
+    check_deadlines(starts_at, due_at)?;
+    LimitsConfig::check(star_chart.cooldown_seconds, star_chart.daily_cap)
+        .map_err(HGError::Invalid)?;
+
+    let now = chrono::Utc::now();
+    // determine chart color: use provided or pick from palette
+    let palette = [
+        "#ff8a65", "#ffd54f", "#81c784", "#64b5f6", "#e57373", "#ba68c8", "#4db6ac",
+        "#ffb74d", "#aed581", "#90caf9",
+    ];
+    let chosen_color = if let Some(c) = star_chart.color.clone() {
+        c
+    } else {
+        let idx = (Utc::now().timestamp_millis() as usize) % palette.len();
+        palette[idx].to_string()
+    };
+
+    let mut new_star_chart = crate::entity::star_charts::ActiveModel {
+        person_id: Set(star_chart.person_id),
+        chart_type: Set(star_chart.name.clone()),
+        chart_key: Set(star_chart.description.clone()),
+        star_total: Set(star_chart.star_total),
+        star_count: Set(star_chart.star_count),
+        color: Set(Some(chosen_color)),
+        created_at: Set(now),
+        updated_at: Set(now),
+        recurrence: Set(star_chart.recurrence),
+        period_started_at: Set(star_chart.recurrence.map(|r| r.period_start(now))),
+        requires_approval: Set(star_chart.requires_approval),
+        cooldown_seconds: Set(star_chart.cooldown_seconds),
+        daily_cap: Set(star_chart.daily_cap),
+        template_key: Set(template_key),
+        cents_per_star: Set(star_chart.cents_per_star),
+        starts_at: Set(starts_at),
+        due_at: Set(due_at),
+        deadline_policy: Set(star_chart.deadline_policy.unwrap_or(DeadlinePolicy::Expire)),
+        ..Default::default()
+    };
+    let overflow_policy = star_chart.overflow_policy.unwrap_or(OverflowPolicy::Clamp);
+    new_star_chart.overflow_policy = Set(overflow_policy);
+    new_star_chart.version = Set(1);
+    let status = if locked {
+        ChartStatus::Locked
+    } else {
+        ChartStatus::Active
+    };
+    new_star_chart.status = Set(status);
+    set_star_count(
+        &mut new_star_chart,
+        &status,
+        overflow_policy.bound(star_chart.star_count, star_chart.star_total),
+        star_chart.star_total,
+    );
+
+    let result = crate::entity::star_charts::Entity::insert(new_star_chart)
+        .exec(db)
+        .await?;
+    for requires_id in &star_chart.requires {
+        crate::entity::star_chart_prerequisites::ActiveModel {
+            star_chart_id: Set(result.last_insert_id),
+            requires_id: Set(*requires_id),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+    }
+
+    Ok(result.last_insert_id)
+}
+
+/// Whether every required chart has been completed or archived. Also checks
+/// the requirements belong to the same person and, for an existing chart,
+/// that they don't lead back to it.
+async fn prerequisites_met<C: ConnectionTrait>(
+    db: &C,
+    person_id: i32,
+    star_chart_id: Option<i32>,
+    requires: &[i32],
+) -> Result<bool, anyhow::Error> {
+    use crate::entity::{star_chart_prerequisites, star_charts};
+
+    let mut met = true;
+    for requires_id in requires {
+        let required = star_charts::Entity::find_by_id(*requires_id)
+            .one(db)
+            .await?;
+        let Some(required) = required.filter(|c| c.person_id == person_id) else {
+            return Err(HGError::Invalid(format!(
+                "star chart {} does not belong to person {}",
+                requires_id, person_id
+            ))
+            .into());
+        };
+        met &= required.status == ChartStatus::Completed
+            || required.completed_at.is_some()
+            || required.archived_at.is_some();
+    }
+
+    if let Some(star_chart_id) = star_chart_id {
+        // Walk up the chain from each requirement looking for this chart
+        let mut pending = requires.to_vec();
+        let mut seen = Vec::new();
+        while let Some(id) = pending.pop() {
+            if id == star_chart_id {
+                return Err(HGError::Invalid(format!(
+                    "star chart {} would end up requiring itself",
+                    star_chart_id
+                ))
+                .into());
+            }
+            if seen.contains(&id) {
+                continue;
+            }
+            seen.push(id);
+            let links = star_chart_prerequisites::Entity::find()
+                .filter(star_chart_prerequisites::Column::StarChartId.eq(id))
+                .all(db)
+                .await?;
+            pending.extend(links.into_iter().map(|l| l.requires_id));
+        }
+    }
+    Ok(met)
+}
+
+/// Unlock the locked charts whose requirements are now all complete,
+/// returning their ids
+async fn unlock_quests<C: ConnectionTrait>(
+    db: &C,
+    completed_chart_id: i32,
+) -> Result<Vec<i32>, anyhow::Error> {
+    let dependents = dependent_charts(db, completed_chart_id).await?;
+    unlock_charts(db, &dependents).await
+}
+
+/// Ids of the charts that require `chart_id`
+async fn dependent_charts<C: ConnectionTrait>(
+    db: &C,
+    chart_id: i32,
+) -> Result<Vec<i32>, anyhow::Error> {
+    use crate::entity::star_chart_prerequisites;
+
+    Ok(star_chart_prerequisites::Entity::find()
+        .filter(star_chart_prerequisites::Column::RequiresId.eq(chart_id))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|l| l.star_chart_id)
+        .collect())
+}
+
+/// Unlock those of `chart_ids` that are locked with every requirement met,
+/// returning their ids
+async fn unlock_charts<C: ConnectionTrait>(
+    db: &C,
+    chart_ids: &[i32],
+) -> Result<Vec<i32>, anyhow::Error> {
+    use crate::entity::{star_chart_prerequisites, star_charts};
+
+    let mut unlocked = Vec::new();
+    for chart_id in chart_ids {
+        let Some(chart) = star_charts::Entity::find_by_id(*chart_id).one(db).await? else {
+            continue;
+        };
+        if chart.status != ChartStatus::Locked {
+            continue;
+        }
+        let requires: Vec<i32> = star_chart_prerequisites::Entity::find()
+            .filter(star_chart_prerequisites::Column::StarChartId.eq(chart.id))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|l| l.requires_id)
+            .collect();
+        if !prerequisites_met(db, chart.person_id, None, &requires).await? {
+            continue;
+        }
+
+        let version = chart.version;
+        let (star_count, star_total) = (chart.star_count, chart.star_total);
+        let mut am: star_charts::ActiveModel = chart.into();
+        am.status = Set(ChartStatus::Active);
+        set_star_count(&mut am, &ChartStatus::Active, star_count, star_total);
+        let res = save_star_chart(db, am, version).await?;
+        unlocked.push(res.id);
+    }
+    Ok(unlocked)
+}
+
+/// Lock the open charts that depend on `chart_id` again once their
+/// requirements are no longer all complete, as when a completion is undone
+async fn relock_quests<C: ConnectionTrait>(db: &C, chart_id: i32) -> Result<(), anyhow::Error> {
+    use crate::entity::{star_chart_prerequisites, star_charts};
+
+    let dependents = star_chart_prerequisites::Entity::find()
+        .filter(star_chart_prerequisites::Column::RequiresId.eq(chart_id))
+        .all(db)
+        .await?;
+    for link in dependents {
+        let Some(chart) = star_charts::Entity::find_by_id(link.star_chart_id)
+            .one(db)
+            .await?
+        else {
+            continue;
+        };
+        if chart.status != ChartStatus::Active {
+            continue;
+        }
+        let requires: Vec<i32> = star_chart_prerequisites::Entity::find()
+            .filter(star_chart_prerequisites::Column::StarChartId.eq(chart.id))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|l| l.requires_id)
+            .collect();
+        if prerequisites_met(db, chart.person_id, None, &requires).await? {
+            continue;
+        }
+
+        let version = chart.version;
+        let mut am: star_charts::ActiveModel = chart.into();
+        am.status = Set(ChartStatus::Locked);
+        am.updated_at = Set(Utc::now());
+        save_star_chart(db, am, version).await?;
+    }
+    Ok(())
+}
+
+/// Write a chart only if it is still at `version`, bumping the version so
+/// anyone holding the old one is refused.
+async fn save_star_chart<C: ConnectionTrait>(
+    db: &C,
+    mut am: crate::entity::star_charts::ActiveModel,
+    version: i32,
+) -> Result<crate::entity::star_charts::Model, anyhow::Error> {
+    use crate::entity::star_charts;
+
+    let id = match &am.id {
+        sea_orm::ActiveValue::Set(id) | sea_orm::ActiveValue::Unchanged(id) => *id,
+        sea_orm::ActiveValue::NotSet => anyhow::bail!("star chart has no id"),
+    };
+    am.version = Set(version + 1);
+
+    let result = star_charts::Entity::update_many()
+        .set(am)
+        .filter(star_charts::Column::Id.eq(id))
+        .filter(star_charts::Column::Version.eq(version))
+        .exec(db)
+        .await?;
+    if result.rows_affected == 0 {
+        return Err(HGError::VersionMismatch(format!(
+            "star chart {} was changed by someone else",
+            id
+        ))
+        .into());
+    }
+
+    star_charts::Entity::find_by_id(id)
+        .one(db)
+        .await?
+        .context("star chart vanished during update")
+}
+
+/// Write a new star count, keeping status and completed_at in step with the
+/// goal. Locked, expired and failed charts keep their status. Returns true
+/// when this change took an active chart to its goal.
+fn set_star_count(
+    am: &mut crate::entity::star_charts::ActiveModel,
+    status: &ChartStatus,
+    star_count: i32,
+    star_total: i32,
+) -> bool {
+    let now = Utc::now();
+    am.star_count = Set(star_count);
+    am.updated_at = Set(now);
+
+    let reached = star_total > 0 && star_count >= star_total;
+    match status {
+        ChartStatus::Active if reached => {
+            am.status = Set(ChartStatus::Completed);
+            am.completed_at = Set(Some(now));
+            true
+        }
+        ChartStatus::Completed if !reached => {
+            am.status = Set(ChartStatus::Active);
+            am.completed_at = Set(None);
+            false
+        }
+        _ => false,
+    }
+}
+
+impl SQLConnector {
+    pub async fn list_approvals(
+        &self,
+        query: &ListApprovalsQuery,
+    ) -> Result<Vec<GetApprovalResponse>, anyhow::Error> {
+        use crate::entity::{star_approvals, star_charts};
+
+        let db = self.database_connection.as_ref().unwrap();
+        let mut select = star_approvals::Entity::find()
+            .find_also_related(star_charts::Entity)
+            .order_by_asc(star_approvals::Column::CreatedAt);
+        if let Some(status) = &query.status {
+            select = select.filter(star_approvals::Column::Status.eq(status.clone()));
+        }
+
+        let rows = select.all(db).await?;
+        Ok(rows
+            .into_iter()
+            .map(|(a, chart)| GetApprovalResponse {
+                id: a.id,
+                star_chart_id: a.star_chart_id,
+                chart_name: chart.map(|c| c.chart_type).unwrap_or_default(),
+                delta: a.delta,
+                note: a.note,
+                actor: a.actor,
+                status: a.status,
+                created_at: a.created_at.to_rfc3339(),
+                resolved_by: a.resolved_by,
+                resolved_at: a.resolved_at.map(|d| d.to_rfc3339()),
+                reason: a.reason,
+            })
+            .collect())
+    }
+
+    /// Resolve a pending request. Approving applies the requested delta on
+    /// behalf of the child who asked, in the same transaction.
+    pub async fn resolve_approval(
+        &self,
+        approval_id: i32,
+        resolve: &ResolveApprovalRequest,
+        approve: bool,
+        caller: Option<&Caller>,
+    ) -> Result<(), anyhow::Error> {
+        use crate::entity::star_approvals::{self, ApprovalStatus};
+        use crate::entity::star_charts;
+
+        let parent = require_parent(caller, "resolve approvals")?;
+        let db = self.database_connection.as_ref().unwrap();
+        let txn = db.begin().await?;
+        let Some(approval) = star_approvals::Entity::find_by_id(approval_id)
+            .one(&txn)
+            .await?
+        else {
+            return Err(HGError::NotFound(format!("approval {} not found", approval_id)).into());
+        };
+        if approval.status != ApprovalStatus::Pending {
+            return Err(
+                HGError::Conflict(format!("approval {} is already resolved", approval_id)).into(),
+            );
+        }
+
+        let person_id = if approve {
+            let Some(chart) = star_charts::Entity::find_by_id(approval.star_chart_id)
+                .one(&txn)
+                .await?
+            else {
+                return Err(HGError::NotFound(format!(
+                    "star chart {} not found",
+                    approval.star_chart_id
+                ))
+                .into());
+            };
+            ensure_room(&chart, approval.delta)?;
+            let (res, completed) = apply_increment(
+                &txn,
+                chart,
+                approval.delta,
+                approval.note.clone(),
+                approval.actor.clone(),
+                StarEventKind::Increment,
+            )
+            .await?;
+            if completed {
+                unlock_quests(&txn, res.id).await?;
+            }
+            Some(res.person_id)
+        } else {
+            None
+        };
+
+        let mut am: star_approvals::ActiveModel = approval.into();
+        am.status = Set(if approve {
+            ApprovalStatus::Approved
+        } else {
+            ApprovalStatus::Rejected
+        });
+        am.resolved_by = Set(Some(parent.first_name.clone()));
+        am.resolved_at = Set(Some(Utc::now()));
+        am.reason = Set(resolve.reason.clone());
+        am.update(&txn).await?;
+        txn.commit().await?;
+        if let Some(person_id) = person_id {
+            self.refresh_progress(person_id).await;
+        }
+
+        Ok(())
+    }
+}
+
+impl SQLConnector {
+    pub async fn list_transfers(
+        &self,
+        query: &ListTransfersQuery,
+    ) -> Result<Vec<TransferResponse>, anyhow::Error> {
+        use crate::entity::star_transfers;
+        use sea_orm::Condition;
+
+        let db = self.database_connection.as_ref().unwrap();
+        let mut select =
+            star_transfers::Entity::find().order_by_desc(star_transfers::Column::CreatedAt);
+        if let Some(status) = &query.status {
+            select = select.filter(star_transfers::Column::Status.eq(status.clone()));
+        }
+        if let Some(person_id) = query.person_id {
+            select = select.filter(
+                Condition::any()
+                    .add(star_transfers::Column::FromPersonId.eq(person_id))
+                    .add(star_transfers::Column::ToPersonId.eq(person_id)),
+            );
+        }
+        let transfers = select.all(db).await?;
+        Ok(transfers.into_iter().map(transfer_response).collect())
+    }
+
+    /// Gift stars from one person's chart to another's. Parents' transfers
+    /// and all transfers when approval is off move the stars straight away;
+    /// anyone else's wait for a parent.
+    pub async fn create_transfer(
+        &self,
+        transfer: &CreateTransferRequest,
+        caller: Option<&Caller>,
+    ) -> Result<TransferResponse, anyhow::Error> {
+        use crate::entity::star_charts::{self, Recurrence};
+        use crate::entity::star_transfers::{self, TransferStatus};
+
+        let db = self.database_connection.as_ref().unwrap();
+        let limits = &self.configuration.transfers;
+        if transfer.amount <= 0 {
+            return Err(HGError::Invalid("amount must be at least one star".to_string()).into());
+        }
+        if let Some(max) = limits.max_stars
+            && transfer.amount > max
+        {
+            return Err(HGError::Invalid(format!(
+                "at most {} stars can be transferred at once",
+                max
+            ))
+            .into());
+        }
+
+        let txn = db.begin().await?;
+        let from = star_charts::Entity::find_by_id(transfer.from_star_chart_id)
+            .one(&txn)
+            .await?
+            .ok_or_else(|| {
+                HGError::NotFound(format!(
+                    "star chart {} not found",
+                    transfer.from_star_chart_id
+                ))
+            })?;
+        let to = star_charts::Entity::find_by_id(transfer.to_star_chart_id)
+            .one(&txn)
+            .await?
+            .ok_or_else(|| {
+                HGError::NotFound(format!(
+                    "star chart {} not found",
+                    transfer.to_star_chart_id
+                ))
+            })?;
+        require_owner_or_parent(&txn, caller, &from, "give away stars from").await?;
+        if from.person_id == to.person_id {
+            return Err(HGError::Invalid(
+                "stars can only be transferred to someone else".to_string(),
+            )
+            .into());
+        }
+        if from.star_count < transfer.amount {
+            return Err(HGError::Conflict(format!(
+                "{} only has {} stars",
+                from.chart_type, from.star_count
+            ))
+            .into());
+        }
+
+        if let Some(limit) = limits.daily_limit {
+            let now = Utc::now();
+            let today = Recurrence::Daily.period_start(now);
+            let given: i32 = star_transfers::Entity::find()
+                .filter(star_transfers::Column::FromPersonId.eq(from.person_id))
+                .filter(star_transfers::Column::Status.ne(TransferStatus::Rejected))
+                .filter(star_transfers::Column::CreatedAt.gte(today))
+                .all(&txn)
+                .await?
+                .iter()
+                .map(|t| t.amount)
+                .sum();
+            if given + transfer.amount > limit {
+                let tomorrow = Recurrence::Daily.next_period_start(today);
+                return Err(HGError::RateLimited {
+                    message: format!("only {} stars can be given away per day", limit),
+                    retry_after_secs: (tomorrow - now).num_seconds().max(1),
+                }
+                .into());
+            }
+        }
+
+        let pending = limits.requires_approval && !caller.is_some_and(|c| c.is_parent);
+        let res = star_transfers::ActiveModel {
+            from_star_chart_id: Set(from.id),
+            to_star_chart_id: Set(to.id),
+            from_person_id: Set(from.person_id),
+            to_person_id: Set(to.person_id),
+            amount: Set(transfer.amount),
+            note: Set(transfer.note.clone()),
+            actor: Set(caller.map(|c| c.first_name.clone())),
+            status: Set(if pending {
+                TransferStatus::Pending
+            } else {
+                TransferStatus::Completed
+            }),
+            created_at: Set(Utc::now()),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+        if !pending {
+            apply_transfer(&txn, &res).await?;
+        }
+        txn.commit().await?;
+        if !pending {
+            self.refresh_progress(res.to_person_id).await;
+        }
+
+        Ok(transfer_response(res))
+    }
+
+    /// Approving moves the stars then, so the source must still have them
+    pub async fn resolve_transfer(
+        &self,
+        transfer_id: i32,
+        resolve: &ResolveApprovalRequest,
+        approve: bool,
+        caller: Option<&Caller>,
+    ) -> Result<TransferResponse, anyhow::Error> {
+        use crate::entity::star_transfers::{self, TransferStatus};
+
+        let parent = require_parent(caller, "resolve transfers")?;
+        let db = self.database_connection.as_ref().unwrap();
+        let txn = db.begin().await?;
+        let transfer = star_transfers::Entity::find_by_id(transfer_id)
+            .one(&txn)
+            .await?
+            .ok_or_else(|| HGError::NotFound(format!("transfer {} not found", transfer_id)))?;
+        if transfer.status != TransferStatus::Pending {
+            return Err(
+                HGError::Conflict(format!("transfer {} is already resolved", transfer_id)).into(),
+            );
+        }
+
+        if approve {
+            apply_transfer(&txn, &transfer).await?;
+        }
+        let mut am: star_transfers::ActiveModel = transfer.into();
+        am.status = Set(if approve {
+            TransferStatus::Completed
+        } else {
+            TransferStatus::Rejected
+        });
+        am.resolved_by = Set(Some(parent.first_name.clone()));
+        am.resolved_at = Set(Some(Utc::now()));
+        am.reason = Set(resolve.reason.clone());
+        let res = am.update(&txn).await?;
+        txn.commit().await?;
+        if approve {
+            self.refresh_progress(res.to_person_id).await;
+        }
+
+        Ok(transfer_response(res))
+    }
+}
+
+/// Move a transfer's stars, taking them off the source chart and adding
+/// them to the destination, each side getting its own ledger entry.
+/// Refuses rather than clamping when either chart can't take the full amount.
+async fn apply_transfer<C: ConnectionTrait>(
+    db: &C,
+    transfer: &crate::entity::star_transfers::Model,
+) -> Result<(), anyhow::Error> {
+    use crate::entity::{people, star_charts};
+
+    let from = star_charts::Entity::find_by_id(transfer.from_star_chart_id)
+        .one(db)
+        .await?
+        .context("transfer source chart vanished")?;
+    let to = star_charts::Entity::find_by_id(transfer.to_star_chart_id)
+        .one(db)
+        .await?
+        .context("transfer destination chart vanished")?;
+    if from.star_count < transfer.amount {
+        return Err(HGError::Conflict(format!(
+            "{} only has {} stars",
+            from.chart_type, from.star_count
+        ))
+        .into());
+    }
+    ensure_room(&to, transfer.amount)?;
+
+    let from_name = people::Entity::find_by_id(transfer.from_person_id)
+        .one(db)
+        .await?
+        .map(|p| p.first_name)
+        .unwrap_or_default();
+    let to_name = people::Entity::find_by_id(transfer.to_person_id)
+        .one(db)
+        .await?
+        .map(|p| p.first_name)
+        .unwrap_or_default();
+
+    apply_increment(
+        db,
+        from,
+        -transfer.amount,
+        Some(format!("gift to {}", to_name)),
+        transfer.actor.clone(),
+        StarEventKind::Gift,
+    )
+    .await?;
+    let (res, completed) = apply_increment(
+        db,
+        to,
+        transfer.amount,
+        Some(format!("gift from {}", from_name)),
+        transfer.actor.clone(),
+        StarEventKind::Gift,
+    )
+    .await?;
+    if completed {
+        unlock_quests(db, res.id).await?;
+    }
+    Ok(())
+}
+
+fn transfer_response(t: crate::entity::star_transfers::Model) -> TransferResponse {
+    TransferResponse {
+        id: t.id,
+        from_star_chart_id: t.from_star_chart_id,
+        to_star_chart_id: t.to_star_chart_id,
+        from_person_id: t.from_person_id,
+        to_person_id: t.to_person_id,
+        amount: t.amount,
+        note: t.note,
+        actor: t.actor,
+        status: t.status,
+        created_at: t.created_at.to_rfc3339(),
+        resolved_by: t.resolved_by,
+        resolved_at: t.resolved_at.map(|d| d.to_rfc3339()),
+        reason: t.reason,
+    }
+}
+
+impl SQLConnector {
+    /// Close active charts whose due date has passed, as expired or failed
+    /// depending on each chart's policy. Returns the number of charts closed.
+    pub async fn close_overdue_charts(&self) -> Result<u64, anyhow::Error> {
+        use crate::entity::star_charts;
+        use sea_orm::sea_query::ExprTrait;
+        use sea_orm::{IntoActiveValue, Iterable};
+
+        let db = self.database_connection.as_ref().unwrap();
+        let now = Utc::now();
+        let mut closed = 0;
+        for policy in DeadlinePolicy::iter() {
+            let res = star_charts::Entity::update_many()
+                .set(star_charts::ActiveModel {
+                    status: policy.status().into_active_value(),
+                    updated_at: Set(now),
+                    ..Default::default()
+                })
+                .col_expr(
+                    star_charts::Column::Version,
+                    Expr::col(star_charts::Column::Version).add(1),
+                )
+                .filter(star_charts::Column::Status.eq(ChartStatus::Active))
+                .filter(star_charts::Column::DeadlinePolicy.eq(policy))
+                .filter(star_charts::Column::DueAt.lte(now))
+                .exec(db)
+                .await?;
+            closed += res.rows_affected;
+        }
+        Ok(closed)
+    }
+
+    /// Active charts due within the configured window, soonest first
+    pub async fn list_due_soon(&self) -> Result<Vec<DueSoonResponse>, anyhow::Error> {
+        use crate::entity::{people, star_charts};
+
+        let db = self.database_connection.as_ref().unwrap();
+        let now = Utc::now();
+        let days = self.configuration.deadlines.due_soon_days.unwrap_or(3);
+        let until = now + chrono::Duration::days(i64::from(days));
+        let rows = star_charts::Entity::find()
+            .find_also_related(people::Entity)
+            .filter(star_charts::Column::Status.eq(ChartStatus::Active))
+            .filter(star_charts::Column::ArchivedAt.is_null())
+            .filter(star_charts::Column::DueAt.gt(now))
+            .filter(star_charts::Column::DueAt.lte(until))
+            .order_by_asc(star_charts::Column::DueAt)
+            .all(db)
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|(c, p)| {
+                let due_at = c.due_at?;
+                Some(DueSoonResponse {
+                    star_chart_id: c.id,
+                    name: c.chart_type,
+                    person_id: c.person_id,
+                    person_first_name: p.map(|p| p.first_name).unwrap_or_default(),
+                    due_at: due_at.to_rfc3339(),
+                    hours_left: (due_at - now).num_hours(),
+                    star_count: c.star_count,
+                    star_total: c.star_total,
+                    stars_needed: (c.star_total - c.star_count).max(0),
+                    deadline_policy: c.deadline_policy,
+                })
+            })
+            .collect())
+    }
+}
+
+impl SQLConnector {
+    pub async fn list_bonus_windows(
+        &self,
+        query: &ListBonusWindowsQuery,
+    ) -> Result<Vec<BonusWindowResponse>, anyhow::Error> {
+        use crate::entity::bonus_windows;
+
+        let db = self.database_connection.as_ref().unwrap();
+        let mut select =
+            bonus_windows::Entity::find().order_by_asc(bonus_windows::Column::StartsAt);
+        if query.upcoming {
+            select = select.filter(bonus_windows::Column::EndsAt.gt(Utc::now()));
+        }
+        let windows = select.all(db).await?;
+        windows.into_iter().map(bonus_window_response).collect()
+    }
+
+    /// Schedule a bonus window. Only parents can hand out extra stars.
+    pub async fn create_bonus_window(
+        &self,
+        window: &CreateBonusWindowRequest,
+        caller: Option<&Caller>,
+    ) -> Result<BonusWindowResponse, anyhow::Error> {
+        use crate::entity::bonus_windows::{self, BonusScope};
+        use crate::entity::{people, star_charts};
+        use sea_orm::PaginatorTrait;
+
+        let parent = require_parent(caller, "schedule bonuses")?;
+        let db = self.database_connection.as_ref().unwrap();
+        if !(2..=MAX_BONUS_MULTIPLIER).contains(&window.multiplier) {
+            return Err(HGError::Invalid(format!(
+                "multiplier must be between 2 and {}",
+                MAX_BONUS_MULTIPLIER
+            ))
+            .into());
+        }
+        let starts_at = parse_deadline(&window.starts_at, false)?;
+        let ends_at = parse_deadline(&window.ends_at, true)?;
+        if ends_at <= starts_at {
+            return Err(HGError::Invalid("ends_at must be after starts_at".to_string()).into());
+        }
+
+        let mut target_ids = window.target_ids.clone();
+        target_ids.sort_unstable();
+        target_ids.dedup();
+        let found = match window.scope {
+            BonusScope::All if target_ids.is_empty() => 0,
+            BonusScope::All => {
+                return Err(HGError::Invalid(
+                    "a bonus for everyone takes no target_ids".to_string(),
+                )
+                .into());
+            }
+            BonusScope::Charts => {
+                star_charts::Entity::find()
+                    .filter(star_charts::Column::Id.is_in(target_ids.iter().copied()))
+                    .count(db)
+                    .await?
+            }
+            BonusScope::People => {
+                people::Entity::find()
+                    .filter(people::Column::Id.is_in(target_ids.iter().copied()))
+                    .count(db)
+                    .await?
+            }
+        };
+        if window.scope != BonusScope::All
+            && (target_ids.is_empty() || found != target_ids.len() as u64)
+        {
+            return Err(HGError::Invalid(format!(
+                "target_ids must list existing {}",
+                window.scope.to_value()
+            ))
+            .into());
+        }
+
+        let res = bonus_windows::ActiveModel {
+            name: Set(window.name.clone()),
+            multiplier: Set(window.multiplier),
+            scope: Set(window.scope),
+            target_ids: Set(if target_ids.is_empty() {
+                None
+            } else {
+                Some(serde_json::to_string(&target_ids)?)
+            }),
+            starts_at: Set(starts_at),
+            ends_at: Set(ends_at),
+            created_by: Set(parent.first_name.clone()),
+            created_at: Set(Utc::now()),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+        bonus_window_response(res)
+    }
+
+    pub async fn delete_bonus_window(&self, bonus_window_id: i32) -> Result<(), anyhow::Error> {
+        use crate::entity::bonus_windows;
+
+        let db = self.database_connection.as_ref().unwrap();
+        let res = bonus_windows::Entity::delete_by_id(bonus_window_id)
+            .exec(db)
+            .await?;
+        if res.rows_affected == 0 {
+            return Err(
+                HGError::NotFound(format!("bonus window {} not found", bonus_window_id)).into(),
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Largest multiplier a bonus window may have
+const MAX_BONUS_MULTIPLIER: i32 = 10;
+
+/// The bonus window covering this chart right now. Overlapping windows do
+/// not stack, the biggest multiplier wins.
+async fn active_bonus<C: ConnectionTrait>(
+    db: &C,
+    chart: &crate::entity::star_charts::Model,
+) -> Result<Option<crate::entity::bonus_windows::Model>, anyhow::Error> {
+    use crate::entity::bonus_windows::{self, BonusScope};
+
+    let now = Utc::now();
+    let windows = bonus_windows::Entity::find()
+        .filter(bonus_windows::Column::StartsAt.lte(now))
+        .filter(bonus_windows::Column::EndsAt.gt(now))
+        .order_by_desc(bonus_windows::Column::Multiplier)
+        .all(db)
+        .await?;
+    for window in windows {
+        let target = match window.scope {
+            BonusScope::All => return Ok(Some(window)),
+            BonusScope::Charts => chart.id,
+            BonusScope::People => chart.person_id,
+        };
+        let targets: Vec<i32> = serde_json::from_str(window.target_ids.as_deref().unwrap_or("[]"))?;
+        if targets.contains(&target) {
+            return Ok(Some(window));
+        }
+    }
+    Ok(None)
+}
+
+fn bonus_window_response(
+    w: crate::entity::bonus_windows::Model,
+) -> Result<BonusWindowResponse, anyhow::Error> {
+    let now = Utc::now();
+    Ok(BonusWindowResponse {
+        id: w.id,
+        name: w.name,
+        multiplier: w.multiplier,
+        scope: w.scope,
+        target_ids: serde_json::from_str(w.target_ids.as_deref().unwrap_or("[]"))?,
+        starts_at: w.starts_at.to_rfc3339(),
+        ends_at: w.ends_at.to_rfc3339(),
+        created_by: w.created_by,
+        created_at: w.created_at.to_rfc3339(),
+        active: w.starts_at <= now && now < w.ends_at,
+    })
+}
+
+impl SQLConnector {
+    /// Close every recurring chart whose period has ended: archive the final
+    /// count, log the reset in the ledger and start the new period from zero.
+    /// Returns the number of charts that were reset.
+    pub async fn close_recurring_periods(&self) -> Result<usize, anyhow::Error> {
+        use crate::entity::{star_chart_periods, star_charts};
+
+        let db = self.database_connection.as_ref().unwrap();
+        let now = Utc::now();
+        let charts = star_charts::Entity::find()
+            .filter(star_charts::Column::Recurrence.is_not_null())
+            .all(db)
+            .await?;
+
+        let mut closed = 0;
+        for chart in charts {
+            let Some(recurrence) = chart.recurrence else {
+                continue;
+            };
+            let current_start = recurrence.period_start(now);
+            let started = chart.period_started_at.unwrap_or(chart.created_at);
+            if started >= current_start {
+                continue;
+            }
+
+            let txn = db.begin().await?;
+            star_chart_periods::ActiveModel {
+                star_chart_id: Set(chart.id),
+                period_start: Set(started),
+                period_end: Set(current_start),
+                star_count: Set(chart.star_count),
+                star_total: Set(chart.star_total),
+                ..Default::default()
+            }
+            .insert(&txn)
+            .await?;
+
+            if chart.star_count != 0 {
+                crate::entity::helpers::record_star_event(
+                    &txn,
+                    chart.id,
+                    -chart.star_count,
+                    StarEventKind::Reset,
+                    Some("period reset".to_string()),
+                    None,
+                )
+                .await?;
+            }
+
+            let status = chart.status;
+            let star_total = chart.star_total;
+            let version = chart.version;
+            let mut am: star_charts::ActiveModel = chart.into();
+            set_star_count(&mut am, &status, 0, star_total);
+            am.period_started_at = Set(Some(current_start));
+            save_star_chart(&txn, am, version).await?;
+            txn.commit().await?;
+            closed += 1;
+        }
+
+        Ok(closed)
+    }
+
+    pub async fn list_star_chart_periods(
+        &self,
+        star_chart_id: i32,
+    ) -> Result<Option<Vec<StarChartPeriodResponse>>, anyhow::Error> {
+        use crate::entity::{star_chart_periods, star_charts};
+
+        let db = self.database_connection.as_ref().unwrap();
+        if star_charts::Entity::find_by_id(star_chart_id)
+            .one(db)
+            .await?
+            .is_none()
+        {
+            return Ok(None);
+        }
+
+        let periods = star_chart_periods::Entity::find()
+            .filter(star_chart_periods::Column::StarChartId.eq(star_chart_id))
+            .order_by_desc(star_chart_periods::Column::PeriodStart)
+            .all(db)
+            .await?;
+
+        Ok(Some(
+            periods
+                .into_iter()
+                .map(|p| StarChartPeriodResponse {
+                    id: p.id,
+                    star_chart_id: p.star_chart_id,
+                    period_start: p.period_start.to_rfc3339(),
+                    period_end: p.period_end.to_rfc3339(),
+                    star_count: p.star_count,
+                    star_total: p.star_total,
+                })
+                .collect(),
+        ))
+    }
+}
+
+impl SQLConnector {
+    pub async fn get_person_streaks(
+        &self,
+        first_name: &str,
+    ) -> Result<Option<Vec<ChartStreakResponse>>, anyhow::Error> {
+        use crate::entity::star_charts::{self, Recurrence};
+
+        let db = self.database_connection.as_ref().unwrap();
+        let person = crate::entity::people::Entity::find()
+            .filter(crate::entity::people::Column::FirstName.eq(first_name))
+            .one(db)
+            .await?;
+        let Some(person) = person else {
+            return Ok(None);
+        };
+
+        let charts = star_charts::Entity::find()
+            .filter(star_charts::Column::PersonId.eq(person.id))
+            .all(db)
+            .await?;
+        let streaks = load_streaks(db, &charts).await?;
+
+        Ok(Some(
+            charts
+                .into_iter()
+                .map(|c| {
+                    let (current_streak, best_streak) =
+                        streaks.get(&c.id).copied().unwrap_or_default();
+                    ChartStreakResponse {
+                        star_chart_id: c.id,
+                        name: c.chart_type,
+                        unit: c.recurrence.unwrap_or(Recurrence::Daily),
+                        current_streak,
+                        best_streak,
+                    }
+                })
+                .collect(),
+        ))
+    }
+}
+
+impl SQLConnector {
+    /// Record new levels and unlock any badges the person now qualifies for.
+    /// Failures are logged rather than returned so they never undo the star
+    /// change behind them.
+    async fn refresh_progress(&self, person_id: i32) {
+        if let Err(e) = self.record_level_ups(person_id).await {
+            warn!("Failed to record level ups for person {}: {}", person_id, e);
+        }
+        if let Err(e) = self.unlock_achievements(person_id).await {
+            warn!(
+                "Failed to evaluate achievements for person {}: {}",
+                person_id, e
+            );
+        }
+    }
+
+    /// Level, XP needed for the next level and percent through this one
+    fn level_progress(&self, xp: i32) -> (i32, i32, i32) {
+        let (level, start, next) = self.configuration.levels.level_for(xp);
+        let progress = (xp - start) * 100 / (next - start).max(1);
+        (level, next, progress)
+    }
+
+    /// Bring the person's level ups in line with their XP. Levels can be
+    /// lost: when stars taken back drop them below a level, it comes off the
+    /// list and is recorded afresh if they reach it again.
+    async fn record_level_ups(&self, person_id: i32) -> Result<(), anyhow::Error> {
+        use crate::entity::{level_ups, people};
+        use sea_orm::sea_query::OnConflict;
+
+        let db = self.database_connection.as_ref().unwrap();
+        let Some(person) = people::Entity::find_by_id(person_id).one(db).await? else {
+            return Ok(());
+        };
+        let (level, _, _) = self.configuration.levels.level_for(person.xp);
+        level_ups::Entity::delete_many()
+            .filter(level_ups::Column::PersonId.eq(person_id))
+            .filter(level_ups::Column::Level.gt(level))
+            .exec(db)
+            .await?;
+        let reached = level_ups::Entity::find()
+            .filter(level_ups::Column::PersonId.eq(person_id))
+            .order_by_desc(level_ups::Column::Level)
+            .one(db)
+            .await?
+            .map(|l| l.level)
+            .unwrap_or(1);
+
+        // Another increment may have recorded the same level meanwhile
+        for new_level in (reached + 1)..=level {
+            level_ups::Entity::insert(level_ups::ActiveModel {
+                person_id: Set(person_id),
+                level: Set(new_level),
+                xp: Set(person.xp),
+                reached_at: Set(Utc::now()),
+                ..Default::default()
+            })
+            .on_conflict(
+                OnConflict::columns([level_ups::Column::PersonId, level_ups::Column::Level])
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .exec_without_returning(db)
+            .await?;
+        }
+        Ok(())
+    }
+
+    pub async fn list_level_ups(
+        &self,
+        first_name: &str,
+    ) -> Result<Option<Vec<LevelUpResponse>>, anyhow::Error> {
+        use crate::entity::{level_ups, people};
+
+        let db = self.database_connection.as_ref().unwrap();
+        let Some(person) = people::Entity::find()
+            .filter(people::Column::FirstName.eq(first_name))
+            .one(db)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        let level_ups = level_ups::Entity::find()
+            .filter(level_ups::Column::PersonId.eq(person.id))
+            .order_by_asc(level_ups::Column::Level)
+            .all(db)
+            .await?;
+        Ok(Some(
+            level_ups
+                .into_iter()
+                .map(|l| LevelUpResponse {
+                    level: l.level,
+                    xp: l.xp,
+                    reached_at: l.reached_at.to_rfc3339(),
+                })
+                .collect(),
+        ))
+    }
+
+    async fn unlock_achievements(&self, person_id: i32) -> Result<(), anyhow::Error> {
+        use crate::entity::achievements;
+        use sea_orm::sea_query::OnConflict;
+
+        let db = self.database_connection.as_ref().unwrap();
+        if self.configuration.achievements.is_empty() {
+            return Ok(());
+        }
+
+        let earned: Vec<String> = achievements::Entity::find()
+            .filter(achievements::Column::PersonId.eq(person_id))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|a| a.badge_key)
+            .collect();
+        let progress = achievement_progress(db, person_id).await?;
+
+        // This runs after the change is committed, so a concurrent increment
+        // may unlock the same badge first
+        for (key, badge) in &self.configuration.achievements {
+            if earned.contains(key) || progress.get(badge.kind) < badge.threshold {
+                continue;
+            }
+            achievements::Entity::insert(achievements::ActiveModel {
+                person_id: Set(person_id),
+                badge_key: Set(key.clone()),
+                unlocked_at: Set(Utc::now()),
+                ..Default::default()
+            })
+            .on_conflict(
+                OnConflict::columns([
+                    achievements::Column::PersonId,
+                    achievements::Column::BadgeKey,
+                ])
+                .do_nothing()
+                .to_owned(),
+            )
+            .exec_without_returning(db)
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn earned_badges(&self, person_id: i32) -> Result<Vec<BadgeResponse>, anyhow::Error> {
+        use crate::entity::achievements;
+
+        let db = self.database_connection.as_ref().unwrap();
+        let earned = achievements::Entity::find()
+            .filter(achievements::Column::PersonId.eq(person_id))
+            .order_by_asc(achievements::Column::UnlockedAt)
+            .all(db)
+            .await?;
+
+        // Badges removed from the configuration stay earned but are not shown
+        Ok(earned
+            .into_iter()
+            .filter_map(|a| {
+                let badge = self.configuration.achievements.get(&a.badge_key)?;
+                Some(BadgeResponse {
+                    key: a.badge_key,
+                    name: badge.name.clone(),
+                    description: badge.description.clone(),
+                    unlocked_at: a.unlocked_at.to_rfc3339(),
+                })
+            })
+            .collect())
+    }
+
+    pub async fn list_locked_badges(
+        &self,
+        first_name: &str,
+    ) -> Result<Option<Vec<LockedBadgeResponse>>, anyhow::Error> {
+        use crate::entity::{achievements, people};
+
+        let db = self.database_connection.as_ref().unwrap();
+        let Some(person) = people::Entity::find()
+            .filter(people::Column::FirstName.eq(first_name))
+            .one(db)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        let earned: Vec<String> = achievements::Entity::find()
+            .filter(achievements::Column::PersonId.eq(person.id))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|a| a.badge_key)
+            .collect();
+        let progress = achievement_progress(db, person.id).await?;
+
+        let mut locked: Vec<LockedBadgeResponse> = self
+            .configuration
+            .achievements
+            .iter()
+            .filter(|(key, _)| !earned.contains(key))
+            .map(|(key, badge)| LockedBadgeResponse {
+                key: key.clone(),
+                name: badge.name.clone(),
+                description: badge.description.clone(),
+                progress: progress.get(badge.kind).min(badge.threshold),
+                threshold: badge.threshold,
+            })
+            .collect();
+        locked.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(Some(locked))
+    }
+}
+
+/// Everything a badge can be measured against, for one person
+struct AchievementProgress {
+    total_stars: i32,
+    best_streak: i32,
+    charts_completed: i32,
+}
+
+impl AchievementProgress {
+    fn get(&self, kind: AchievementKind) -> i32 {
+        match kind {
+            AchievementKind::TotalStars => self.total_stars,
+            AchievementKind::Streak => self.best_streak,
+            AchievementKind::ChartsCompleted => self.charts_completed,
+        }
+    }
+}
+
+async fn achievement_progress(
+    db: &DatabaseConnection,
+    person_id: i32,
+) -> Result<AchievementProgress, anyhow::Error> {
+    use crate::entity::{star_chart_periods, star_charts, star_events};
+
+    let charts = star_charts::Entity::find()
+        .filter(star_charts::Column::PersonId.eq(person_id))
+        .all(db)
+        .await?;
+    let chart_ids: Vec<i32> = charts.iter().map(|c| c.id).collect();
+
+    let total_stars: i32 = star_events::Entity::find()
+        .filter(star_events::Column::StarChartId.is_in(chart_ids.clone()))
+        .filter(star_events::Column::Delta.gt(0))
+        .all(db)
+        .await?
+        .iter()
+        .map(|e| e.delta)
+        .sum();
+
+    let best_streak = load_streaks(db, &charts)
+        .await?
+        .values()
+        .map(|(_, best)| *best)
+        .max()
+        .unwrap_or_default();
+
+    // A recurring chart counts once for every period it finished
+    let completed_periods = star_chart_periods::Entity::find()
+        .filter(star_chart_periods::Column::StarChartId.is_in(chart_ids))
+        .all(db)
+        .await?
+        .iter()
+        .filter(|p| p.star_total > 0 && p.star_count >= p.star_total)
+        .count();
+    let completed_charts = charts.iter().filter(|c| c.completed_at.is_some()).count();
+
+    Ok(AchievementProgress {
+        total_stars,
+        best_streak,
+        charts_completed: (completed_periods + completed_charts) as i32,
+    })
+}
+
+impl SQLConnector {
+    pub async fn list_templates(&self) -> Result<Vec<TemplateResponse>, anyhow::Error> {
+        use crate::entity::star_charts;
+        use sea_orm::PaginatorTrait;
+
+        let db = self.database_connection.as_ref().unwrap();
+        let mut templates = Vec::new();
+        for (key, t) in &self.configuration.templates {
+            let chart_count = star_charts::Entity::find()
+                .filter(star_charts::Column::TemplateKey.eq(key.as_str()))
+                .filter(star_charts::Column::ArchivedAt.is_null())
+                .count(db)
+                .await?;
+            templates.push(TemplateResponse {
+                key: key.clone(),
+                name: t.name.clone(),
+                description: t.description.clone(),
+                star_total: t.star_total,
+                color: t.color.clone(),
+                recurrence: t.recurrence,
+                chart_count,
+            });
+        }
+        templates.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(templates)
+    }
+
+    /// Create a chart from the template for one person, or for each child of
+    /// a parent. People who already have a chart from it are skipped, whatever
+    /// its status, unless it has been archived.
+    pub async fn instantiate_template(
+        &self,
+        key: &str,
+        request: &InstantiateTemplateRequest,
+    ) -> Result<InstantiateTemplateResponse, anyhow::Error> {
+        use crate::entity::{people, star_charts};
+
+        let Some(template) = self.configuration.templates.get(key) else {
+            return Err(HGError::NotFound(format!("template {} not found", key)).into());
+        };
+        let db = self.database_connection.as_ref().unwrap();
+
+        let people_ids: Vec<i32> = match (request.person_id, request.parent_id) {
+            (Some(person_id), None) => {
+                let person = people::Entity::find_by_id(person_id)
+                    .one(db)
+                    .await?
+                    .ok_or_else(|| HGError::NotFound(format!("person {} not found", person_id)))?;
+                if person.archived_at.is_some() {
+                    return Err(
+                        HGError::Conflict(format!("person {} is archived", person_id)).into(),
+                    );
+                }
+                vec![person_id]
+            }
+            (None, Some(parent_id)) => {
+                let person = people::Entity::find_by_id(parent_id).one(db).await?;
+                if person.is_none() {
+                    return Err(HGError::NotFound(format!("person {} not found", parent_id)).into());
+                }
+                crate::entity::helpers::children_of(db, parent_id)
+                    .await?
+                    .into_iter()
+                    .filter(|c| c.archived_at.is_none())
+                    .map(|c| c.id)
+                    .collect()
+            }
+            _ => {
+                return Err(HGError::Invalid(
+                    "set exactly one of person_id or parent_id".to_string(),
+                )
+                .into());
+            }
+        };
+
+        let txn = db.begin().await?;
+        let mut response = InstantiateTemplateResponse {
+            star_chart_ids: Vec::new(),
+            skipped_person_ids: Vec::new(),
+        };
+        for person_id in people_ids {
+            let existing = star_charts::Entity::find()
+                .filter(star_charts::Column::PersonId.eq(person_id))
+                .filter(star_charts::Column::TemplateKey.eq(key))
+                .filter(star_charts::Column::ArchivedAt.is_null())
+                .one(&txn)
+                .await?;
+            if existing.is_some() {
+                response.skipped_person_ids.push(person_id);
+                continue;
+            }
+
+            let chart = CreateStarChartRequest {
+                name: template.name.clone(),
+                description: template.description.clone(),
+                person_id,
+                star_count: 0,
+                star_total: template.star_total,
+                color: template.color.clone(),
+                recurrence: template.recurrence,
+                requires_approval: false,
+                cooldown_seconds: None,
+                daily_cap: None,
+                overflow_policy: None,
+                cents_per_star: None,
+                requires: Vec::new(),
+                starts_at: None,
+                due_at: None,
+                deadline_policy: None,
+            };
+            let id = insert_star_chart(&txn, &chart, Some(key.to_string())).await?;
+            response.star_chart_ids.push(id);
+        }
+        txn.commit().await?;
+
+        Ok(response)
+    }
+
+    /// Copy the template's current settings onto every chart created from it
+    /// that has not been archived. Star counts are kept.
+    pub async fn propagate_template(
+        &self,
+        key: &str,
+    ) -> Result<PropagateTemplateResponse, anyhow::Error> {
+        use crate::entity::star_charts;
+
+        let Some(template) = self.configuration.templates.get(key) else {
+            return Err(HGError::NotFound(format!("template {} not found", key)).into());
+        };
+        let db = self.database_connection.as_ref().unwrap();
+        let txn = db.begin().await?;
+
+        let charts = star_charts::Entity::find()
+            .filter(star_charts::Column::TemplateKey.eq(key))
+            .filter(star_charts::Column::ArchivedAt.is_null())
+            .all(&txn)
+            .await?;
+
+        let mut star_chart_ids = Vec::new();
+        for chart in charts {
+            let unchanged = chart.chart_type == template.name
+                && chart.chart_key == template.description
+                && chart.star_total == template.star_total
+                && (template.color.is_none() || chart.color == template.color)
+                && chart.recurrence == template.recurrence;
+            if unchanged {
+                continue;
+            }
+
+            let version = chart.version;
+            let status = chart.status;
+            let previous_count = chart.star_count;
+            let star_count = chart
+                .overflow_policy
+                .bound(chart.star_count, template.star_total);
+            let recurrence_changed = chart.recurrence != template.recurrence;
+
+            let mut am: star_charts::ActiveModel = chart.into();
+            am.chart_type = Set(template.name.clone());
+            am.chart_key = Set(template.description.clone());
+            am.star_total = Set(template.star_total);
+            if let Some(color) = &template.color {
+                am.color = Set(Some(color.clone()));
+            }
+            if recurrence_changed {
+                am.recurrence = Set(template.recurrence);
+                am.period_started_at = Set(template.recurrence.map(|r| r.period_start(Utc::now())));
+            }
+            set_star_count(&mut am, &status, star_count, template.star_total);
+
+            let res = save_star_chart(&txn, am, version).await?;
+            // A lower goal can clamp the count, keep the ledger in step
+            if res.star_count != previous_count {
+                crate::entity::helpers::record_star_event(
+                    &txn,
+                    res.id,
+                    res.star_count - previous_count,
+                    StarEventKind::Adjustment,
+                    Some(format!("template {} updated", key)),
+                    None,
+                )
+                .await?;
+            }
+            star_chart_ids.push(res.id);
+        }
+        txn.commit().await?;
+
+        Ok(PropagateTemplateResponse { star_chart_ids })
+    }
+}
+
+impl SQLConnector {
+    pub async fn get_allowance_balances(
+        &self,
+    ) -> Result<Vec<AllowanceBalanceResponse>, anyhow::Error> {
+        use crate::entity::people;
+        use sea_orm::{FromQueryResult, JoinType, QuerySelect, RelationTrait};
+
+        #[derive(FromQueryResult)]
+        struct Balance {
+            person_id: i32,
+            first_name: String,
+            last_name: String,
+            cents_per_star: Option<i32>,
+            balance_cents: i64,
+        }
+
+        let db = self.database_connection.as_ref().unwrap();
+        let balances = people::Entity::find()
+            .select_only()
+            .column_as(people::Column::Id, "person_id")
+            .column_as(people::Column::FirstName, "first_name")
+            .column_as(people::Column::LastName, "last_name")
+            .column_as(people::Column::CentsPerStar, "cents_per_star")
+            .column_as(
+                Expr::cust(r#"COALESCE(SUM("allowance_entries"."amount_cents"), 0)"#),
+                "balance_cents",
+            )
+            .join(JoinType::LeftJoin, people::Relation::AllowanceEntries.def())
+            .group_by(people::Column::Id)
+            .order_by_asc(people::Column::Id)
+            .into_model::<Balance>()
+            .all(db)
+            .await?;
+
+        Ok(balances
+            .into_iter()
+            .map(|b| AllowanceBalanceResponse {
+                person_id: b.person_id,
+                first_name: b.first_name,
+                last_name: b.last_name,
+                cents_per_star: b.cents_per_star,
+                balance_cents: b.balance_cents,
+            })
+            .collect())
+    }
+
+    pub async fn get_allowance_statement(
+        &self,
+        first_name: &str,
+    ) -> Result<Option<AllowanceStatementResponse>, anyhow::Error> {
+        use crate::entity::{allowance_entries, people};
+
+        let db = self.database_connection.as_ref().unwrap();
+        let Some(person) = people::Entity::find()
+            .filter(people::Column::FirstName.eq(first_name))
+            .one(db)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        let entries = allowance_entries::Entity::find()
+            .filter(allowance_entries::Column::PersonId.eq(person.id))
+            .order_by_asc(allowance_entries::Column::CreatedAt)
+            .order_by_asc(allowance_entries::Column::Id)
+            .all(db)
+            .await?;
+
+        let mut balance_cents = 0;
+        let entries = entries
+            .into_iter()
+            .map(|e| {
+                balance_cents += e.amount_cents;
+                AllowanceEntryResponse {
+                    id: e.id,
+                    kind: e.kind,
+                    amount_cents: e.amount_cents,
+                    balance_cents,
+                    star_chart_id: e.star_chart_id,
+                    stars: e.stars,
+                    note: e.note,
+                    actor: e.actor,
+                    created_at: e.created_at.to_rfc3339(),
+                }
+            })
+            .collect();
+
+        Ok(Some(AllowanceStatementResponse {
+            person_id: person.id,
+            first_name: person.first_name,
+            last_name: person.last_name,
+            cents_per_star: person.cents_per_star,
+            balance_cents,
+            entries,
+        }))
+    }
+
+    pub async fn update_allowance(
+        &self,
+        first_name: &str,
+        update: &UpdateAllowanceRequest,
+    ) -> Result<(), anyhow::Error> {
+        use crate::entity::people;
+
+        if update.cents_per_star.is_some_and(|c| c < 0) {
+            return Err(HGError::Invalid("cents_per_star cannot be negative".to_string()).into());
+        }
+        let db = self.database_connection.as_ref().unwrap();
+        let Some(person) = people::Entity::find()
+            .filter(people::Column::FirstName.eq(first_name))
+            .one(db)
+            .await?
+        else {
+            return Err(HGError::NotFound(format!("No person named {}", first_name)).into());
+        };
+
+        let mut am: people::ActiveModel = person.into();
+        am.cents_per_star = Set(update.cents_per_star);
+        am.update(db).await?;
+        Ok(())
+    }
+
+    /// Hand money over, which can never take the balance below zero
+    pub async fn record_payout(
+        &self,
+        first_name: &str,
+        payout: &AllowanceEntryRequest,
+    ) -> Result<AllowanceEntryResponse, anyhow::Error> {
+        use crate::entity::allowance_entries::EntryKind;
+
+        if payout.amount_cents <= 0 {
+            return Err(HGError::Invalid("payouts must be a positive amount".to_string()).into());
+        }
+        self.record_allowance_entry(first_name, EntryKind::Payout, -payout.amount_cents, payout)
+            .await
+    }
+
+    pub async fn record_adjustment(
+        &self,
+        first_name: &str,
+        adjustment: &AllowanceEntryRequest,
+    ) -> Result<AllowanceEntryResponse, anyhow::Error> {
+        use crate::entity::allowance_entries::EntryKind;
+
+        if adjustment.amount_cents == 0 {
+            return Err(HGError::Invalid("adjustments cannot be zero".to_string()).into());
+        }
+        self.record_allowance_entry(
+            first_name,
+            EntryKind::Adjustment,
+            adjustment.amount_cents,
+            adjustment,
+        )
+        .await
+    }
+
+    async fn record_allowance_entry(
+        &self,
+        first_name: &str,
+        kind: crate::entity::allowance_entries::EntryKind,
+        amount_cents: i64,
+        request: &AllowanceEntryRequest,
+    ) -> Result<AllowanceEntryResponse, anyhow::Error> {
+        use crate::entity::{allowance_entries, people};
+
+        let db = self.database_connection.as_ref().unwrap();
+        let txn = db.begin().await?;
+        let Some(person) = people::Entity::find()
+            .filter(people::Column::FirstName.eq(first_name))
+            .one(&txn)
+            .await?
+        else {
+            return Err(HGError::NotFound(format!("No person named {}", first_name)).into());
+        };
+
+        let balance_cents = allowance_balance(&txn, person.id).await? + amount_cents;
+        if balance_cents < 0 {
+            return Err(HGError::Conflict(format!(
+                "{} only has {} cents",
+                person.first_name,
+                balance_cents - amount_cents
+            ))
+            .into());
+        }
+
+        let entry = allowance_entries::ActiveModel {
+            person_id: Set(person.id),
+            kind: Set(kind),
+            amount_cents: Set(amount_cents),
+            note: Set(request.note.clone()),
+            actor: Set(request.actor.clone()),
+            created_at: Set(Utc::now()),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+        txn.commit().await?;
+
+        Ok(AllowanceEntryResponse {
+            id: entry.id,
+            kind: entry.kind,
+            amount_cents: entry.amount_cents,
+            balance_cents,
+            star_chart_id: None,
+            stars: None,
+            note: entry.note,
+            actor: entry.actor,
+            created_at: entry.created_at.to_rfc3339(),
+        })
+    }
+}
+
+impl SQLConnector {
+    pub async fn list_chores(&self) -> Result<Vec<ChoreResponse>, anyhow::Error> {
+        use crate::entity::chores;
+
+        let db = self.database_connection.as_ref().unwrap();
+        let chores = chores::Entity::find()
+            .order_by_asc(chores::Column::Id)
+            .all(db)
+            .await?;
+        Ok(chores.into_iter().map(chore_response).collect())
+    }
+
+    pub async fn create_chore(
+        &self,
+        chore: &CreateChoreRequest,
+    ) -> Result<ChoreResponse, anyhow::Error> {
+        use crate::entity::{chores, people};
+
+        let db = self.database_connection.as_ref().unwrap();
+        let assignee_ids = match (&chore.assignee_ids, chore.parent_id) {
+            (Some(ids), None) => ids.clone(),
+            (None, Some(parent_id)) => crate::entity::helpers::children_of(db, parent_id)
+                .await?
+                .into_iter()
+                .map(|c| c.id)
+                .collect(),
+            _ => {
+                return Err(HGError::Invalid(
+                    "set exactly one of assignee_ids or parent_id".to_string(),
+                )
+                .into());
+            }
+        };
+        if assignee_ids.is_empty() {
+            return Err(HGError::Invalid(format!("{} has nobody to do it", chore.name)).into());
+        }
+        for id in &assignee_ids {
+            if people::Entity::find_by_id(*id).one(db).await?.is_none() {
+                return Err(HGError::NotFound(format!("person {} not found", id)).into());
+            }
+        }
+        let starts_on = match &chore.starts_on {
+            Some(date) => parse_date(date)?,
+            None => chrono::Local::now().date_naive(),
+        };
+
+        let res = chores::ActiveModel {
+            name: Set(chore.name.clone()),
+            description: Set(chore.description.clone()),
+            rotation: Set(chore.rotation),
+            assignee_ids: Set(assignee_ids
+                .iter()
+                .map(|id| id.to_string())
+                .collect::<Vec<_>>()
+                .join(",")),
+            starts_on: Set(starts_on),
+            created_at: Set(Utc::now()),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+        self.schedule_chores().await?;
+
+        Ok(chore_response(res))
+    }
+
+    pub async fn delete_chore(&self, chore_id: i32) -> Result<(), anyhow::Error> {
+        use crate::entity::chores;
+
+        let db = self.database_connection.as_ref().unwrap();
+        let res = chores::Entity::delete_by_id(chore_id).exec(db).await?;
+        if res.rows_affected == 0 {
+            return Err(HGError::NotFound(format!("chore {} not found", chore_id)).into());
+        }
+        Ok(())
+    }
+
+    /// Make sure every chore has an assignment for today and the configured
+    /// number of days ahead. Existing assignments are never moved.
+    pub async fn schedule_chores(&self) -> Result<usize, anyhow::Error> {
+        use crate::entity::{chore_assignments, chores, people};
+
+        let db = self.database_connection.as_ref().unwrap();
+        let today = chrono::Local::now().date_naive();
+        let days_ahead = self.configuration.chores.days_ahead.unwrap_or(7);
+        let last_day = today + chrono::Duration::days(i64::from(days_ahead));
+
+        // Weighted rotations use ages from the family configuration
+        let ages: HashMap<&str, u32> = self
+            .configuration
+            .family
+            .values()
+            .map(|f| (f.first_name.as_str(), f.age))
+            .collect();
+
+        let mut created = 0;
+        for chore in chores::Entity::find().all(db).await? {
+            let mut assignees = Vec::new();
+            for id in chore.assignees() {
+                if let Some(p) = people::Entity::find_by_id(id).one(db).await?
+                    && p.archived_at.is_none()
+                {
+                    let age = ages.get(p.first_name.as_str()).copied().unwrap_or(1);
+                    assignees.push((p.id, age));
+                }
+            }
+
+            let scheduled: Vec<chrono::NaiveDate> = chore_assignments::Entity::find()
+                .filter(chore_assignments::Column::ChoreId.eq(chore.id))
+                .filter(chore_assignments::Column::DueOn.gte(today))
+                .all(db)
+                .await?
+                .into_iter()
+                .map(|a| a.due_on)
+                .collect();
+
+            let mut day = today.max(chore.starts_on);
+            while day <= last_day {
+                if !scheduled.contains(&day)
+                    && let Some(person_id) =
+                        chore.rotation.assignee(&assignees, chore.starts_on, day)
+                {
+                    chore_assignments::ActiveModel {
+                        chore_id: Set(chore.id),
+                        person_id: Set(person_id),
+                        due_on: Set(day),
+                        ..Default::default()
+                    }
+                    .insert(db)
+                    .await?;
+                    created += 1;
+                }
+                day += chrono::Duration::days(1);
+            }
+        }
+        Ok(created)
+    }
+
+    pub async fn list_chore_assignments(
+        &self,
+        query: &ChoreAssignmentsQuery,
+    ) -> Result<Vec<ChoreAssignmentResponse>, anyhow::Error> {
+        use crate::entity::{chore_assignments, chores, people};
+
+        let date = match &query.date {
+            Some(date) => parse_date(date)?,
+            None => chrono::Local::now().date_naive(),
+        };
+        let db = self.database_connection.as_ref().unwrap();
+        let mut assignments = chore_assignments::Entity::find()
+            .filter(chore_assignments::Column::DueOn.eq(date))
+            .order_by_asc(chore_assignments::Column::ChoreId);
+        if let Some(person_id) = query.person_id {
+            assignments = assignments.filter(chore_assignments::Column::PersonId.eq(person_id));
+        }
+
+        let mut results = Vec::new();
+        for a in assignments.all(db).await? {
+            let chore = chores::Entity::find_by_id(a.chore_id).one(db).await?;
+            let person = people::Entity::find_by_id(a.person_id).one(db).await?;
+            results.push(ChoreAssignmentResponse {
+                id: a.id,
+                chore_id: a.chore_id,
+                chore_name: chore.map(|c| c.name).unwrap_or_default(),
+                person_id: a.person_id,
+                person_first_name: person.map(|p| p.first_name).unwrap_or_default(),
+                due_on: a.due_on.to_string(),
+                completed_at: a.completed_at.map(|d| d.to_rfc3339()),
+                completed_by: a.completed_by,
+            });
+        }
+        Ok(results)
+    }
+
+    pub async fn complete_chore_assignment(
+        &self,
+        assignment_id: i32,
+        complete: &CompleteChoreRequest,
+    ) -> Result<(), anyhow::Error> {
+        use crate::entity::chore_assignments;
+
+        let db = self.database_connection.as_ref().unwrap();
+        let Some(assignment) = chore_assignments::Entity::find_by_id(assignment_id)
+            .one(db)
+            .await?
+        else {
+            return Err(
+                HGError::NotFound(format!("chore assignment {} not found", assignment_id)).into(),
+            );
+        };
+        if assignment.completed_at.is_some() {
+            return Err(HGError::Conflict(format!(
+                "chore assignment {} is already done",
+                assignment_id
+            ))
+            .into());
+        }
+
+        let mut am: chore_assignments::ActiveModel = assignment.into();
+        am.completed_at = Set(Some(Utc::now()));
+        am.completed_by = Set(complete.completed_by.clone());
+        am.update(db).await?;
+        Ok(())
+    }
+}
+
+fn chore_response(c: crate::entity::chores::Model) -> ChoreResponse {
+    ChoreResponse {
+        assignee_ids: c.assignees(),
+        id: c.id,
+        name: c.name,
+        description: c.description,
+        rotation: c.rotation,
+        starts_on: c.starts_on.to_string(),
+    }
+}
+
+fn parse_date(date: &str) -> Result<chrono::NaiveDate, HGError> {
+    chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|_| HGError::Invalid(format!("{} is not a YYYY-MM-DD date", date)))
+}
+
+/// An RFC 3339 timestamp, or a local date meaning the start of that day, or
+/// its end when it is a due date
+fn parse_deadline(value: &str, end_of_day: bool) -> Result<chrono::DateTime<Utc>, HGError> {
+    use chrono::{Local, TimeZone};
+
+    if let Ok(at) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Ok(at.with_timezone(&Utc));
+    }
+    let mut day = parse_date(value)?;
+    if end_of_day {
+        day = day.succ_opt().unwrap_or(day);
+    }
+    let midnight = day.and_hms_opt(0, 0, 0).unwrap();
+    Ok(Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .map(|d| d.with_timezone(&Utc))
+        .unwrap_or_else(|| midnight.and_utc()))
+}
+
+fn check_deadlines(
+    starts_at: Option<chrono::DateTime<Utc>>,
+    due_at: Option<chrono::DateTime<Utc>>,
+) -> Result<(), HGError> {
+    if let (Some(starts_at), Some(due_at)) = (starts_at, due_at)
+        && due_at <= starts_at
+    {
+        return Err(HGError::Invalid(
+            "due_at must be after starts_at".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+impl SQLConnector {
+    pub async fn list_bounties(
+        &self,
+        query: &ListBountiesQuery,
+    ) -> Result<Vec<BountyResponse>, anyhow::Error> {
+        use crate::entity::bounties;
+
+        let db = self.database_connection.as_ref().unwrap();
+        let mut select = bounties::Entity::find().order_by_desc(bounties::Column::CreatedAt);
+        if let Some(status) = &query.status {
+            select = select.filter(bounties::Column::Status.eq(status.clone()));
+        }
+        let bounties = select.all(db).await?;
+        Ok(bounties.into_iter().map(bounty_response).collect())
+    }
+
+    pub async fn create_bounty(
+        &self,
+        bounty: &CreateBountyRequest,
+        caller: Option<&Caller>,
+    ) -> Result<BountyResponse, anyhow::Error> {
+        use crate::entity::bounties::{self, BountyStatus};
+
+        let parent = require_parent(caller, "post bounties")?;
+        let db = self.database_connection.as_ref().unwrap();
+        if bounty.reward <= 0 {
+            return Err(HGError::Invalid("reward must be at least one star".to_string()).into());
+        }
+
+        let res = bounties::ActiveModel {
+            title: Set(bounty.title.clone()),
+            description: Set(bounty.description.clone()),
+            reward: Set(bounty.reward),
+            status: Set(BountyStatus::Open),
+            posted_by: Set(parent.first_name.clone()),
+            created_at: Set(Utc::now()),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+        Ok(bounty_response(res))
+    }
+
+    pub async fn delete_bounty(&self, bounty_id: i32) -> Result<(), anyhow::Error> {
+        use crate::entity::bounties;
+
+        let db = self.database_connection.as_ref().unwrap();
+        let res = bounties::Entity::delete_by_id(bounty_id).exec(db).await?;
+        if res.rows_affected == 0 {
+            return Err(HGError::NotFound(format!("bounty {} not found", bounty_id)).into());
+        }
+        Ok(())
+    }
+
+    /// First child to claim an open bounty gets it; anyone racing them is
+    /// refused because the claim only applies while it is still open.
+    pub async fn claim_bounty(
+        &self,
+        bounty_id: i32,
+        claim: &ClaimBountyRequest,
+    ) -> Result<BountyResponse, anyhow::Error> {
+        use crate::entity::bounties::{self, BountyStatus};
+
+        let db = self.database_connection.as_ref().unwrap();
+        if crate::entity::helpers::parents_of(db, claim.person_id)
+            .await?
+            .is_empty()
+        {
+            return Err(HGError::Forbidden(format!(
+                "person {} is not a child and cannot claim bounties",
+                claim.person_id
+            ))
+            .into());
+        }
+        bounty_chart(db, claim.person_id, claim.star_chart_id).await?;
+
+        let am = bounties::ActiveModel {
+            status: Set(BountyStatus::Claimed),
+            claimed_by: Set(Some(claim.person_id)),
+            star_chart_id: Set(Some(claim.star_chart_id)),
+            claimed_at: Set(Some(Utc::now())),
+            ..Default::default()
+        };
+        let res = transition_bounty(db, bounty_id, BountyStatus::Open, am).await?;
+        Ok(bounty_response(res))
+    }
+
+    pub async fn complete_bounty(
+        &self,
+        bounty_id: i32,
+        complete: &CompleteBountyRequest,
+    ) -> Result<BountyResponse, anyhow::Error> {
+        use crate::entity::bounties::{self, BountyStatus};
+
+        let db = self.database_connection.as_ref().unwrap();
+        let Some(bounty) = bounties::Entity::find_by_id(bounty_id).one(db).await? else {
+            return Err(HGError::NotFound(format!("bounty {} not found", bounty_id)).into());
+        };
+        if bounty.claimed_by != Some(complete.person_id) {
+            return Err(HGError::Forbidden(format!(
+                "bounty {} was not claimed by person {}",
+                bounty_id, complete.person_id
+            ))
+            .into());
+        }
+
+        let am = bounties::ActiveModel {
+            status: Set(BountyStatus::Done),
+            completed_at: Set(Some(Utc::now())),
+            ..Default::default()
+        };
+        let res = transition_bounty(db, bounty_id, BountyStatus::Claimed, am).await?;
+        Ok(bounty_response(res))
+    }
+
+    /// A parent confirms the work, crediting the reward to the chart the
+    /// claimant picked
+    /// Pay a done bounty out. The reward goes to the chart chosen when it
+    /// was claimed, unless `verify` names another chart of the claimant, for
+    /// when that one can no longer take the stars.
+    pub async fn verify_bounty(
+        &self,
+        bounty_id: i32,
+        verify: &VerifyBountyRequest,
+        caller: Option<&Caller>,
+    ) -> Result<BountyResponse, anyhow::Error> {
+        use crate::entity::bounties::{self, BountyStatus};
+
+        let parent = require_parent(caller, "verify bounties")?;
+        let db = self.database_connection.as_ref().unwrap();
+        let txn = db.begin().await?;
+
+        let mut am = bounties::ActiveModel {
+            status: Set(BountyStatus::Verified),
+            verified_by: Set(Some(parent.first_name.clone())),
+            verified_at: Set(Some(Utc::now())),
+            ..Default::default()
+        };
+        if let Some(star_chart_id) = verify.star_chart_id {
+            am.star_chart_id = Set(Some(star_chart_id));
+        }
+        let bounty = transition_bounty(&txn, bounty_id, BountyStatus::Done, am).await?;
+
+        let claimed_by = bounty
+            .claimed_by
+            .context("verified bounty has no claimant")?;
+        let chart_id = bounty
+            .star_chart_id
+            .context("verified bounty has no chart")?;
+        let chart = bounty_chart(&txn, claimed_by, chart_id).await?;
+        ensure_room(&chart, bounty.reward)?;
+        let (chart, completed) = apply_increment(
+            &txn,
+            chart,
+            bounty.reward,
+            Some(format!("bounty {}", bounty.title)),
+            Some(parent.first_name.clone()),
+            StarEventKind::Bounty,
+        )
+        .await?;
+        if completed {
+            unlock_quests(&txn, chart.id).await?;
+        }
+        txn.commit().await?;
+        self.refresh_progress(chart.person_id).await;
+
+        Ok(bounty_response(bounty))
+    }
+}
+
+/// The caller, as long as the server recognised them as a parent
+fn require_parent<'a>(caller: Option<&'a Caller>, action: &str) -> Result<&'a Caller, HGError> {
+    caller
+        .filter(|c| c.is_parent)
+        .ok_or_else(|| HGError::Forbidden(format!("only a parent can {}", action)))
+}
+
+/// Only a chart's owner or a parent can take stars off it for someone else
+async fn require_owner_or_parent<C: ConnectionTrait>(
+    db: &C,
+    caller: Option<&Caller>,
+    chart: &crate::entity::star_charts::Model,
+    action: &str,
+) -> Result<(), anyhow::Error> {
+    let owner = crate::entity::people::Entity::find_by_id(chart.person_id)
+        .one(db)
+        .await?
+        .map(|p| p.first_name)
+        .unwrap_or_default();
+    if !caller.is_some_and(|c| c.is_parent || c.first_name == owner) {
+        return Err(HGError::Forbidden(format!(
+            "only {} or a parent can {} {}",
+            owner, action, chart.chart_type
+        ))
+        .into());
+    }
+    Ok(())
+}
+
+/// A chart of `person_id` that a bounty's reward can go to
+async fn bounty_chart<C: ConnectionTrait>(
+    db: &C,
+    person_id: i32,
+    star_chart_id: i32,
+) -> Result<crate::entity::star_charts::Model, anyhow::Error> {
+    let chart = crate::entity::star_charts::Entity::find_by_id(star_chart_id)
+        .one(db)
+        .await?;
+    match chart {
+        Some(c) if c.person_id == person_id && c.archived_at.is_none() => {
+            if !c.status.takes_stars() {
+                return Err(HGError::Conflict(format!(
+                    "{} is {}",
+                    c.chart_type,
+                    c.status.to_value()
+                ))
+                .into());
+            }
+            Ok(c)
+        }
+        _ => Err(HGError::NotFound(format!(
+            "star chart {} not found for person {}",
+            star_chart_id, person_id
+        ))
+        .into()),
+    }
+}
+
+/// Move a bounty on only if it is still in `from`, so two requests can't
+/// both make the same transition
+async fn transition_bounty<C: ConnectionTrait>(
+    db: &C,
+    bounty_id: i32,
+    from: crate::entity::bounties::BountyStatus,
+    am: crate::entity::bounties::ActiveModel,
+) -> Result<crate::entity::bounties::Model, anyhow::Error> {
+    use crate::entity::bounties;
+
+    let result = bounties::Entity::update_many()
+        .set(am)
+        .filter(bounties::Column::Id.eq(bounty_id))
+        .filter(bounties::Column::Status.eq(from.clone()))
+        .exec(db)
+        .await?;
+    let bounty = bounties::Entity::find_by_id(bounty_id).one(db).await?;
+    match bounty {
+        None => Err(HGError::NotFound(format!("bounty {} not found", bounty_id)).into()),
+        Some(b) if result.rows_affected == 0 => Err(HGError::Conflict(format!(
+            "bounty {} is {}, not {}",
+            bounty_id,
+            b.status.to_value(),
+            from.to_value()
+        ))
+        .into()),
+        Some(b) => Ok(b),
+    }
+}
+
+fn bounty_response(b: crate::entity::bounties::Model) -> BountyResponse {
+    BountyResponse {
+        id: b.id,
+        title: b.title,
+        description: b.description,
+        reward: b.reward,
+        status: b.status,
+        posted_by: b.posted_by,
+        created_at: b.created_at.to_rfc3339(),
+        claimed_by: b.claimed_by,
+        star_chart_id: b.star_chart_id,
+        claimed_at: b.claimed_at.map(|d| d.to_rfc3339()),
+        completed_at: b.completed_at.map(|d| d.to_rfc3339()),
+        verified_by: b.verified_by,
+        verified_at: b.verified_at.map(|d| d.to_rfc3339()),
+    }
+}
+
+impl SQLConnector {
+    /// Replace the charts this one requires, locking or unlocking it to match
+    pub async fn set_star_chart_prerequisites(
+        &self,
+        star_chart_id: i32,
+        request: &SetPrerequisitesRequest,
+    ) -> Result<(), anyhow::Error> {
+        use crate::entity::{star_chart_prerequisites, star_charts};
+
+        let db = self.database_connection.as_ref().unwrap();
+        let txn = db.begin().await?;
+        let Some(chart) = star_charts::Entity::find_by_id(star_chart_id)
+            .one(&txn)
+            .await?
+        else {
+            return Err(
+                HGError::NotFound(format!("star chart {} not found", star_chart_id)).into(),
+            );
+        };
+        let met =
+            prerequisites_met(&txn, chart.person_id, Some(chart.id), &request.requires).await?;
+
+        star_chart_prerequisites::Entity::delete_many()
+            .filter(star_chart_prerequisites::Column::StarChartId.eq(chart.id))
+            .exec(&txn)
+            .await?;
+        for requires_id in &request.requires {
+            star_chart_prerequisites::ActiveModel {
+                star_chart_id: Set(chart.id),
+                requires_id: Set(*requires_id),
+                ..Default::default()
+            }
+            .insert(&txn)
+            .await?;
+        }
+
+        let status = match chart.status {
+            ChartStatus::Active if !met => Some(ChartStatus::Locked),
+            ChartStatus::Locked if met => Some(ChartStatus::Active),
+            _ => None,
+        };
+        if let Some(status) = status {
+            let version = chart.version;
+            let (star_count, star_total) = (chart.star_count, chart.star_total);
+            let mut am: star_charts::ActiveModel = chart.into();
+            am.status = Set(status);
+            set_star_count(&mut am, &status, star_count, star_total);
+            save_star_chart(&txn, am, version).await?;
+        }
+        txn.commit().await?;
+        Ok(())
+    }
+
+    /// Charts of the person that take part in a quest chain, with the links
+    /// either way so the frontend can draw the tree
+    pub async fn get_person_quests(
+        &self,
+        first_name: &str,
+    ) -> Result<Option<Vec<QuestResponse>>, anyhow::Error> {
+        use crate::entity::{people, star_chart_prerequisites, star_charts};
+
+        let db = self.database_connection.as_ref().unwrap();
+        let Some(person) = people::Entity::find()
+            .filter(people::Column::FirstName.eq(first_name))
+            .one(db)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        let charts = star_charts::Entity::find()
+            .filter(star_charts::Column::PersonId.eq(person.id))
+            .order_by_asc(star_charts::Column::Id)
+            .all(db)
+            .await?;
+        let links = star_chart_prerequisites::Entity::find()
+            .filter(
+                star_chart_prerequisites::Column::StarChartId
+                    .is_in(charts.iter().map(|c| c.id).collect::<Vec<_>>()),
+            )
+            .all(db)
+            .await?;
+
+        Ok(Some(
+            charts
+                .into_iter()
+                .filter(|c| {
+                    links
+                        .iter()
+                        .any(|l| l.star_chart_id == c.id || l.requires_id == c.id)
+                })
+                .map(|c| QuestResponse {
+                    requires: links
+                        .iter()
+                        .filter(|l| l.star_chart_id == c.id)
+                        .map(|l| l.requires_id)
+                        .collect(),
+                    unlocks: links
+                        .iter()
+                        .filter(|l| l.requires_id == c.id)
+                        .map(|l| l.star_chart_id)
+                        .collect(),
+                    star_chart_id: c.id,
+                    name: c.chart_type,
+                    status: c.status,
+                    star_count: c.star_count,
+                    star_total: c.star_total,
+                })
+                .collect(),
+        ))
+    }
+}
+
+impl SQLConnector {
+    /// Really delete people and charts archived longer ago than the
+    /// retention period
+    pub async fn purge_archived(&self) -> Result<PurgeResponse, anyhow::Error> {
+        use crate::entity::{people, star_charts};
+
+        let db = self.database_connection.as_ref().unwrap();
+        let retention_days = self.configuration.archive.retention_days.unwrap_or(30);
+        let cutoff = Utc::now() - chrono::Duration::days(i64::from(retention_days));
+
+        let expired_people = people::Entity::find()
+            .filter(people::Column::ArchivedAt.lt(cutoff))
+            .all(db)
+            .await?;
+        for p in &expired_people {
+            self.delete_person(p.id).await?;
+        }
+
+        let expired_charts = star_charts::Entity::find()
+            .filter(star_charts::Column::ArchivedAt.lt(cutoff))
+            .all(db)
+            .await?;
+        for c in &expired_charts {
+            self.delete_star_chart(c.id).await?;
+        }
+
+        Ok(PurgeResponse {
+            people: expired_people.len(),
+            star_charts: expired_charts.len(),
+        })
+    }
+}
+
+impl SQLConnector {
+    pub async fn list_goals(&self) -> Result<Vec<GoalResponse>, anyhow::Error> {
+        use crate::entity::goals;
+
+        let db = self.database_connection.as_ref().unwrap();
+        let goals = goals::Entity::find()
+            .order_by_asc(goals::Column::Id)
+            .all(db)
+            .await?;
+        let mut results = Vec::new();
+        for g in goals {
+            results.push(goal_response(db, g).await?);
+        }
+        Ok(results)
+    }
+
+    pub async fn get_goal(&self, goal_id: i32) -> Result<GoalResponse, anyhow::Error> {
+        let db = self.database_connection.as_ref().unwrap();
+        let goal = crate::entity::goals::Entity::find_by_id(goal_id)
+            .one(db)
+            .await?
+            .ok_or_else(|| HGError::NotFound(format!("goal {} not found", goal_id)))?;
+        goal_response(db, goal).await
+    }
+
+    pub async fn create_goal(
+        &self,
+        goal: &CreateGoalRequest,
+    ) -> Result<GoalResponse, anyhow::Error> {
+        use crate::entity::goals;
+
+        let db = self.database_connection.as_ref().unwrap();
+        if goal.star_total <= 0 {
+            return Err(
+                HGError::Invalid("star_total must be at least one star".to_string()).into(),
+            );
+        }
+
+        let res = goals::ActiveModel {
+            name: Set(goal.name.clone()),
+            description: Set(goal.description.clone()),
+            star_count: Set(0),
+            star_total: Set(goal.star_total),
+            color: Set(goal.color.clone()),
+            status: Set(ChartStatus::Active),
+            created_at: Set(Utc::now()),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+        goal_response(db, res).await
+    }
+
+    pub async fn delete_goal(&self, goal_id: i32) -> Result<(), anyhow::Error> {
+        use crate::entity::goals;
+
+        let db = self.database_connection.as_ref().unwrap();
+        let res = goals::Entity::delete_by_id(goal_id).exec(db).await?;
+        if res.rows_affected == 0 {
+            return Err(HGError::NotFound(format!("goal {} not found", goal_id)).into());
+        }
+        Ok(())
+    }
+
+    /// Add (or take back) stars on a household goal on someone's behalf.
+    /// The count stays between zero and the goal's total and only what was
+    /// actually applied is recorded against the contributor.
+    /// Stars put into a goal come off the contributor's own chart, and any
+    /// taken back out return to it. They were earned on the chart, so XP and
+    /// allowance don't move either way.
+    pub async fn contribute_to_goal(
+        &self,
+        goal_id: i32,
+        contribution: &ContributeGoalRequest,
+        caller: Option<&Caller>,
+    ) -> Result<GoalResponse, anyhow::Error> {
+        use crate::entity::{goal_contributions, goals, star_charts};
+        use sea_orm::sea_query::ExprTrait;
+
+        let db = self.database_connection.as_ref().unwrap();
+        let txn = db.begin().await?;
+        let goal = goals::Entity::find_by_id(goal_id)
+            .one(&txn)
+            .await?
+            .ok_or_else(|| HGError::NotFound(format!("goal {} not found", goal_id)))?;
+        let chart = star_charts::Entity::find_by_id(contribution.star_chart_id)
+            .one(&txn)
+            .await?
+            .ok_or_else(|| {
+                HGError::NotFound(format!(
+                    "star chart {} not found",
+                    contribution.star_chart_id
+                ))
+            })?;
+        require_owner_or_parent(&txn, caller, &chart, "spend stars from").await?;
+        if goal.status != ChartStatus::Active {
+            return Err(
+                HGError::Conflict(format!("{} is {}", goal.name, goal.status.to_value())).into(),
+            );
+        }
+
+        let person_id = chart.person_id;
+        let new_count = goal
+            .star_count
+            .saturating_add(contribution.delta)
+            .clamp(0, goal.star_total);
+        let applied = new_count - goal.star_count;
+        if applied > chart.star_count {
+            return Err(HGError::Conflict(format!(
+                "{} only has {} stars",
+                chart.chart_type, chart.star_count
+            ))
+            .into());
+        }
+        // Only what this person put in can come back out
+        if applied < 0 {
+            let given: i32 = goal_contributions::Entity::find()
+                .filter(goal_contributions::Column::GoalId.eq(goal.id))
+                .filter(goal_contributions::Column::PersonId.eq(person_id))
+                .all(&txn)
+                .await?
+                .iter()
+                .map(|c| c.delta)
+                .sum();
+            if -applied > given {
+                return Err(HGError::Conflict(format!(
+                    "only {} stars in {} came from this person",
+                    std::cmp::max(given, 0),
+                    goal.name
+                ))
+                .into());
+            }
+            ensure_room(&chart, -applied)?;
+        }
+        if applied == 0 {
+            txn.commit().await?;
+            return goal_response(db, goal).await;
+        }
+
+        let mut am = goals::ActiveModel {
+            id: Set(goal.id),
+            ..Default::default()
+        };
+        if new_count >= goal.star_total {
+            am.status = Set(ChartStatus::Completed);
+            am.completed_at = Set(Some(Utc::now()));
+        }
+        // Applied against the stored count and only while the count is the
+        // one we clamped from, so two people adding at once can't overshoot
+        let result = goals::Entity::update_many()
+            .set(am)
+            .col_expr(
+                goals::Column::StarCount,
+                Expr::col(goals::Column::StarCount).add(applied),
+            )
+            .filter(goals::Column::Id.eq(goal.id))
+            .filter(goals::Column::StarCount.eq(goal.star_count))
+            .exec(&txn)
+            .await?;
+        if result.rows_affected == 0 {
+            return Err(HGError::Conflict(format!("goal {} changed", goal.id)).into());
+        }
+
+        let note = if applied > 0 {
+            format!("goal {}", goal.name)
+        } else {
+            format!("back from goal {}", goal.name)
+        };
+        let (res, completed) = apply_increment(
+            &txn,
+            chart,
+            -applied,
+            Some(note),
+            caller.map(|c| c.first_name.clone()),
+            StarEventKind::Goal,
+        )
+        .await?;
+        if completed {
+            unlock_quests(&txn, res.id).await?;
+        }
+        goal_contributions::ActiveModel {
+            goal_id: Set(goal.id),
+            person_id: Set(person_id),
+            delta: Set(applied),
+            star_chart_id: Set(Some(res.id)),
+            note: Set(contribution.note.clone()),
+            created_at: Set(Utc::now()),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+        txn.commit().await?;
+
+        self.refresh_progress(person_id).await;
+        self.get_goal(goal_id).await
+    }
+}
+
+/// A goal with its net stars per contributor, biggest first
+async fn goal_response<C: ConnectionTrait>(
+    db: &C,
+    goal: crate::entity::goals::Model,
+) -> Result<GoalResponse, anyhow::Error> {
+    use crate::entity::{goal_contributions, people};
+    use sea_orm::{FromQueryResult, JoinType, QuerySelect, RelationTrait};
+
+    #[derive(FromQueryResult)]
+    struct ContributorRow {
+        person_id: i32,
+        first_name: String,
+        stars: i32,
+        contributions: i32,
+    }
+
+    let rows = goal_contributions::Entity::find()
+        .select_only()
+        .column(goal_contributions::Column::PersonId)
+        .column_as(people::Column::FirstName, "first_name")
+        .column_as(Expr::cust("SUM(\"goal_contributions\".\"delta\")"), "stars")
+        .column_as(Expr::cust("COUNT(*)"), "contributions")
+        .join(
+            JoinType::InnerJoin,
+            goal_contributions::Relation::People.def(),
+        )
+        .filter(goal_contributions::Column::GoalId.eq(goal.id))
+        .group_by(goal_contributions::Column::PersonId)
+        .group_by(people::Column::FirstName)
+        .into_model::<ContributorRow>()
+        .all(db)
+        .await?;
+
+    let mut contributors: Vec<GoalContributorResponse> = rows
+        .into_iter()
+        .map(|r| GoalContributorResponse {
+            person_id: r.person_id,
+            first_name: r.first_name,
+            stars: r.stars,
+            contributions: r.contributions,
+        })
+        .collect();
+    contributors.sort_by(|a, b| b.stars.cmp(&a.stars).then(a.person_id.cmp(&b.person_id)));
+
+    Ok(GoalResponse {
+        id: goal.id,
+        name: goal.name,
+        description: goal.description,
+        star_count: goal.star_count,
+        star_total: goal.star_total,
+        color: goal.color,
+        status: goal.status,
+        created_at: goal.created_at.to_rfc3339(),
+        completed_at: goal.completed_at.map(|d| d.to_rfc3339()),
+        contributors,
+    })
+}
+
+// Aggregates are computed by SQLite, dates are bucketed in local time
+impl SQLConnector {
+    pub async fn get_person_stats(
+        &self,
+        query: &StatsQuery,
+    ) -> Result<Vec<PersonStatsResponse>, anyhow::Error> {
+        use crate::entity::{people, star_charts, star_events};
+        use sea_orm::sea_query::{ExprTrait, IntoCondition};
+        use sea_orm::{FromQueryResult, JoinType, QuerySelect, RelationTrait};
+
+        #[derive(FromQueryResult)]
+        struct ChartTotals {
+            person_id: i32,
+            first_name: String,
+            last_name: String,
+            chart_count: i64,
+            completed_charts: i64,
+            completion_percent: i64,
+        }
+        #[derive(FromQueryResult)]
+        struct EarnedTotals {
+            person_id: i32,
+            stars_earned: i64,
+        }
+
+        let db = self.database_connection.as_ref().unwrap();
+        let mut totals = people::Entity::find()
+            .select_only()
+            .column_as(people::Column::Id, "person_id")
+            .column_as(people::Column::FirstName, "first_name")
+            .column_as(people::Column::LastName, "last_name")
+            .column_as(Expr::cust(r#"COUNT("star_charts"."id")"#), "chart_count")
+            .column_as(Expr::cust(COMPLETED_CHARTS_SQL), "completed_charts")
+            .column_as(
+                Expr::cust(format!(
+                    r#"{} * 100 / MAX(COUNT("star_charts"."id"), 1)"#,
+                    COMPLETED_CHARTS_SQL
+                )),
+                "completion_percent",
+            )
+            .join(
+                JoinType::LeftJoin,
+                people::Relation::StarCharts.def().on_condition(|_, chart| {
+                    Expr::col((chart, star_charts::Column::ArchivedAt))
+                        .is_null()
+                        .into_condition()
+                }),
+            )
+            .filter(people::Column::ArchivedAt.is_null())
+            .group_by(people::Column::Id)
+            .order_by_asc(people::Column::Id);
+        if let Some(person_id) = query.person_id {
+            totals = totals.filter(people::Column::Id.eq(person_id));
+        }
+        let totals = totals.into_model::<ChartTotals>().all(db).await?;
+
+        let earned = star_events::Entity::find()
+            .select_only()
+            .column_as(star_charts::Column::PersonId, "person_id")
+            .column_as(Expr::cust(r#"SUM("star_events"."delta")"#), "stars_earned")
+            .join(JoinType::InnerJoin, star_events::Relation::StarCharts.def())
+            .filter(star_charts::Column::ArchivedAt.is_null())
+            .filter(star_events::Column::Delta.gt(0))
+            .filter(stats_range(query)?)
+            .group_by(star_charts::Column::PersonId)
+            .into_model::<EarnedTotals>()
+            .all(db)
+            .await?;
+        let earned: HashMap<i32, i64> = earned
+            .into_iter()
+            .map(|e| (e.person_id, e.stars_earned))
+            .collect();
+
+        Ok(totals
+            .into_iter()
+            .map(|t| PersonStatsResponse {
+                stars_earned: earned.get(&t.person_id).copied().unwrap_or_default(),
+                person_id: t.person_id,
+                first_name: t.first_name,
+                last_name: t.last_name,
+                chart_count: t.chart_count,
+                completed_charts: t.completed_charts,
+                completion_percent: t.completion_percent,
+            })
+            .collect())
+    }
+
+    pub async fn get_stars_per_period(
+        &self,
+        query: &StatsQuery,
+    ) -> Result<Vec<StarsPerPeriodResponse>, anyhow::Error> {
+        use crate::entity::{star_charts, star_events};
+        use sea_orm::{FromQueryResult, JoinType, QuerySelect, RelationTrait};
+
+        #[derive(FromQueryResult)]
+        struct Bucket {
+            period: String,
+            person_id: i32,
+            stars: i64,
+        }
+
+        let period = match query.bucket {
+            StatsBucket::Day => r#"date("star_events"."created_at", 'localtime')"#,
+            // Step forward to Sunday then back to that week's Monday
+            StatsBucket::Week => {
+                r#"date("star_events"."created_at", 'localtime', 'weekday 0', '-6 days')"#
+            }
+        };
+
+        let db = self.database_connection.as_ref().unwrap();
+        let mut buckets = star_events::Entity::find()
+            .select_only()
+            .column_as(Expr::cust(period), "period")
+            .column_as(star_charts::Column::PersonId, "person_id")
+            .column_as(Expr::cust(r#"SUM("star_events"."delta")"#), "stars")
+            .join(JoinType::InnerJoin, star_events::Relation::StarCharts.def())
+            .filter(star_charts::Column::ArchivedAt.is_null())
+            .filter(star_events::Column::Delta.gt(0))
+            .filter(stats_range(query)?)
+            .group_by(Expr::cust(period))
+            .group_by(star_charts::Column::PersonId)
+            .order_by_asc(Expr::cust(period))
+            .order_by_asc(star_charts::Column::PersonId);
+        if let Some(person_id) = query.person_id {
+            buckets = buckets.filter(star_charts::Column::PersonId.eq(person_id));
+        }
+
+        Ok(buckets
+            .into_model::<Bucket>()
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|b| StarsPerPeriodResponse {
+                period: b.period,
+                person_id: b.person_id,
+                stars: b.stars,
+            })
+            .collect())
+    }
+
+    /// Rank people by the stars on their charts, or by how close those
+    /// charts are to their goals. Archived charts don't count.
+    pub async fn get_leaderboard(
+        &self,
+        query: &LeaderboardQuery,
+    ) -> Result<Vec<LeaderboardEntryResponse>, anyhow::Error> {
+        use crate::entity::{people, star_charts};
+        use sea_orm::{FromQueryResult, JoinType, QuerySelect, RelationTrait};
+
+        #[derive(FromQueryResult)]
+        struct Entry {
+            rank: i64,
+            person_id: i32,
+            first_name: String,
+            last_name: String,
+            star_count: i64,
+            star_total: i64,
+            percent_of_goal: i64,
+        }
+
+        let percent =
+            r#"SUM("star_charts"."star_count") * 100 / MAX(SUM("star_charts"."star_total"), 1)"#;
+        let ranked_by = match query.rank_by {
+            LeaderboardRank::Stars => r#"SUM("star_charts"."star_count")"#,
+            LeaderboardRank::Percent => percent,
+        };
+
+        let db = self.database_connection.as_ref().unwrap();
+        let entries = star_charts::Entity::find()
+            .select_only()
+            .column_as(
+                Expr::cust(format!("RANK() OVER (ORDER BY {} DESC)", ranked_by)),
+                "rank",
+            )
+            .column_as(people::Column::Id, "person_id")
+            .column_as(people::Column::FirstName, "first_name")
+            .column_as(people::Column::LastName, "last_name")
+            .column_as(
+                Expr::cust(r#"SUM("star_charts"."star_count")"#),
+                "star_count",
+            )
+            .column_as(
+                Expr::cust(r#"SUM("star_charts"."star_total")"#),
+                "star_total",
+            )
+            .column_as(Expr::cust(percent), "percent_of_goal")
+            .join(JoinType::InnerJoin, star_charts::Relation::People.def())
+            .filter(star_charts::Column::ArchivedAt.is_null())
+            .filter(people::Column::ArchivedAt.is_null())
+            .group_by(people::Column::Id)
+            .order_by_asc(Expr::cust("rank"))
+            .order_by_asc(people::Column::FirstName)
+            .into_model::<Entry>()
+            .all(db)
+            .await?;
+
+        Ok(entries
+            .into_iter()
+            .map(|e| LeaderboardEntryResponse {
+                rank: e.rank,
+                person_id: e.person_id,
+                first_name: e.first_name,
+                last_name: e.last_name,
+                star_count: e.star_count,
+                star_total: e.star_total,
+                percent_of_goal: e.percent_of_goal,
+            })
+            .collect())
+    }
+}
+
+const COMPLETED_CHARTS_SQL: &str =
+    r#"COALESCE(SUM(CASE WHEN "star_charts"."status" = 'completed' THEN 1 ELSE 0 END), 0)"#;
+
+/// Limit star events to the query's from/to dates, both inclusive
+fn stats_range(query: &StatsQuery) -> Result<sea_orm::Condition, HGError> {
+    let mut condition = sea_orm::Condition::all();
+    for (date, op) in [(&query.from, ">="), (&query.to, "<=")] {
+        let Some(date) = date else {
+            continue;
+        };
+        parse_date(date)?;
+        condition = condition.add(Expr::cust_with_values(
+            format!(r#"date("star_events"."created_at", 'localtime') {} ?"#, op),
+            [date.clone()],
+        ));
+    }
+    Ok(condition)
+}
+
+impl SQLConnector {
+    pub async fn list_calendar_people(
+        &self,
+    ) -> Result<Vec<crate::server::types::CalendarPersonResponse>, anyhow::Error> {
+        let db = self.database_connection.as_ref().unwrap();
+        let people = crate::entity::people::Entity::find()
+            .filter(crate::entity::people::Column::ArchivedAt.is_null())
+            .all(db)
+            .await?;
+
+        // Default color palette in case a person doesn't have a stored color
+        let default_palette = [
+            "#FF6B9D", "#4ECDC4", "#FFD93D", "#95E1D3", "#F38181", "#AA96DA", "#FCBAD3", "#A8E6CF",
+            "#FFD700", "#87CEEB", "#FF8C42", "#C77DFF",
+        ];
+
+        let items = people
+            .into_iter()
+            .map(|p| {
+                // Use stored color or fall back to palette based on ID
+                let color = p.calendar_color.clone().unwrap_or_else(|| {
+                    default_palette[(p.id as usize) % default_palette.len()].to_string()
+                });
+
+                // Make titles very clear for young children
+                let title = format!("📅 {}'s Calendar", p.first_name);
+                crate::server::types::CalendarPersonResponse {
+                    id: p.id,
+                    title,
+                    event_background_color: Some(color),
+                    event_text_color: Some("#000000".to_string()),
+                }
+            })
+            .collect();
+        Ok(items)
+    }
+
+    /// Events overlapping the requested window, with repeating series
+    /// expanded into their occurrences. Without a window every single event
+    /// is returned and series are expanded from a month back to a year ahead.
+    pub async fn list_calendar_events(
+        &self,
+        query: &ListCalendarEventsQuery,
+    ) -> Result<Vec<crate::server::types::CalendarEventResponse>, anyhow::Error> {
+        use crate::data::rrule::{format_event_time, parse_event_time};
+        use crate::entity::calendar_events;
+        use std::collections::HashSet;
+
+        let db = self.database_connection.as_ref().unwrap();
+        let now = chrono::Local::now().naive_local();
+        let from = query.start.as_deref().map(parse_event_time).transpose()?;
+        let to = query.end.as_deref().map(parse_event_time).transpose()?;
+        let windowed = from.is_some() || to.is_some();
+        let from = from.unwrap_or(now - chrono::Duration::days(31));
+        let to = to.unwrap_or(now + chrono::Duration::days(365));
+        if to <= from {
+            return Err(HGError::Invalid("end must be after start".to_string()).into());
+        }
+
+        let events = calendar_events::Entity::find()
+            .order_by_asc(calendar_events::Column::StartTime)
+            .all(db)
+            .await?;
+        // Occurrences that were changed on their own are listed as themselves
+        let overridden: HashSet<(i32, chrono::NaiveDateTime)> = events
+            .iter()
+            .filter_map(|e| {
+                let recurrence_id = parse_event_time(e.recurrence_id.as_deref()?).ok()?;
+                Some((e.parent_event_id?, recurrence_id))
+            })
+            .collect();
+
+        let mut results = Vec::new();
+        for event in events {
+            let series = match event_series(&event) {
+                Ok(series) => series,
+                Err(e) => {
+                    warn!("Skipping calendar event {}: {}", event.id, e);
+                    continue;
+                }
+            };
+            let Some(series) = series else {
+                let outside = match (
+                    parse_event_time(&event.start_time),
+                    parse_event_time(&event.end_time),
+                ) {
+                    (Ok(start), Ok(end)) => start >= to || end <= from,
+                    _ => false,
+                };
+                if windowed && outside {
+                    continue;
+                }
+                results.push(crate::server::types::CalendarEventResponse {
+                    id: event.id,
+                    title: event.title,
+                    start: event.start_time,
+                    end: event.end_time,
+                    resource_id: event.person_id,
+                    series_id: event.parent_event_id,
+                    occurrence: event.recurrence_id,
+                    rrule: None,
+                });
+                continue;
+            };
+
+            for start in series
+                .rule
+                .occurrences(series.start, series.duration, from, to)
+            {
+                if series.exdates.contains(&start) || overridden.contains(&(event.id, start)) {
+                    continue;
+                }
+                results.push(crate::server::types::CalendarEventResponse {
+                    id: event.id,
+                    title: event.title.clone(),
+                    start: format_event_time(start),
+                    end: format_event_time(start + series.duration),
+                    resource_id: event.person_id,
+                    series_id: Some(event.id),
+                    occurrence: Some(format_event_time(start)),
+                    rrule: event.rrule.clone(),
+                });
+            }
+        }
+        Ok(results)
+    }
+
+    pub async fn create_calendar_event(
+        &self,
+        payload: &crate::server::types::CreateCalendarEventRequest,
+        client: Option<&str>,
+    ) -> Result<crate::server::types::CreateCalendarEventResponse, anyhow::Error> {
+        use crate::data::rrule::{RecurrenceRule, parse_event_time};
+
+        let db = self.database_connection.as_ref().unwrap();
+        let rrule = match &payload.rrule {
+            Some(rrule) => Some(rrule.parse::<RecurrenceRule>()?.to_string()),
+            None if !payload.exdates.is_empty() => {
+                return Err(
+                    HGError::Invalid("exdates only apply to repeating events".to_string()).into(),
+                );
+            }
+            None => None,
+        };
+        let exdates = payload
+            .exdates
+            .iter()
+            .map(|d| parse_event_time(d))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let txn = db.begin().await?;
+        let event = crate::entity::calendar_events::ActiveModel {
+            person_id: Set(payload.person_id),
+            title: Set(payload.title.clone()),
+            start_time: Set(payload.start.clone()),
+            end_time: Set(payload.end.clone()),
+            rrule: Set(rrule),
+            exdates: Set(join_exdates(exdates)),
+            updated_at: Set(Some(Utc::now())),
+            ..Default::default()
+        };
+        let result = event.insert(&txn).await?;
+        // A series needs times it can repeat
+        event_series(&result)?;
+        record_undo(
+            &txn,
+            client,
+            UndoAction::CreateEvent,
+            result.id,
+            None,
+            None,
+            Some(&result),
+        )
+        .await?;
+        touch_calendar(&txn).await?;
+        txn.commit().await?;
+        Ok(crate::server::types::CreateCalendarEventResponse { id: result.id })
+    }
+
+    /// Import the events of an iCalendar file for one person. Events are
+    /// recognised by their UID, so importing the same file again only
+    /// updates the ones that changed since.
+    pub async fn import_calendar(
+        &self,
+        person_id: i32,
+        text: &str,
+    ) -> Result<ImportCalendarResponse, anyhow::Error> {
+        use crate::data::ical::parse_calendar;
+        use crate::data::rrule::format_event_time;
+        use crate::entity::{calendar_events, people};
+
+        let db = self.database_connection.as_ref().unwrap();
+        if people::Entity::find_by_id(person_id)
+            .one(db)
+            .await?
+            .is_none()
+        {
+            return Err(HGError::NotFound(format!("person {} not found", person_id)).into());
+        }
+
+        let mut report = ImportCalendarResponse {
+            created: Vec::new(),
+            updated: Vec::new(),
+            skipped: Vec::new(),
+        };
+        let mut events = Vec::new();
+        for event in parse_calendar(text, self.configuration.calendar.timezone)? {
+            match event {
+                Ok(event) => events.push(event),
+                Err(e) => report.skipped.push(SkippedCalendarEvent {
+                    uid: e.uid,
+                    title: e.summary,
+                    reason: e.reason,
+                }),
+            }
+        }
+        // Series first, so the occurrences replacing parts of them find them
+        events.sort_by_key(|e| e.recurrence_id.is_some());
+        // Cancelled occurrences are left out of series in the same file
+        let cancelled: Vec<(String, chrono::NaiveDateTime)> = events
+            .iter()
+            .filter(|e| e.cancelled)
+            .filter_map(|e| Some((e.uid.clone()?, e.recurrence_id?)))
+            .collect();
+        for event in events.iter_mut().filter(|e| e.recurrence_id.is_none()) {
+            event.exdates.extend(
+                cancelled
+                    .iter()
+                    .filter(|(uid, _)| event.uid.as_ref() == Some(uid))
+                    .map(|(_, at)| *at),
+            );
+        }
+
+        let txn = db.begin().await?;
+        for event in events {
+            let title = event
+                .summary
+                .clone()
+                .unwrap_or_else(|| "Untitled".to_string());
+            let skip = |reason: &str| SkippedCalendarEvent {
+                uid: event.uid.clone(),
+                title: event.summary.clone(),
+                reason: reason.to_string(),
+            };
+            let Some(uid) = event.uid.clone() else {
+                report.skipped.push(skip("no UID to recognise it by"));
+                continue;
+            };
+            let series = calendar_events::Entity::find()
+                .filter(calendar_events::Column::PersonId.eq(person_id))
+                .filter(calendar_events::Column::ImportUid.eq(&uid))
+                .filter(calendar_events::Column::ParentEventId.is_null())
+                .one(&txn)
+                .await?;
+
+            let (existing, parent_event_id) = match event.recurrence_id {
+                None if event.cancelled => {
+                    report.skipped.push(skip("cancelled"));
+                    continue;
+                }
+                None => (series, None),
+                Some(recurrence_id) => {
+                    let Some(series) = series.filter(|s| s.rrule.is_some()) else {
+                        report.skipped.push(skip(
+                            "replaces an occurrence of a series that was not imported",
+                        ));
+                        continue;
+                    };
+                    let existing = calendar_events::Entity::find()
+                        .filter(calendar_events::Column::ParentEventId.eq(series.id))
+                        .filter(
+                            calendar_events::Column::RecurrenceId
+                                .eq(format_event_time(recurrence_id)),
+                        )
+                        .one(&txn)
+                        .await?;
+                    if event.cancelled {
+                        // A cancelled occurrence is left out of its series
+                        let replaced = existing.is_some();
+                        if let Some(existing) = existing {
+                            calendar_events::Entity::delete_by_id(existing.id)
+                                .exec(&txn)
+                                .await?;
+                        }
+                        let mut exdates = event_series(&series)?
+                            .map(|s| s.exdates)
+                            .unwrap_or_default();
+                        let left_out = exdates.contains(&recurrence_id);
+                        if left_out && !replaced {
+                            report.skipped.push(skip("cancelled"));
+                            continue;
+                        }
+                        if !left_out {
+                            exdates.push(recurrence_id);
+                            let mut active: calendar_events::ActiveModel = series.clone().into();
+                            active.exdates = Set(join_exdates(exdates));
+                            active.updated_at = Set(Some(Utc::now()));
+                            active.update(&txn).await?;
+                        }
+                        report.updated.push(ImportedCalendarEvent {
+                            id: series.id,
+                            uid,
+                            title: series.title,
+                        });
+                        continue;
+                    }
+                    (existing, Some(series.id))
+                }
+            };
+
+            let start_time = format_event_time(event.start);
+            let end_time = format_event_time(event.end);
+            let rrule = event.rrule.as_ref().map(|r| r.to_string());
+            let recurrence_id = event.recurrence_id.map(format_event_time);
+            let exdates = match rrule {
+                Some(_) => join_exdates(event.exdates.iter().copied()),
+                None => None,
+            };
+            match existing {
+                Some(existing)
+                    if existing.title == title
+                        && existing.start_time == start_time
+                        && existing.end_time == end_time
+                        && existing.rrule == rrule
+                        && existing.exdates == exdates =>
+                {
+                    report.skipped.push(skip("unchanged"));
+                    continue;
+                }
+                Some(existing) => {
+                    let id = existing.id;
+                    let mut active: calendar_events::ActiveModel = existing.into();
+                    active.title = Set(title.clone());
+                    active.start_time = Set(start_time);
+                    active.end_time = Set(end_time);
+                    active.rrule = Set(rrule);
+                    active.exdates = Set(exdates);
+                    active.import_uid = Set(Some(uid.clone()));
+                    active.updated_at = Set(Some(Utc::now()));
+                    let updated = active.update(&txn).await?;
+                    event_series(&updated)?;
+                    report
+                        .updated
+                        .push(ImportedCalendarEvent { id, uid, title });
+                }
+                None => {
+                    let created = calendar_events::ActiveModel {
+                        person_id: Set(person_id),
+                        title: Set(title.clone()),
+                        start_time: Set(start_time),
+                        end_time: Set(end_time),
+                        rrule: Set(rrule),
+                        exdates: Set(exdates),
+                        parent_event_id: Set(parent_event_id),
+                        recurrence_id: Set(recurrence_id),
+                        import_uid: Set(Some(uid.clone())),
+                        updated_at: Set(Some(Utc::now())),
+                        ..Default::default()
+                    }
+                    .insert(&txn)
+                    .await?;
+                    event_series(&created)?;
+                    report.created.push(ImportedCalendarEvent {
+                        id: created.id,
+                        uid,
+                        title,
+                    });
+                }
+            }
+        }
+        touch_calendar(&txn).await?;
+        txn.commit().await?;
+        Ok(report)
+    }
+
+    /// The family calendar, or one person's part of it, as iCalendar
+    pub async fn calendar_feed(
+        &self,
+        person_id: Option<i32>,
+    ) -> Result<CalendarFeed, anyhow::Error> {
+        use crate::data::rrule::parse_event_time;
+        use crate::entity::{calendar_changes, calendar_events, people};
+
+        let db = self.database_connection.as_ref().unwrap();
+        let people: HashMap<i32, people::Model> = people::Entity::find()
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|p| (p.id, p))
+            .collect();
+        let person = match person_id {
+            Some(id) => Some(
+                people
+                    .get(&id)
+                    .ok_or_else(|| HGError::NotFound(format!("person {} not found", id)))?,
+            ),
+            None => None,
+        };
+
+        // Ordered so an unchanged calendar renders to the same bytes
+        let (events, others): (Vec<_>, Vec<_>) = calendar_events::Entity::find()
+            .order_by_asc(calendar_events::Column::Id)
+            .all(db)
+            .await?
+            .into_iter()
+            .partition(|e| person_id.is_none_or(|id| e.person_id == id));
+        let mut moved_away: HashMap<i32, Vec<chrono::NaiveDateTime>> = HashMap::new();
+        for event in others {
+            if let (Some(parent_id), Some(recurrence_id)) = (
+                event.parent_event_id,
+                event
+                    .recurrence_id
+                    .as_deref()
+                    .and_then(|r| parse_event_time(r).ok()),
+            ) {
+                moved_away.entry(parent_id).or_default().push(recurrence_id);
+            }
+        }
+
+        let name = match person {
+            Some(p) => format!("{}'s Calendar", p.first_name),
+            None => "Family Calendar".to_string(),
+        };
+        let last_modified = calendar_changes::Entity::find_by_id(1)
+            .one(db)
+            .await?
+            .map(|c| c.changed_at);
+        Ok(CalendarFeed {
+            body: crate::data::ical::write_calendar(
+                &name,
+                person.and_then(|p| p.calendar_color.as_deref()),
+                self.configuration.calendar.timezone,
+                &events,
+                &moved_away,
+                &people,
+            ),
+            last_modified,
+        })
+    }
+
+    /// Change an event. For a repeating series the change applies to the
+    /// whole series, to one occurrence (stored as a separate event replacing
+    /// it) or to one occurrence and all after it (split off as a new series).
+    /// Returns the id of the event now holding the change.
+    pub async fn update_calendar_event(
+        &self,
+        event_id: i32,
+        update: &UpdateCalendarEventRequest,
+        client: Option<&str>,
+    ) -> Result<UpdateCalendarEventResponse, anyhow::Error> {
+        use crate::entity::calendar_events;
+
+        let db = self.database_connection.as_ref().unwrap();
+        let txn = db.begin().await?;
+        let event = calendar_events::Entity::find_by_id(event_id)
+            .one(&txn)
+            .await?
+            .ok_or_else(|| HGError::NotFound(format!("calendar event {} not found", event_id)))?;
+        let touched = EventRows::of(&event);
+        let before = touched.load(&txn).await?;
+        let series = event_series(&event)?;
+        let occurrence = match (&series, update.scope) {
+            (Some(series), EditScope::This | EditScope::Future) => Some(find_occurrence(
+                &event,
+                series,
+                update.occurrence.as_deref(),
+            )?),
+            _ => None,
+        };
+
+        let id = match (series, occurrence) {
+            (Some(series), Some(occurrence)) if update.scope == EditScope::This => {
+                override_occurrence(&txn, &event, &series, occurrence, update).await?
+            }
+            (Some(series), Some(occurrence)) if occurrence != series.start => {
+                split_series(&txn, event, series, occurrence, update).await?
+            }
+            (series, _) => update_event(&txn, event, series, update).await?,
+        };
+        let after = touched.with(id).load(&txn).await?;
+        record_event_undo(
+            &txn,
+            client,
+            UndoAction::UpdateEvent,
+            event_id,
+            before,
+            after,
+        )
+        .await?;
+        touch_calendar(&txn).await?;
+        txn.commit().await?;
+        Ok(UpdateCalendarEventResponse { id })
+    }
+
+    /// Delete an event, or just one occurrence of a series, or one occurrence
+    /// and all after it
+    pub async fn delete_calendar_event(
+        &self,
+        event_id: i32,
+        query: &DeleteCalendarEventQuery,
+        client: Option<&str>,
+    ) -> Result<(), anyhow::Error> {
+        use crate::data::rrule::parse_event_time;
+        use crate::entity::calendar_events;
+
+        let db = self.database_connection.as_ref().unwrap();
+        let txn = db.begin().await?;
+        let event = calendar_events::Entity::find_by_id(event_id)
+            .one(&txn)
+            .await?
+            .ok_or_else(|| HGError::NotFound(format!("calendar event {} not found", event_id)))?;
+        let touched = EventRows::of(&event);
+        let before = touched.load(&txn).await?;
+        let series = event_series(&event)?;
+        let occurrence = match (&series, query.scope) {
+            (Some(series), EditScope::This | EditScope::Future) => Some(find_occurrence(
+                &event,
+                series,
+                query.occurrence.as_deref(),
+            )?),
+            _ => None,
+        };
+
+        match (series, occurrence) {
+            (Some(series), Some(occurrence)) if query.scope == EditScope::This => {
+                // Leave the occurrence out, along with any change made to it
+                move_overrides(&txn, event.id, occurrence, Some(occurrence), None).await?;
+                let mut exdates = series.exdates;
+                exdates.push(occurrence);
+                let mut am: calendar_events::ActiveModel = event.into();
+                am.exdates = Set(join_exdates(exdates));
+                am.updated_at = Set(Some(Utc::now()));
+                am.update(&txn).await?;
+            }
+            (Some(series), Some(occurrence)) if occurrence != series.start => {
+                move_overrides(&txn, event.id, occurrence, None, None).await?;
+                let mut am: calendar_events::ActiveModel = event.into();
+                am.rrule = Set(Some(end_before(&series, occurrence).to_string()));
+                am.exdates = Set(join_exdates(
+                    series.exdates.into_iter().filter(|d| *d < occurrence),
+                ));
+                am.updated_at = Set(Some(Utc::now()));
+                am.update(&txn).await?;
+            }
+            _ => {
+                move_overrides(&txn, event.id, chrono::NaiveDateTime::MIN, None, None).await?;
+                calendar_events::Entity::delete_by_id(event_id)
+                    .exec(&txn)
+                    .await?;
+                // A replaced occurrence that is deleted should not come back
+                // as it was originally scheduled
+                let parent = match event.parent_event_id {
+                    Some(parent_id) => {
+                        calendar_events::Entity::find_by_id(parent_id)
+                            .one(&txn)
+                            .await?
+                    }
+                    None => None,
+                };
+                if let (Some(parent), Some(recurrence_id)) = (parent, &event.recurrence_id)
+                    && let Some(series) = event_series(&parent)?
+                {
+                    let mut exdates = series.exdates;
+                    exdates.push(parse_event_time(recurrence_id)?);
+                    let mut am: calendar_events::ActiveModel = parent.into();
+                    am.exdates = Set(join_exdates(exdates));
+                    am.updated_at = Set(Some(Utc::now()));
+                    am.update(&txn).await?;
+                }
+            }
+        }
+        let after = touched.load(&txn).await?;
+        record_event_undo(
+            &txn,
+            client,
+            UndoAction::DeleteEvent,
+            event_id,
+            before,
+            after,
+        )
+        .await?;
+        touch_calendar(&txn).await?;
+        txn.commit().await?;
+        Ok(())
+    }
+}
+
+/// The calendar events a change to one event can touch: the event, the
+/// series it replaces an occurrence of, the occurrences replaced in it and
+/// any series split off from it
+#[derive(Clone)]
+struct EventRows {
+    ids: Vec<i32>,
+    parents: Vec<i32>,
+}
+
+impl EventRows {
+    fn of(event: &crate::entity::calendar_events::Model) -> EventRows {
+        EventRows {
+            ids: std::iter::once(event.id)
+                .chain(event.parent_event_id)
+                .collect(),
+            parents: vec![event.id],
+        }
+    }
+
+    /// Also the rows of an event the change created
+    fn with(mut self, event_id: i32) -> EventRows {
+        self.ids.push(event_id);
+        self.parents.push(event_id);
+        self
+    }
+
+    async fn load<C: ConnectionTrait>(
+        &self,
+        db: &C,
+    ) -> Result<Vec<crate::entity::calendar_events::Model>, anyhow::Error> {
+        use crate::entity::calendar_events;
+        use sea_orm::Condition;
+
+        Ok(calendar_events::Entity::find()
+            .filter(
+                Condition::any()
+                    .add(calendar_events::Column::Id.is_in(self.ids.clone()))
+                    .add(calendar_events::Column::ParentEventId.is_in(self.parents.clone())),
+            )
+            .order_by_asc(calendar_events::Column::Id)
+            .all(db)
+            .await?)
+    }
+}
+
+/// Record a calendar change as the rows it changed, before and after
+async fn record_event_undo<C: ConnectionTrait>(
+    db: &C,
+    client: Option<&str>,
+    action: UndoAction,
+    event_id: i32,
+    mut before: Vec<crate::entity::calendar_events::Model>,
+    mut after: Vec<crate::entity::calendar_events::Model>,
+) -> Result<(), anyhow::Error> {
+    let unchanged: Vec<_> = before
+        .iter()
+        .filter(|b| after.contains(b))
+        .cloned()
+        .collect();
+    before.retain(|b| !unchanged.contains(b));
+    after.retain(|a| !unchanged.contains(a));
+    if before.is_empty() && after.is_empty() {
+        return Ok(());
+    }
+    record_undo(
+        db,
+        client,
+        action,
+        event_id,
+        None,
+        Some(&before),
+        Some(&after),
+    )
+    .await
+}
+
+/// Calendar events in an undo snapshot. Older entries hold a single event.
+fn event_snapshot(
+    snapshot: Option<&str>,
+) -> Result<Vec<crate::entity::calendar_events::Model>, anyhow::Error> {
+    let Some(snapshot) = snapshot else {
+        return Ok(Vec::new());
+    };
+    match serde_json::from_str(snapshot) {
+        Ok(events) => Ok(events),
+        Err(_) => Ok(vec![serde_json::from_str(snapshot)?]),
+    }
+}
+
+/// A repeating event's rule, first occurrence, length and left out dates
+struct Series {
+    rule: crate::data::rrule::RecurrenceRule,
+    start: chrono::NaiveDateTime,
+    duration: chrono::Duration,
+    exdates: Vec<chrono::NaiveDateTime>,
+}
+
+fn event_series(event: &crate::entity::calendar_events::Model) -> Result<Option<Series>, HGError> {
+    use crate::data::rrule::parse_event_time;
+
+    let Some(rrule) = &event.rrule else {
+        return Ok(None);
+    };
+    let start = parse_event_time(&event.start_time)?;
+    let end = parse_event_time(&event.end_time)?;
+    if end < start {
+        return Err(HGError::Invalid(
+            "a repeating event must end after it starts".to_string(),
+        ));
+    }
+    let exdates = event
+        .exdates
+        .as_deref()
+        .unwrap_or_default()
+        .split(',')
+        .filter(|d| !d.is_empty())
+        .map(parse_event_time)
+        .collect::<Result<_, _>>()?;
+    Ok(Some(Series {
+        rule: rrule.parse()?,
+        start,
+        duration: end - start,
+        exdates,
+    }))
+}
+
+fn join_exdates(exdates: impl IntoIterator<Item = chrono::NaiveDateTime>) -> Option<String> {
+    use crate::data::rrule::format_event_time;
+
+    let mut exdates: Vec<chrono::NaiveDateTime> = exdates.into_iter().collect();
+    exdates.sort();
+    exdates.dedup();
+    if exdates.is_empty() {
+        return None;
+    }
+    Some(
+        exdates
+            .into_iter()
+            .map(format_event_time)
+            .collect::<Vec<_>>()
+            .join(","),
+    )
+}
+
+/// The occurrence of a series a partial edit is aimed at
+fn find_occurrence(
+    event: &crate::entity::calendar_events::Model,
+    series: &Series,
+    occurrence: Option<&str>,
+) -> Result<chrono::NaiveDateTime, HGError> {
+    use crate::data::rrule::parse_event_time;
+
+    let occurrence = occurrence.ok_or_else(|| {
+        HGError::Invalid("occurrence is required to change part of a series".to_string())
+    })?;
+    let at = parse_event_time(occurrence)?;
+    if !series.rule.includes(series.start, at) || series.exdates.contains(&at) {
+        return Err(HGError::NotFound(format!(
+            "{} has no occurrence at {}",
+            event.title, occurrence
+        )));
+    }
+    Ok(at)
+}
+
+/// The series' rule cut short so its last occurrence is the one before `at`
+fn end_before(series: &Series, at: chrono::NaiveDateTime) -> crate::data::rrule::RecurrenceRule {
+    let mut rule = series.rule.clone();
+    match rule.count {
+        Some(_) => rule.count = Some(series.rule.count_before(series.start, at)),
+        None => rule.until = Some(at - chrono::Duration::seconds(1)),
+    }
+    rule
+}
+
+/// The series' rule picked up again at `at`, with what is left of its COUNT
+fn resume_at(series: &Series, at: chrono::NaiveDateTime) -> crate::data::rrule::RecurrenceRule {
+    let mut rule = series.rule.clone();
+    if let Some(count) = rule.count {
+        rule.count = Some(count - series.rule.count_before(series.start, at));
+    }
+    rule
+}
+
+/// Hand the replaced occurrences of a series from `since` on (or only the
+/// one at `since` when `only` is set) to another series, shifted by the
+/// time that series moved, or drop them when there is nowhere to go
+async fn move_overrides<C: ConnectionTrait>(
+    db: &C,
+    series_id: i32,
+    since: chrono::NaiveDateTime,
+    only: Option<chrono::NaiveDateTime>,
+    to: Option<(i32, chrono::Duration)>,
+) -> Result<(), anyhow::Error> {
+    use crate::data::rrule::{format_event_time, parse_event_time};
+    use crate::entity::calendar_events;
+
+    let overrides = calendar_events::Entity::find()
+        .filter(calendar_events::Column::ParentEventId.eq(series_id))
+        .all(db)
+        .await?;
+    for row in overrides {
+        let Some(recurrence_id) = row
+            .recurrence_id
+            .as_deref()
+            .and_then(|r| parse_event_time(r).ok())
+        else {
+            continue;
+        };
+        if recurrence_id < since || only.is_some_and(|at| at != recurrence_id) {
+            continue;
+        }
+        match to {
+            Some((to, shift)) => {
+                let mut am: calendar_events::ActiveModel = row.into();
+                am.parent_event_id = Set(Some(to));
+                am.recurrence_id = Set(Some(format_event_time(recurrence_id + shift)));
+                am.updated_at = Set(Some(Utc::now()));
+                am.update(db).await?;
+            }
+            None => {
+                calendar_events::Entity::delete_by_id(row.id)
+                    .exec(db)
+                    .await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn apply_event_fields(
+    am: &mut crate::entity::calendar_events::ActiveModel,
+    update: &UpdateCalendarEventRequest,
+) {
+    if let Some(title) = &update.title {
+        am.title = Set(title.clone());
+    }
+    if let Some(person_id) = update.person_id {
+        am.person_id = Set(person_id);
+    }
+    if let Some(start) = &update.start {
+        am.start_time = Set(start.clone());
+    }
+    if let Some(end) = &update.end {
+        am.end_time = Set(end.clone());
+    }
+}
+
+/// Change a single event, or a whole series in place. Moving a series'
+/// start moves its left out and replaced occurrences along with it.
+async fn update_event<C: ConnectionTrait>(
+    db: &C,
+    event: crate::entity::calendar_events::Model,
+    series: Option<Series>,
+    update: &UpdateCalendarEventRequest,
+) -> Result<i32, anyhow::Error> {
+    use crate::data::rrule::{RecurrenceRule, parse_event_time};
+    use crate::entity::calendar_events;
+
+    let id = event.id;
+    let mut am: calendar_events::ActiveModel = event.clone().into();
+    apply_event_fields(&mut am, update);
+    match update.rrule.as_deref() {
+        Some(_) if event.parent_event_id.is_some() => {
+            return Err(HGError::Invalid(
+                "a replaced occurrence cannot repeat on its own".to_string(),
+            )
+            .into());
+        }
+        Some("") => {
+            am.rrule = Set(None);
+            am.exdates = Set(None);
+            move_overrides(db, id, chrono::NaiveDateTime::MIN, None, None).await?;
+        }
+        Some(rrule) => am.rrule = Set(Some(rrule.parse::<RecurrenceRule>()?.to_string())),
+        None => {}
+    }
+    if let (Some(series), Some(start), None) = (series, &update.start, &update.rrule) {
+        let shift = parse_event_time(start)? - series.start;
+        am.exdates = Set(join_exdates(series.exdates.iter().map(|d| *d + shift)));
+        move_overrides(db, id, chrono::NaiveDateTime::MIN, None, Some((id, shift))).await?;
+    }
+
+    if !am.is_changed() {
+        return Ok(id);
+    }
+    am.updated_at = Set(Some(Utc::now()));
+    let res = am.update(db).await?;
+    event_series(&res)?;
+    Ok(res.id)
+}
+
+/// Replace one occurrence of a series with an event of its own, or change
+/// the one already replacing it
+async fn override_occurrence<C: ConnectionTrait>(
+    db: &C,
+    event: &crate::entity::calendar_events::Model,
+    series: &Series,
+    occurrence: chrono::NaiveDateTime,
+    update: &UpdateCalendarEventRequest,
+) -> Result<i32, anyhow::Error> {
+    use crate::data::rrule::format_event_time;
+    use crate::entity::calendar_events;
+
+    if update.rrule.is_some() {
+        return Err(
+            HGError::Invalid("a single occurrence cannot have its own rrule".to_string()).into(),
+        );
+    }
+    let recurrence_id = format_event_time(occurrence);
+    let existing = calendar_events::Entity::find()
+        .filter(calendar_events::Column::ParentEventId.eq(event.id))
+        .filter(calendar_events::Column::RecurrenceId.eq(recurrence_id.clone()))
+        .one(db)
+        .await?;
+    let row = match existing {
+        Some(row) => row,
+        None => {
+            calendar_events::ActiveModel {
+                person_id: Set(event.person_id),
+                title: Set(event.title.clone()),
+                start_time: Set(recurrence_id.clone()),
+                end_time: Set(format_event_time(occurrence + series.duration)),
+                parent_event_id: Set(Some(event.id)),
+                recurrence_id: Set(Some(recurrence_id)),
+                updated_at: Set(Some(Utc::now())),
+                ..Default::default()
+            }
+            .insert(db)
+            .await?
+        }
+    };
+
+    let id = row.id;
+    let mut am: calendar_events::ActiveModel = row.into();
+    apply_event_fields(&mut am, update);
+    if am.is_changed() {
+        am.updated_at = Set(Some(Utc::now()));
+        am.update(db).await?;
+    }
+    Ok(id)
+}
+
+/// End a series just before `occurrence` and carry on from there as a new
+/// series with the requested changes
+async fn split_series<C: ConnectionTrait>(
+    db: &C,
+    event: crate::entity::calendar_events::Model,
+    series: Series,
+    occurrence: chrono::NaiveDateTime,
+    update: &UpdateCalendarEventRequest,
+) -> Result<i32, anyhow::Error> {
+    use crate::data::rrule::{RecurrenceRule, format_event_time, parse_event_time};
+    use crate::entity::calendar_events;
+
+    let rest = match update.rrule.as_deref() {
+        Some("") => None,
+        Some(rrule) => Some(rrule.parse::<RecurrenceRule>()?),
+        None => Some(resume_at(&series, occurrence)),
+    };
+    let start = match &update.start {
+        Some(start) => parse_event_time(start)?,
+        None => occurrence,
+    };
+    let end = match &update.end {
+        Some(end) => parse_event_time(end)?,
+        None => start + series.duration,
+    };
+    let shift = start - occurrence;
+    let (before, after): (Vec<_>, Vec<_>) = series.exdates.iter().partition(|d| **d < occurrence);
+
+    let tail = calendar_events::ActiveModel {
+        person_id: Set(update.person_id.unwrap_or(event.person_id)),
+        title: Set(update.title.clone().unwrap_or_else(|| event.title.clone())),
+        start_time: Set(format_event_time(start)),
+        end_time: Set(format_event_time(end)),
+        rrule: Set(rest.as_ref().map(|r| r.to_string())),
+        exdates: Set(rest
+            .as_ref()
+            .and_then(|_| join_exdates(after.iter().map(|d| *d + shift)))),
+        updated_at: Set(Some(Utc::now())),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+    event_series(&tail)?;
+    move_overrides(
+        db,
+        event.id,
+        occurrence,
+        None,
+        rest.as_ref().map(|_| (tail.id, shift)),
+    )
+    .await?;
+
+    let ended = end_before(&series, occurrence);
+    let mut am: calendar_events::ActiveModel = event.into();
+    am.rrule = Set(Some(ended.to_string()));
+    am.exdates = Set(join_exdates(before));
+    am.updated_at = Set(Some(Utc::now()));
+    am.update(db).await?;
+    Ok(tail.id)
+}
+
+/// How many steps of undo history each client keeps
+const UNDO_HISTORY: usize = 20;
+
+/// Push a step onto the client's undo history. A new step forgets anything
+/// the client had undone, and the oldest steps fall off past UNDO_HISTORY.
+/// Without a client id nothing is recorded.
+async fn record_undo<T: serde::Serialize>(
+    db: &impl ConnectionTrait,
+    client: Option<&str>,
+    action: UndoAction,
+    target_id: i32,
+    delta: Option<i32>,
+    before: Option<&T>,
+    after: Option<&T>,
+) -> Result<(), anyhow::Error> {
+    use crate::entity::undo_entries;
+    use sea_orm::QuerySelect;
+
+    let Some(client) = client else {
+        return Ok(());
+    };
+
+    undo_entries::Entity::delete_many()
+        .filter(undo_entries::Column::ClientId.eq(client))
+        .filter(undo_entries::Column::Undone.eq(true))
+        .exec(db)
+        .await?;
+    undo_entries::ActiveModel {
+        client_id: Set(client.to_string()),
+        action: Set(action),
+        target_id: Set(target_id),
+        delta: Set(delta),
+        before: Set(before.map(serde_json::to_string).transpose()?),
+        after: Set(after.map(serde_json::to_string).transpose()?),
+        undone: Set(false),
+        created_at: Set(Utc::now()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+
+    let ids: Vec<i32> = undo_entries::Entity::find()
+        .select_only()
+        .column(undo_entries::Column::Id)
+        .filter(undo_entries::Column::ClientId.eq(client))
+        .order_by_desc(undo_entries::Column::Id)
+        .into_tuple()
+        .all(db)
+        .await?;
+    let expired: Vec<i32> = ids.into_iter().skip(UNDO_HISTORY).collect();
+    if !expired.is_empty() {
+        undo_entries::Entity::delete_many()
+            .filter(undo_entries::Column::Id.is_in(expired))
+            .exec(db)
+            .await?;
+    }
+    Ok(())
+}
+
+impl SQLConnector {
+    /// Revert the client's most recent change that hasn't been undone yet
+    pub async fn undo(&self, client: &str) -> Result<UndoResponse, anyhow::Error> {
+        use crate::entity::undo_entries;
+
+        let db = self.database_connection.as_ref().unwrap();
+        let entry = undo_entries::Entity::find()
+            .filter(undo_entries::Column::ClientId.eq(client))
+            .filter(undo_entries::Column::Undone.eq(false))
+            .order_by_desc(undo_entries::Column::Id)
+            .one(db)
+            .await?
+            .ok_or_else(|| HGError::NotFound("nothing to undo".to_string()))?;
+        self.replay_undo_entry(entry, true).await
+    }
+
+    /// Reapply the change the client undid most recently
+    pub async fn redo(&self, client: &str) -> Result<UndoResponse, anyhow::Error> {
+        use crate::entity::undo_entries;
+
+        let db = self.database_connection.as_ref().unwrap();
+        let entry = undo_entries::Entity::find()
+            .filter(undo_entries::Column::ClientId.eq(client))
+            .filter(undo_entries::Column::Undone.eq(true))
+            .order_by_asc(undo_entries::Column::Id)
+            .one(db)
+            .await?
+            .ok_or_else(|| HGError::NotFound("nothing to redo".to_string()))?;
+        self.replay_undo_entry(entry, false).await
+    }
+
+    /// Walk an entry backwards (undo) or forwards (redo). Rows are only
+    /// touched while they still look the way the entry left them, so an undo
+    /// never clobbers someone else's later edit.
+    async fn replay_undo_entry(
+        &self,
+        entry: crate::entity::undo_entries::Model,
+        undo: bool,
+    ) -> Result<UndoResponse, anyhow::Error> {
+        use crate::entity::{calendar_events, star_charts, undo_entries};
+        use sea_orm::IntoActiveModel;
+
+        let db = self.database_connection.as_ref().unwrap();
+        let txn = db.begin().await?;
+        let note = if undo { "undo" } else { "redo" };
+        let mut delta = entry.delta;
+        let mut person_id = None;
+        let mut landed = None;
+
+        match entry.action {
+            UndoAction::Increment => {
+                let expected = if undo { &entry.after } else { &entry.before };
+                let expected: Option<star_charts::Model> =
+                    expected.as_deref().map(serde_json::from_str).transpose()?;
+                let chart = star_charts::Entity::find_by_id(entry.target_id)
+                    .one(&txn)
+                    .await?
+                    .ok_or_else(|| {
+                        HGError::NotFound(format!(
+                            "star chart {} no longer exists",
+                            entry.target_id
+                        ))
+                    })?;
+                if !expected.is_some_and(|e| same_chart_state(&chart, &e)) {
+                    return Err(HGError::Conflict(format!(
+                        "star chart {} has changed since",
+                        entry.target_id
+                    ))
+                    .into());
+                }
+                let previous_count = chart.star_count;
+                let step = entry.delta.unwrap_or(0);
+                let (res, completed) = apply_increment(
+                    &txn,
+                    chart,
+                    if undo { -step } else { step },
+                    Some(note.to_string()),
+                    None,
+                    StarEventKind::Undo,
+                )
+                .await?;
+                if completed {
+                    unlock_quests(&txn, res.id).await?;
+                } else if res.completed_at.is_none() {
+                    relock_quests(&txn, res.id).await?;
+                }
+                let applied = res.star_count - previous_count;
+                // Redo puts back exactly what the undo removed
+                delta = Some(if undo { -applied } else { applied });
+                person_id = Some(res.person_id);
+                // Completion times differ on a redo, so keep the state the
+                // next replay has to find
+                landed = Some(serde_json::to_string(&res)?);
+            }
+            UndoAction::UpdateChart => {
+                let (expected, target) = if undo {
+                    (&entry.after, &entry.before)
+                } else {
+                    (&entry.before, &entry.after)
+                };
+                let expected: star_charts::Model =
+                    serde_json::from_str(expected.as_deref().unwrap_or_default())?;
+                let target: star_charts::Model =
+                    serde_json::from_str(target.as_deref().unwrap_or_default())?;
+                let current = star_charts::Entity::find_by_id(entry.target_id)
+                    .one(&txn)
+                    .await?
+                    .ok_or_else(|| {
+                        HGError::NotFound(format!(
+                            "star chart {} no longer exists",
+                            entry.target_id
+                        ))
+                    })?;
+                if !same_chart_state(&current, &expected) {
+                    return Err(HGError::Conflict(format!(
+                        "star chart {} has changed since",
+                        entry.target_id
+                    ))
+                    .into());
+                }
+
+                let mut am = target.into_active_model().reset_all();
+                am.updated_at = Set(Utc::now());
+                let res = save_star_chart(&txn, am, current.version).await?;
+                if res.star_count != current.star_count {
+                    crate::entity::helpers::record_star_event(
+                        &txn,
+                        res.id,
+                        res.star_count - current.star_count,
+                        StarEventKind::Undo,
+                        Some(note.to_string()),
+                        None,
+                    )
+                    .await?;
+                    credit_allowance(&txn, &res, res.star_count - current.star_count, None).await?;
+                }
+                if res.completed_at.is_some() {
+                    unlock_quests(&txn, res.id).await?;
+                } else {
+                    relock_quests(&txn, res.id).await?;
+                }
+                person_id = Some(res.person_id);
+            }
+            UndoAction::CreateEvent | UndoAction::UpdateEvent | UndoAction::DeleteEvent => {
+                let (expected, target) = if undo {
+                    (&entry.after, &entry.before)
+                } else {
+                    (&entry.before, &entry.after)
+                };
+                let expected = event_snapshot(expected.as_deref())?;
+                let target = event_snapshot(target.as_deref())?;
+                for event in &expected {
+                    let current = calendar_events::Entity::find_by_id(event.id)
+                        .one(&txn)
+                        .await?;
+                    if current.as_ref() != Some(event) {
+                        return Err(HGError::Conflict(format!(
+                            "calendar event {} has changed since",
+                            event.id
+                        ))
+                        .into());
+                    }
+                }
+                for event in target
+                    .iter()
+                    .filter(|t| !expected.iter().any(|e| e.id == t.id))
+                {
+                    if calendar_events::Entity::find_by_id(event.id)
+                        .one(&txn)
+                        .await?
+                        .is_some()
+                    {
+                        return Err(HGError::Conflict(format!(
+                            "calendar event {} already exists",
+                            event.id
+                        ))
+                        .into());
+                    }
+                }
+
+                for event in expected
+                    .iter()
+                    .filter(|e| !target.iter().any(|t| t.id == e.id))
+                {
+                    calendar_events::Entity::delete_by_id(event.id)
+                        .exec(&txn)
+                        .await?;
+                }
+                for event in target {
+                    let existed = expected.iter().any(|e| e.id == event.id);
+                    let am = event.into_active_model().reset_all();
+                    if existed {
+                        am.update(&txn).await?;
+                    } else {
+                        am.insert(&txn).await?;
+                    }
+                }
+                touch_calendar(&txn).await?;
+            }
+        }
+
+        let mut am = undo_entries::ActiveModel {
+            id: Set(entry.id),
+            undone: Set(undo),
+            delta: Set(delta),
+            ..Default::default()
+        };
+        if let Some(landed) = landed {
+            if undo {
+                am.before = Set(Some(landed));
+            } else {
+                am.after = Set(Some(landed));
+            }
+        }
+        am.update(&txn).await?;
+        txn.commit().await?;
+        if let Some(person_id) = person_id {
+            self.refresh_progress(person_id).await;
+        }
+
+        Ok(UndoResponse {
+            action: entry.action,
+            target_id: entry.target_id,
+        })
+    }
+}
+
+/// Whether a chart still matches a snapshot, ignoring the bookkeeping
+/// columns every write touches
+fn same_chart_state(
+    current: &crate::entity::star_charts::Model,
+    snapshot: &crate::entity::star_charts::Model,
+) -> bool {
+    let mut snapshot = snapshot.clone();
+    snapshot.version = current.version;
+    snapshot.updated_at = current.updated_at;
+    *current == snapshot
+}
+
+impl SQLConnector {
+    pub async fn list_rewards(&self) -> Result<Vec<GetRewardResponse>, anyhow::Error> {
+        let db = self.database_connection.as_ref().unwrap();
+        let rewards = crate::entity::rewards::Entity::find().all(db).await?;
+        Ok(rewards.into_iter().map(reward_response).collect())
+    }
+
+    pub async fn get_reward(
+        &self,
+        reward_id: i32,
+    ) -> Result<Option<GetRewardResponse>, anyhow::Error> {
+        let db = self.database_connection.as_ref().unwrap();
+        let reward = crate::entity::rewards::Entity::find_by_id(reward_id)
+            .one(db)
+            .await?;
+        Ok(reward.map(reward_response))
+    }
+
+    pub async fn create_reward(
+        &self,
+        reward: &CreateRewardRequest,
+    ) -> Result<CreateRewardResponse, anyhow::Error> {
+        let db = self.database_connection.as_ref().unwrap();
+        validate_reward(reward.cost, reward.stock)?;
+        validate_reward_person(db, reward.person_id).await?;
+
+        let new_reward = crate::entity::rewards::ActiveModel {
+            name: Set(reward.name.clone()),
+            description: Set(reward.description.clone()),
+            cost: Set(reward.cost),
+            person_id: Set(reward.person_id),
+            stock: Set(reward.stock),
+            created_at: Set(Utc::now()),
+            ..Default::default()
+        };
+        let result = new_reward.insert(db).await?;
+        Ok(CreateRewardResponse { id: result.id })
+    }
+
+    pub async fn update_reward(
+        &self,
+        reward_id: i32,
+        reward: &UpdateRewardRequest,
+    ) -> Result<(), anyhow::Error> {
+        use crate::entity::rewards;
+
+        let db = self.database_connection.as_ref().unwrap();
+        let existing = rewards::Entity::find_by_id(reward_id).one(db).await?;
+        let Some(existing) = existing else {
+            return Err(HGError::NotFound(format!("reward {} not found", reward_id)).into());
+        };
+        validate_reward(
+            reward.cost.unwrap_or(existing.cost),
+            reward.stock.unwrap_or(existing.stock),
+        )?;
+        if let Some(person_id) = reward.person_id {
+            validate_reward_person(db, person_id).await?;
+        }
+
+        let mut am: rewards::ActiveModel = existing.into();
+        am.name = Set(reward.name.clone());
+        if let Some(description) = &reward.description {
+            am.description = Set(description.clone());
+        }
+        if let Some(cost) = reward.cost {
+            am.cost = Set(cost);
+        }
+        if let Some(person_id) = reward.person_id {
+            am.person_id = Set(person_id);
+        }
+        if let Some(stock) = reward.stock {
+            am.stock = Set(stock);
+        }
+        am.update(db).await?;
+        Ok(())
+    }
+
+    /// Delete a reward nobody has redeemed yet. Redemptions are the record of
+    /// stars spent, so a redeemed reward is retired by setting its stock to 0.
+    pub async fn delete_reward(&self, reward_id: i32) -> Result<(), anyhow::Error> {
+        use crate::entity::{reward_redemptions, rewards};
+        use sea_orm::PaginatorTrait;
+
+        let db = self.database_connection.as_ref().unwrap();
+        let txn = db.begin().await?;
+        let redemptions = reward_redemptions::Entity::find()
+            .filter(reward_redemptions::Column::RewardId.eq(reward_id))
+            .count(&txn)
+            .await?;
+        if redemptions > 0 {
+            return Err(HGError::Conflict(format!(
+                "reward {} has been redeemed, set its stock to 0 instead",
+                reward_id
+            ))
+            .into());
+        }
+        let res = rewards::Entity::delete_by_id(reward_id).exec(&txn).await?;
+        if res.rows_affected == 0 {
+            return Err(HGError::NotFound(format!("reward {} not found", reward_id)).into());
+        }
+        txn.commit().await?;
+        Ok(())
+    }
+
+    /// Spend stars on a reward. Stars come out of the requested chart, or out of
+    /// the person's charts in order when no chart is given. The deduction, the
+    /// stock change and the redemption row are committed together.
+    pub async fn redeem_reward(
+        &self,
+        reward_id: i32,
+        redeem: &RedeemRewardRequest,
+    ) -> Result<RedeemRewardResponse, anyhow::Error> {
+        use crate::entity::reward_redemptions::{self, RedemptionStatus};
+        use crate::entity::{people, rewards, star_charts};
+
+        let db = self.database_connection.as_ref().unwrap();
+        let txn = db.begin().await?;
+
+        let Some(reward) = rewards::Entity::find_by_id(reward_id).one(&txn).await? else {
+            return Err(HGError::NotFound(format!("reward {} not found", reward_id)).into());
+        };
+        let Some(person) = people::Entity::find_by_id(redeem.person_id)
+            .one(&txn)
+            .await?
+        else {
+            return Err(HGError::NotFound(format!("person {} not found", redeem.person_id)).into());
+        };
+        if person.archived_at.is_some() {
+            return Err(HGError::Conflict(format!("{} is archived", person.first_name)).into());
+        }
+        if reward.person_id.is_some_and(|id| id != person.id) {
+            return Err(HGError::Invalid(format!(
+                "{} is not available to {}",
+                reward.name, person.first_name
+            ))
+            .into());
+        }
+        if reward.stock.is_some_and(|s| s <= 0) {
+            return Err(HGError::Conflict(format!("{} is out of stock", reward.name)).into());
+        }
+
+        let charts = match redeem.star_chart_id {
+            Some(chart_id) => {
+                let chart = star_charts::Entity::find_by_id(chart_id).one(&txn).await?;
+                match chart {
+                    Some(c) if c.person_id == person.id => {
+                        if c.archived_at.is_some() {
+                            return Err(
+                                HGError::Conflict(format!("{} is archived", c.chart_type)).into()
+                            );
+                        }
+                        if !c.status.takes_stars() {
+                            return Err(HGError::Conflict(format!(
+                                "{} is {}",
+                                c.chart_type,
+                                c.status.to_value()
+                            ))
+                            .into());
+                        }
+                        vec![c]
+                    }
+                    _ => {
+                        return Err(HGError::NotFound(format!(
+                            "star chart {} not found for {}",
+                            chart_id, person.first_name
+                        ))
+                        .into());
+                    }
+                }
+            }
+            // Only charts that can still lose stars are spent from
+            None => star_charts::Entity::find()
+                .filter(star_charts::Column::PersonId.eq(person.id))
+                .filter(star_charts::Column::ArchivedAt.is_null())
+                .order_by_asc(star_charts::Column::Id)
+                .all(&txn)
+                .await?
+                .into_iter()
+                .filter(|c| c.status.takes_stars())
+                .collect(),
+        };
+
+        let available: i32 = charts.iter().map(|c| c.star_count.max(0)).sum();
+        if available < reward.cost {
+            return Err(HGError::Conflict(format!(
+                "{} needs {} stars but only {} are available",
+                reward.name, reward.cost, available
+            ))
+            .into());
+        }
+
+        let note = format!("redeemed {}", reward.name);
+        let mut remaining = reward.cost;
+        for chart in charts {
+            if remaining == 0 {
+                break;
+            }
+            let take = chart.star_count.max(0).min(remaining);
+            if take == 0 {
+                continue;
+            }
+            remaining -= take;
+            let chart_id = chart.id;
+            let status = chart.status;
+            let star_total = chart.star_total;
+            let version = chart.version;
+            let new_count = chart.star_count - take;
+            let mut am: star_charts::ActiveModel = chart.into();
+            set_star_count(&mut am, &status, new_count, star_total);
+            save_star_chart(&txn, am, version).await?;
+            crate::entity::helpers::record_star_event(
+                &txn,
+                chart_id,
+                -take,
+                StarEventKind::Redemption,
+                Some(note.clone()),
+                redeem.actor.clone(),
+            )
+            .await?;
+        }
+
+        if let Some(stock) = reward.stock {
+            let mut am: rewards::ActiveModel = reward.clone().into();
+            am.stock = Set(Some(stock - 1));
+            am.update(&txn).await?;
+        }
+
+        let redemption = reward_redemptions::ActiveModel {
+            reward_id: Set(reward.id),
+            person_id: Set(person.id),
+            star_chart_id: Set(redeem.star_chart_id),
+            cost: Set(reward.cost),
+            status: Set(RedemptionStatus::Pending),
+            actor: Set(redeem.actor.clone()),
+            created_at: Set(Utc::now()),
+            delivered_at: Set(None),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+        txn.commit().await?;
+
+        Ok(RedeemRewardResponse {
+            id: redemption.id,
+            cost: redemption.cost,
+            status: redemption.status,
+        })
+    }
+
+    pub async fn list_redemptions(
+        &self,
+        query: &ListRedemptionsQuery,
+    ) -> Result<Vec<GetRedemptionResponse>, anyhow::Error> {
+        use crate::entity::{reward_redemptions, rewards};
+
+        let db = self.database_connection.as_ref().unwrap();
+        let mut select = reward_redemptions::Entity::find()
+            .find_also_related(rewards::Entity)
+            .order_by_desc(reward_redemptions::Column::CreatedAt);
+        if let Some(status) = &query.status {
+            select = select.filter(reward_redemptions::Column::Status.eq(status.clone()));
+        }
+
+        let rows = select.all(db).await?;
+        Ok(rows
+            .into_iter()
+            .map(|(r, reward)| GetRedemptionResponse {
+                id: r.id,
+                reward_id: r.reward_id,
+                reward_name: reward.map(|rw| rw.name).unwrap_or_default(),
+                person_id: r.person_id,
+                star_chart_id: r.star_chart_id,
+                cost: r.cost,
+                status: r.status,
+                actor: r.actor,
+                created_at: r.created_at.to_rfc3339(),
+                delivered_at: r.delivered_at.map(|d| d.to_rfc3339()),
+            })
+            .collect())
+    }
+
+    pub async fn deliver_redemption(&self, redemption_id: i32) -> Result<(), anyhow::Error> {
+        use crate::entity::reward_redemptions::{self, RedemptionStatus};
+
+        let db = self.database_connection.as_ref().unwrap();
+        let existing = reward_redemptions::Entity::find_by_id(redemption_id)
+            .one(db)
+            .await?;
+        let Some(existing) = existing else {
+            return Err(
+                HGError::NotFound(format!("redemption {} not found", redemption_id)).into(),
+            );
+        };
+        if existing.status == RedemptionStatus::Delivered {
+            return Err(HGError::Conflict(format!(
+                "redemption {} was already delivered",
+                redemption_id
+            ))
+            .into());
+        }
+
+        let mut am: reward_redemptions::ActiveModel = existing.into();
+        am.status = Set(RedemptionStatus::Delivered);
+        am.delivered_at = Set(Some(Utc::now()));
+        am.update(db).await?;
+        Ok(())
+    }
+}
+
+/// Current and best streak for each chart, keyed by chart id. Streaks count
+/// periods of the chart's recurrence, or days for charts that never reset.
+async fn load_streaks(
+    db: &DatabaseConnection,
+    charts: &[crate::entity::star_charts::Model],
+) -> Result<HashMap<i32, (i32, i32)>, anyhow::Error> {
+    use crate::entity::star_charts::Recurrence;
+    use crate::entity::star_events;
+
+    let events = star_events::Entity::find()
+        .filter(star_events::Column::StarChartId.is_in(charts.iter().map(|c| c.id)))
+        .filter(star_events::Column::Kind.eq(StarEventKind::Increment))
+        .filter(star_events::Column::Delta.gt(0))
+        .all(db)
+        .await?;
+
+    let mut times: HashMap<i32, Vec<chrono::DateTime<Utc>>> = HashMap::new();
+    for e in events {
+        times.entry(e.star_chart_id).or_default().push(e.created_at);
+    }
+
+    let now = Utc::now();
+    Ok(charts
+        .iter()
+        .map(|c| {
+            let unit = c.recurrence.unwrap_or(Recurrence::Daily);
+            let chart_times = times.get(&c.id).map(Vec::as_slice).unwrap_or_default();
+            (c.id, unit.streaks(chart_times, now))
+        })
+        .collect())
+}
+
+fn star_chart_response(
+    c: crate::entity::star_charts::Model,
+    person_first_name: String,
+    person_last_name: String,
+    streaks: &HashMap<i32, (i32, i32)>,
+) -> GetStarChartResponse {
+    let (current_streak, best_streak) = streaks.get(&c.id).copied().unwrap_or_default();
+    GetStarChartResponse {
+        id: c.id,
+        name: c.chart_type,
+        description: c.chart_key,
+        star_count: c.star_count,
+        star_total: c.star_total,
+        color: c.color,
+        recurrence: c.recurrence,
+        current_streak,
+        best_streak,
+        requires_approval: c.requires_approval,
+        cooldown_seconds: c.cooldown_seconds,
+        daily_cap: c.daily_cap,
+        status: c.status,
+        completed_at: c.completed_at.map(|d| d.to_rfc3339()),
+        overflow_policy: c.overflow_policy,
+        version: c.version,
+        template: c.template_key,
+        cents_per_star: c.cents_per_star,
+        starts_at: c.starts_at.map(|d| d.to_rfc3339()),
+        due_at: c.due_at.map(|d| d.to_rfc3339()),
+        deadline_policy: c.deadline_policy,
+        archived_at: c.archived_at.map(|d| d.to_rfc3339()),
+        person_first_name,
+        person_last_name,
+    }
+}
+
+fn reward_response(r: crate::entity::rewards::Model) -> GetRewardResponse {
+    GetRewardResponse {
+        id: r.id,
+        name: r.name,
+        description: r.description,
+        cost: r.cost,
+        person_id: r.person_id,
+        stock: r.stock,
+    }
+}
+
+fn validate_reward(cost: i32, stock: Option<i32>) -> Result<(), HGError> {
+    if cost <= 0 {
+        return Err(HGError::Invalid(
+            "reward cost must be at least one star".into(),
+        ));
+    }
+    if stock.is_some_and(|s| s < 0) {
+        return Err(HGError::Invalid("reward stock cannot be negative".into()));
+    }
+    Ok(())
+}
+
+/// A reward can only be limited to a person who exists
+async fn validate_reward_person(
+    db: &DatabaseConnection,
+    person_id: Option<i32>,
+) -> Result<(), anyhow::Error> {
+    let Some(person_id) = person_id else {
+        return Ok(());
+    };
+    let person = crate::entity::people::Entity::find_by_id(person_id)
+        .one(db)
+        .await?;
+    if person.is_none() {
+        return Err(HGError::NotFound(format!("person {} not found", person_id)).into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::rrule::parse_event_time;
+
+    fn series(rrule: &str, start: &str) -> Series {
+        Series {
+            rule: rrule.parse().unwrap(),
+            start: parse_event_time(start).unwrap(),
+            duration: chrono::Duration::hours(1),
+            exdates: Vec::new(),
+        }
+    }
+
+    fn starts(
+        rule: &crate::data::rrule::RecurrenceRule,
+        start: &str,
+    ) -> Vec<chrono::NaiveDateTime> {
+        rule.occurrences(
+            parse_event_time(start).unwrap(),
+            chrono::Duration::hours(1),
+            parse_event_time("2026-01-01").unwrap(),
+            parse_event_time("2027-01-01").unwrap(),
+        )
+    }
+
+    #[test]
+    fn split_counted_series() {
+        let series = series("FREQ=WEEKLY;BYDAY=TU,TH;COUNT=5", "2026-03-03T17:00");
+        let at = parse_event_time("2026-03-10T17:00").unwrap();
+        let head = end_before(&series, at);
+        let tail = resume_at(&series, at);
+        assert_eq!(head.count, Some(2));
+        assert_eq!(tail.count, Some(3));
+        assert_eq!(starts(&head, "2026-03-03T17:00").len(), 2);
+        assert_eq!(
+            starts(&tail, "2026-03-10T17:00").last(),
+            starts(&series.rule, "2026-03-03T17:00").last()
+        );
+    }
+
+    #[test]
+    fn split_counted_series_starting_off_the_rule() {
+        // Wednesday start, Monday rule: the start is the first of the four
+        let series = series("FREQ=WEEKLY;BYDAY=MO;COUNT=4", "2026-03-04T09:00");
+        let at = parse_event_time("2026-03-16T09:00").unwrap();
+        assert_eq!(end_before(&series, at).count, Some(2));
+        assert_eq!(resume_at(&series, at).count, Some(2));
+    }
+
+    #[test]
+    fn split_open_series() {
+        let series = series("FREQ=DAILY", "2026-03-01T08:00");
+        let at = parse_event_time("2026-03-05T08:00").unwrap();
+        let head = end_before(&series, at);
+        assert_eq!(head.count, None);
+        assert_eq!(
+            head.until,
+            Some(parse_event_time("2026-03-05T07:59:59").unwrap())
+        );
+        assert_eq!(starts(&head, "2026-03-01T08:00").len(), 4);
+        assert_eq!(resume_at(&series, at), series.rule);
     }
 }
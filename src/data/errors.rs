@@ -0,0 +1,33 @@
+use std::fmt;
+
+/// Errors that callers are expected to handle, as opposed to database failures.
+/// The server maps each variant onto an HTTP status code.
+#[derive(Debug)]
+pub enum HGError {
+    NotFound(String),
+    Invalid(String),
+    Forbidden(String),
+    Conflict(String),
+    /// The row changed since the caller read it
+    VersionMismatch(String),
+    /// The caller should try again after the given number of seconds
+    RateLimited {
+        message: String,
+        retry_after_secs: i64,
+    },
+}
+
+impl fmt::Display for HGError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HGError::NotFound(msg)
+            | HGError::Invalid(msg)
+            | HGError::Forbidden(msg)
+            | HGError::Conflict(msg)
+            | HGError::VersionMismatch(msg) => write!(f, "{msg}"),
+            HGError::RateLimited { message, .. } => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for HGError {}
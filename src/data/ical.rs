@@ -0,0 +1,707 @@
+//! iCalendar (RFC 5545) feeds of the family calendar, so phone calendar apps
+//! can subscribe to it, and reading of calendars exported from elsewhere.
+
+use crate::data::errors::HGError;
+use crate::data::rrule::{RecurrenceRule, parse_event_time};
+use crate::entity::{calendar_events, people};
+use chrono::{
+    DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, Offset, TimeZone, Utc,
+};
+use chrono_tz::Tz;
+use log::warn;
+use std::collections::{HashMap, HashSet};
+
+/// Lines longer than this many octets are folded
+const MAX_LINE: usize = 75;
+
+/// UID of an event, shared by a series and its replaced occurrences
+pub fn event_uid(event_id: i32) -> String {
+    format!("event-{}@hearthglow", event_id)
+}
+
+/// Render `events` as a VCALENDAR. Replaced occurrences are written against
+/// their series' UID when the series is in the feed too, and as events of
+/// their own when it is not. `moved_away` lists, per series, occurrences
+/// replaced by events that are not in the feed, which the series leaves out.
+pub fn write_calendar(
+    name: &str,
+    color: Option<&str>,
+    timezone: Option<Tz>,
+    events: &[calendar_events::Model],
+    moved_away: &HashMap<i32, Vec<NaiveDateTime>>,
+    people: &HashMap<i32, people::Model>,
+) -> String {
+    let mut out = Vec::new();
+    out.push("BEGIN:VCALENDAR".to_string());
+    out.push("VERSION:2.0".to_string());
+    out.push("PRODID:-//HearthGlow//Family Calendar//EN".to_string());
+    out.push("CALSCALE:GREGORIAN".to_string());
+    out.push("METHOD:PUBLISH".to_string());
+    out.push(format!("NAME:{}", escape_text(name)));
+    out.push(format!("X-WR-CALNAME:{}", escape_text(name)));
+    if let Some(color) = color {
+        out.push(format!("X-APPLE-CALENDAR-COLOR:{}", color));
+    }
+    if let Some(timezone) = timezone {
+        out.push(format!("X-WR-TIMEZONE:{}", timezone.name()));
+    }
+    out.push("REFRESH-INTERVAL;VALUE=DURATION:PT1H".to_string());
+    out.push("X-PUBLISHED-TTL:PT1H".to_string());
+    if let Some(timezone) = timezone {
+        // Cover every event and a couple of years of repeats to come
+        let first = events
+            .iter()
+            .filter_map(|e| parse_event_time(&e.start_time).ok())
+            .map(|at| at.year())
+            .min();
+        let this_year = Local::now().year();
+        write_timezone(
+            timezone,
+            first.unwrap_or(this_year),
+            this_year + 2,
+            &mut out,
+        );
+    }
+
+    let in_feed: HashSet<i32> = events.iter().map(|e| e.id).collect();
+
+    for event in events {
+        let (Ok(start), Ok(end)) = (
+            parse_event_time(&event.start_time),
+            parse_event_time(&event.end_time),
+        ) else {
+            warn!("Leaving calendar event {} out of the feed", event.id);
+            continue;
+        };
+
+        let parent = event.parent_event_id.filter(|p| in_feed.contains(p));
+        out.push("BEGIN:VEVENT".to_string());
+        out.push(format!("UID:{}", event_uid(parent.unwrap_or(event.id))));
+        let stamp = event.updated_at.unwrap_or(DateTime::UNIX_EPOCH);
+        out.push(format!("DTSTAMP:{}", stamp.format("%Y%m%dT%H%M%SZ")));
+        if let Some(updated_at) = event.updated_at {
+            out.push(format!(
+                "LAST-MODIFIED:{}",
+                updated_at.format("%Y%m%dT%H%M%SZ")
+            ));
+        }
+        out.push(date_time("DTSTART", start, timezone));
+        out.push(date_time("DTEND", end, timezone));
+        if let (Some(_), Some(recurrence_id)) = (parent, &event.recurrence_id)
+            && let Ok(recurrence_id) = parse_event_time(recurrence_id)
+        {
+            out.push(date_time("RECURRENCE-ID", recurrence_id, timezone));
+        }
+        if let Some(rule) = event
+            .rrule
+            .as_deref()
+            .and_then(|r| r.parse::<RecurrenceRule>().ok())
+        {
+            out.push(rrule(rule, timezone));
+            let mut exdates: Vec<NaiveDateTime> = event
+                .exdates
+                .as_deref()
+                .unwrap_or_default()
+                .split(',')
+                .filter_map(|d| parse_event_time(d).ok())
+                .collect();
+            if let Some(moved) = moved_away.get(&event.id) {
+                exdates.extend(moved);
+            }
+            for exdate in exdates {
+                out.push(date_time("EXDATE", exdate, timezone));
+            }
+        }
+        out.push(format!("SUMMARY:{}", escape_text(&event.title)));
+        if let Some(person) = people.get(&event.person_id) {
+            out.push(format!(
+                "DESCRIPTION:{}",
+                escape_text(&format!("For {}", person.first_name))
+            ));
+            out.push(format!("CATEGORIES:{}", escape_text(&person.first_name)));
+            if let Some(color) = &person.calendar_color {
+                out.push(format!("X-HEARTHGLOW-COLOR:{}", color));
+            }
+        }
+        out.push(format!("X-HEARTHGLOW-PERSON-ID:{}", event.person_id));
+        out.push("END:VEVENT".to_string());
+    }
+    out.push("END:VCALENDAR".to_string());
+
+    let mut body = String::new();
+    for line in out {
+        fold_line(&line, &mut body);
+    }
+    body
+}
+
+/// RRULE content line. With a zone UNTIL has to be given in UTC.
+fn rrule(mut rule: RecurrenceRule, timezone: Option<Tz>) -> String {
+    let until = rule.until.take();
+    let mut line = format!("RRULE:{}", rule);
+    match (until, timezone) {
+        (Some(until), Some(timezone)) => {
+            let until = timezone
+                .from_local_datetime(&until)
+                .earliest()
+                .map(|at| at.with_timezone(&Utc))
+                .unwrap_or_else(|| until.and_utc());
+            line.push_str(&format!(";UNTIL={}", until.format("%Y%m%dT%H%M%SZ")));
+        }
+        (Some(until), None) => line.push_str(&format!(";UNTIL={}", until.format("%Y%m%dT%H%M%S"))),
+        (None, _) => {}
+    }
+    line
+}
+
+/// VTIMEZONE describing `timezone`, found by probing its offset for changes
+/// from the start of `from` to the end of `to`
+fn write_timezone(timezone: Tz, from: i32, to: i32, out: &mut Vec<String>) {
+    let offset_at = |at: NaiveDateTime| {
+        timezone
+            .offset_from_utc_datetime(&at)
+            .fix()
+            .local_minus_utc()
+    };
+    let start = NaiveDate::from_ymd_opt(from, 1, 1)
+        .unwrap_or_default()
+        .and_time(chrono::NaiveTime::MIN);
+    let end = NaiveDate::from_ymd_opt(to + 1, 1, 1)
+        .unwrap_or_default()
+        .and_time(chrono::NaiveTime::MIN);
+
+    // (UTC instant of the change, offset before, offset after)
+    let mut changes = Vec::new();
+    let mut day = start;
+    while day < end {
+        let next = day + Duration::days(1);
+        let (before, after) = (offset_at(day), offset_at(next));
+        if before != after {
+            // Narrow the change down to the second
+            let (mut lo, mut hi) = (day, next);
+            while hi - lo > Duration::seconds(1) {
+                let mid = lo + (hi - lo) / 2;
+                if offset_at(mid) == before {
+                    lo = mid;
+                } else {
+                    hi = mid;
+                }
+            }
+            changes.push((hi, before, after));
+        }
+        day = next;
+    }
+
+    out.push("BEGIN:VTIMEZONE".to_string());
+    out.push(format!("TZID:{}", timezone.name()));
+    let initial = offset_at(start);
+    let daylight = changes.iter().map(|c| c.2).max().unwrap_or(initial);
+    let standard = changes.iter().map(|c| c.2).min().unwrap_or(initial);
+    let kind = |offset: i32| {
+        if offset == daylight && daylight != standard {
+            "DAYLIGHT"
+        } else {
+            "STANDARD"
+        }
+    };
+    out.push(format!("BEGIN:{}", kind(initial)));
+    out.push("DTSTART:19700101T000000".to_string());
+    out.push(format!("TZOFFSETFROM:{}", format_offset(initial)));
+    out.push(format!("TZOFFSETTO:{}", format_offset(initial)));
+    out.push(format!("END:{}", kind(initial)));
+    for (at, before, after) in changes {
+        // Onsets are given in the local time in force before the change
+        let onset = at + Duration::seconds(i64::from(before));
+        out.push(format!("BEGIN:{}", kind(after)));
+        out.push(format!("DTSTART:{}", onset.format("%Y%m%dT%H%M%S")));
+        out.push(format!("TZOFFSETFROM:{}", format_offset(before)));
+        out.push(format!("TZOFFSETTO:{}", format_offset(after)));
+        out.push(format!("END:{}", kind(after)));
+    }
+    out.push("END:VTIMEZONE".to_string());
+}
+
+/// UTC offset as iCalendar writes it, such as +0100 or -0530
+fn format_offset(seconds: i32) -> String {
+    let sign = if seconds < 0 { '-' } else { '+' };
+    let minutes = seconds.abs() / 60;
+    format!("{}{:02}{:02}", sign, minutes / 60, minutes % 60)
+}
+
+/// A date-time property in the configured zone, or floating without one
+fn date_time(name: &str, at: NaiveDateTime, timezone: Option<Tz>) -> String {
+    let value = at.format("%Y%m%dT%H%M%S");
+    match timezone {
+        Some(timezone) => format!("{};TZID={}:{}", name, timezone.name(), value),
+        None => format!("{}:{}", name, value),
+    }
+}
+
+fn escape_text(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Write a content line, folding it onto continuation lines that start with
+/// a space so no line is longer than 75 octets
+fn fold_line(line: &str, out: &mut String) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > MAX_LINE {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+/// A VEVENT read from an imported calendar, with its times as floating local
+/// times like the ones the calendar stores
+#[derive(Debug, Clone)]
+pub struct ParsedEvent {
+    pub uid: Option<String>,
+    pub summary: Option<String>,
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+    pub rrule: Option<RecurrenceRule>,
+    pub exdates: Vec<NaiveDateTime>,
+    /// Original start of the occurrence this event replaces in its series
+    pub recurrence_id: Option<NaiveDateTime>,
+    pub cancelled: bool,
+}
+
+/// A VEVENT that could not be read, and why
+#[derive(Debug, Clone)]
+pub struct UnreadableEvent {
+    pub uid: Option<String>,
+    pub summary: Option<String>,
+    pub reason: String,
+}
+
+/// Read every VEVENT of an iCalendar file. Times in UTC are moved to
+/// `timezone`, or the server's zone without one; times with a TZID are taken
+/// as wall-clock times, which is right for calendars kept in the family's
+/// own zone.
+pub fn parse_calendar(
+    text: &str,
+    timezone: Option<Tz>,
+) -> Result<Vec<Result<ParsedEvent, UnreadableEvent>>, HGError> {
+    // Continuation lines start with a space or a tab
+    let text = text
+        .trim_start_matches('\u{feff}')
+        .replace("\r\n ", "")
+        .replace("\r\n\t", "")
+        .replace("\n ", "")
+        .replace("\n\t", "");
+
+    let mut found = false;
+    let mut events = Vec::new();
+    let mut components: Vec<String> = Vec::new();
+    let mut properties = Vec::new();
+    for line in text.lines().filter(|l| !l.trim().is_empty()) {
+        let Some(line) = ContentLine::parse(line) else {
+            continue;
+        };
+        match line.name.as_str() {
+            "BEGIN" => {
+                let component = line.value.to_ascii_uppercase();
+                if component == "VCALENDAR" && !components.is_empty() {
+                    return Err(HGError::Invalid("nested VCALENDAR".to_string()));
+                }
+                if component != "VCALENDAR" && components.is_empty() {
+                    return Err(HGError::Invalid("not an iCalendar file".to_string()));
+                }
+                if component == "VEVENT" {
+                    properties.clear();
+                }
+                found = true;
+                components.push(component);
+            }
+            "END" => {
+                let ended = components.pop();
+                if ended.as_deref() == Some("VEVENT") {
+                    events.push(parse_event(&properties, timezone));
+                }
+            }
+            // Alarms and other parts nested in an event are left behind
+            _ if components.last().map(String::as_str) == Some("VEVENT") => {
+                properties.push(line);
+            }
+            _ => {}
+        }
+    }
+    if !found {
+        return Err(HGError::Invalid("not an iCalendar file".to_string()));
+    }
+    Ok(events)
+}
+
+/// One `NAME;PARAM=value:value` line
+#[derive(Debug, Clone)]
+struct ContentLine {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl ContentLine {
+    fn parse(line: &str) -> Option<ContentLine> {
+        // Colons and semicolons inside quoted parameter values don't count
+        let mut quoted = false;
+        let mut head_end = None;
+        let mut splits = Vec::new();
+        for (i, c) in line.char_indices() {
+            match c {
+                '"' => quoted = !quoted,
+                ';' if !quoted => splits.push(i),
+                ':' if !quoted => {
+                    head_end = Some(i);
+                    break;
+                }
+                _ => {}
+            }
+        }
+        let head_end = head_end?;
+        splits.push(head_end);
+        let name = line[..splits[0]].trim().to_ascii_uppercase();
+        let params = splits
+            .windows(2)
+            .filter_map(|w| {
+                let (key, value) = line[w[0] + 1..w[1]].split_once('=')?;
+                Some((
+                    key.to_ascii_uppercase(),
+                    value.trim_matches('"').to_string(),
+                ))
+            })
+            .collect();
+        Some(ContentLine {
+            name,
+            params,
+            value: line[head_end + 1..].trim_end_matches('\r').to_string(),
+        })
+    }
+
+    fn param(&self, key: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+fn parse_event(
+    properties: &[ContentLine],
+    timezone: Option<Tz>,
+) -> Result<ParsedEvent, UnreadableEvent> {
+    let find = |name: &str| properties.iter().find(|p| p.name == name);
+    let uid = find("UID").map(|p| p.value.trim().to_string());
+    let summary = find("SUMMARY").map(|p| unescape_text(&p.value));
+    let unreadable = |reason: String| UnreadableEvent {
+        uid: uid.clone(),
+        summary: summary.clone(),
+        reason,
+    };
+
+    let (start, all_day) = find("DTSTART")
+        .ok_or_else(|| unreadable("no DTSTART".to_string()))
+        .and_then(|p| parse_date_time(p, &p.value, timezone).map_err(unreadable))?;
+    let end = match (find("DTEND"), find("DURATION")) {
+        (Some(p), _) => {
+            parse_date_time(p, &p.value, timezone)
+                .map_err(unreadable)?
+                .0
+        }
+        (None, Some(p)) => parse_duration(&p.value)
+            .and_then(|d| {
+                start
+                    .checked_add_signed(d)
+                    .ok_or_else(|| format!("DURATION {} is out of range", p.value))
+            })
+            .map_err(unreadable)?,
+        // An all-day event without an end lasts the day
+        (None, None) if all_day => start
+            .checked_add_signed(Duration::days(1))
+            .ok_or_else(|| unreadable("DTSTART is out of range".to_string()))?,
+        (None, None) => start,
+    };
+    if end < start {
+        return Err(unreadable("ends before it starts".to_string()));
+    }
+    // Stored times have four digit years
+    if !(1..=9999).contains(&end.year()) {
+        return Err(unreadable("ends out of range".to_string()));
+    }
+
+    let rrule = match find("RRULE") {
+        Some(p) => {
+            let mut rule: RecurrenceRule = p
+                .value
+                .parse()
+                .map_err(|e: HGError| unreadable(e.to_string()))?;
+            let utc_until = p
+                .value
+                .split(';')
+                .any(|part| part.to_ascii_uppercase().starts_with("UNTIL=") && part.ends_with('Z'));
+            if utc_until {
+                rule.until = rule.until.map(|u| wall_clock(u, timezone));
+            }
+            Some(rule)
+        }
+        None => None,
+    };
+    let mut exdates = Vec::new();
+    for p in properties.iter().filter(|p| p.name == "EXDATE") {
+        for value in p.value.split(',') {
+            exdates.push(parse_date_time(p, value, timezone).map_err(unreadable)?.0);
+        }
+    }
+    let recurrence_id = match find("RECURRENCE-ID") {
+        Some(p) => Some(
+            parse_date_time(p, &p.value, timezone)
+                .map_err(unreadable)?
+                .0,
+        ),
+        None => None,
+    };
+    let cancelled = find("STATUS").is_some_and(|p| p.value.eq_ignore_ascii_case("CANCELLED"));
+
+    Ok(ParsedEvent {
+        uid,
+        summary,
+        start,
+        end,
+        rrule,
+        exdates,
+        recurrence_id,
+        cancelled,
+    })
+}
+
+/// A DATE or DATE-TIME value of `property`, and whether it was a whole day
+fn parse_date_time(
+    property: &ContentLine,
+    value: &str,
+    timezone: Option<Tz>,
+) -> Result<(NaiveDateTime, bool), String> {
+    let value = value.trim();
+    let bad = || format!("invalid {} {}", property.name, value);
+    if property
+        .param("VALUE")
+        .is_some_and(|v| v.eq_ignore_ascii_case("DATE"))
+        || value.len() == 8
+    {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d").map_err(|_| bad())?;
+        return Ok((date.and_time(chrono::NaiveTime::MIN), true));
+    }
+    match value.strip_suffix('Z') {
+        Some(utc) => {
+            let at = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").map_err(|_| bad())?;
+            Ok((wall_clock(at, timezone), false))
+        }
+        None => NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+            .map(|at| (at, false))
+            .map_err(|_| bad()),
+    }
+}
+
+/// A UTC time as the clock on the wall reads it in `timezone`, or in the
+/// server's zone without one
+fn wall_clock(at: NaiveDateTime, timezone: Option<Tz>) -> NaiveDateTime {
+    match timezone {
+        Some(timezone) => timezone.from_utc_datetime(&at).naive_local(),
+        None => Local.from_utc_datetime(&at).naive_local(),
+    }
+}
+
+/// A DURATION value such as PT1H30M or P1W
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let bad = || format!("invalid DURATION {}", value);
+    let (negative, rest) = match value.trim().strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value.trim().trim_start_matches('+')),
+    };
+    let rest = rest.strip_prefix('P').ok_or_else(bad)?;
+    let mut total = Duration::zero();
+    let mut in_time = false;
+    let mut number = String::new();
+    for c in rest.chars() {
+        match c {
+            'T' => in_time = true,
+            '0'..='9' => number.push(c),
+            _ => {
+                let n: i64 = number.parse().map_err(|_| bad())?;
+                number.clear();
+                let part = match (c, in_time) {
+                    ('W', false) => Duration::try_weeks(n),
+                    ('D', false) => Duration::try_days(n),
+                    ('H', true) => Duration::try_hours(n),
+                    ('M', true) => Duration::try_minutes(n),
+                    ('S', true) => Duration::try_seconds(n),
+                    _ => return Err(bad()),
+                };
+                total = part.and_then(|p| total.checked_add(&p)).ok_or_else(bad)?;
+            }
+        }
+    }
+    if !number.is_empty() {
+        return Err(bad());
+    }
+    Ok(if negative { -total } else { total })
+}
+
+fn unescape_text(value: &str) -> String {
+    let mut out = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => {}
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(value: &str) -> NaiveDateTime {
+        parse_event_time(value).unwrap()
+    }
+
+    fn parse_one(event: &str) -> Result<ParsedEvent, UnreadableEvent> {
+        let text = format!(
+            "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nBEGIN:VEVENT\r\n{}\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n",
+            event
+        );
+        let mut events = parse_calendar(&text, None).unwrap();
+        assert_eq!(events.len(), 1);
+        events.remove(0)
+    }
+
+    #[test]
+    fn parse_calendar_unfolds_lines() {
+        let text = "BEGIN:VCALENDAR\r\n\
+                    BEGIN:VEVENT\r\n\
+                    UID:abc\r\n\
+                    SUMMARY:Piano lesson with a ve\r\n ry long title\\, folded\r\n\
+                    DTSTART:2026031\r\n\t4T160000\r\n\
+                    DTEND:20260314T170000\r\n\
+                    RRULE:FREQ=WEEKLY;\n COUNT=3\n\
+                    END:VEVENT\r\n\
+                    END:VCALENDAR\r\n";
+        let events = parse_calendar(text, None).unwrap();
+        let event = events[0].as_ref().unwrap();
+        assert_eq!(
+            event.summary.as_deref(),
+            Some("Piano lesson with a very long title, folded")
+        );
+        assert_eq!(event.start, at("2026-03-14T16:00"));
+        assert_eq!(event.end, at("2026-03-14T17:00"));
+        assert_eq!(event.rrule.as_ref().unwrap().count, Some(3));
+    }
+
+    #[test]
+    fn fold_and_unfold_round_trip() {
+        let summary = format!("SUMMARY:{}", "Långt namn ".repeat(12));
+        let mut folded = String::new();
+        fold_line(&summary, &mut folded);
+        assert!(folded.split("\r\n").all(|l| l.len() <= MAX_LINE));
+        let event = parse_one(&format!("{}DTSTART:20260314T160000", folded)).unwrap();
+        assert_eq!(event.summary.as_deref(), summary.strip_prefix("SUMMARY:"));
+    }
+
+    #[test]
+    fn parse_calendar_rejects_other_files() {
+        assert!(parse_calendar("hello", None).is_err());
+        assert!(parse_calendar("BEGIN:VEVENT\r\nEND:VEVENT\r\n", None).is_err());
+    }
+
+    #[test]
+    fn end_from_duration_or_whole_day() {
+        let event = parse_one("DTSTART:20260314T160000\r\nDURATION:PT1H30M").unwrap();
+        assert_eq!(event.end, at("2026-03-14T17:30"));
+        let event = parse_one("DTSTART;VALUE=DATE:20260314").unwrap();
+        assert_eq!(event.end, at("2026-03-15T00:00"));
+        assert!(parse_one("DTSTART:20260314T160000\r\nDURATION:-PT1H").is_err());
+        assert!(parse_one("DTSTART:99991231T230000\r\nDURATION:PT2H").is_err());
+        assert!(parse_one("DTSTART:20260314T160000\r\nDURATION:P9999999999999W").is_err());
+    }
+
+    #[test]
+    fn timezone_follows_the_configured_zone() {
+        let mut out = Vec::new();
+        write_timezone(chrono_tz::Europe::London, 2026, 2026, &mut out);
+        let text = out.join("\n");
+        assert!(text.contains("TZID:Europe/London"));
+        assert!(text.contains(
+            "BEGIN:DAYLIGHT\nDTSTART:20260329T010000\nTZOFFSETFROM:+0000\nTZOFFSETTO:+0100"
+        ));
+        assert!(text.contains(
+            "BEGIN:STANDARD\nDTSTART:20261025T020000\nTZOFFSETFROM:+0100\nTZOFFSETTO:+0000"
+        ));
+    }
+
+    #[test]
+    fn until_and_utc_times_use_the_configured_zone() {
+        let zone = Some(chrono_tz::America::New_York);
+        let rule: RecurrenceRule = "FREQ=WEEKLY;UNTIL=20260701T100000".parse().unwrap();
+        assert_eq!(
+            rrule(rule.clone(), zone),
+            "RRULE:FREQ=WEEKLY;UNTIL=20260701T140000Z"
+        );
+        assert_eq!(rrule(rule, None), "RRULE:FREQ=WEEKLY;UNTIL=20260701T100000");
+
+        let text = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\n\
+                    DTSTART:20260115T150000Z\r\n\
+                    RRULE:FREQ=DAILY;UNTIL=20260120T150000Z\r\n\
+                    END:VEVENT\r\nEND:VCALENDAR\r\n";
+        let events = parse_calendar(text, zone).unwrap();
+        let event = events[0].as_ref().unwrap();
+        assert_eq!(event.start, at("2026-01-15T10:00"));
+        assert_eq!(
+            event.rrule.as_ref().unwrap().until,
+            Some(at("2026-01-20T10:00"))
+        );
+    }
+
+    #[test]
+    fn parse_duration_values() {
+        assert_eq!(parse_duration("PT1H30M"), Ok(Duration::minutes(90)));
+        assert_eq!(parse_duration("P1W"), Ok(Duration::weeks(1)));
+        assert_eq!(
+            parse_duration("P1DT2H3M4S"),
+            Ok(Duration::seconds(86_400 + 7_200 + 180 + 4))
+        );
+        assert_eq!(parse_duration("+PT15M"), Ok(Duration::minutes(15)));
+        assert_eq!(parse_duration("-PT15M"), Ok(Duration::minutes(-15)));
+    }
+
+    #[test]
+    fn parse_duration_rejects_bad_values() {
+        for value in [
+            "1H",
+            "PT1",
+            "P1H",
+            "PT1D",
+            "PTH",
+            "P1Y",
+            "P99999999999999999999D",
+            "P9999999999999W",
+            "PT9223372036854775807S",
+        ] {
+            assert!(parse_duration(value).is_err(), "{}", value);
+        }
+    }
+}
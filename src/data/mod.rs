@@ -1,2 +1,5 @@
 pub mod configuration;
 pub mod dbconnector;
+pub mod errors;
+pub mod ical;
+pub mod rrule;
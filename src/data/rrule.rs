@@ -0,0 +1,599 @@
+//! Just enough of RFC 5545 recurrence rules for a family calendar: FREQ,
+//! INTERVAL, COUNT, UNTIL, BYDAY, BYMONTHDAY and BYMONTH. Calendar times are
+//! stored as floating local times, so expansion works on naive datetimes.
+
+use crate::data::errors::HGError;
+use chrono::{Datelike, Duration, Months, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+use std::fmt;
+use std::str::FromStr;
+
+/// Upper bound on periods walked while expanding, so a rule that can never
+/// match (say the 30th of February) cannot spin forever
+const MAX_PERIODS: u32 = 10_000;
+
+/// The format calendar event times are stored in
+pub const EVENT_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// Read a calendar time as a floating local time. Accepts the stored format
+/// with or without seconds, a bare date for midnight, and RFC 3339 with the
+/// offset dropped.
+pub fn parse_event_time(value: &str) -> Result<NaiveDateTime, HGError> {
+    if let Ok(at) = NaiveDateTime::parse_from_str(value, EVENT_TIME_FORMAT) {
+        return Ok(at);
+    }
+    if let Ok(at) = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M") {
+        return Ok(at);
+    }
+    if let Ok(day) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(day.and_time(NaiveTime::MIN));
+    }
+    chrono::DateTime::parse_from_rfc3339(value)
+        .map(|at| at.naive_local())
+        .map_err(|_| HGError::Invalid(format!("{} is not a calendar time", value)))
+}
+
+pub fn format_event_time(at: NaiveDateTime) -> String {
+    at.format(EVENT_TIME_FORMAT).to_string()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// A BYDAY entry such as `TU`, `2SA` or `-1FR`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DaySpec {
+    pub ordinal: Option<i32>,
+    pub weekday: Weekday,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecurrenceRule {
+    pub frequency: Frequency,
+    pub interval: u32,
+    pub count: Option<u32>,
+    pub until: Option<NaiveDateTime>,
+    pub by_day: Vec<DaySpec>,
+    pub by_month_day: Vec<i32>,
+    pub by_month: Vec<u32>,
+}
+
+impl RecurrenceRule {
+    /// Start times of the occurrences that overlap `[from, to)`, for a series
+    /// whose first occurrence starts at `dtstart` and lasts `duration`
+    pub fn occurrences(
+        &self,
+        dtstart: NaiveDateTime,
+        duration: Duration,
+        from: NaiveDateTime,
+        to: NaiveDateTime,
+    ) -> Vec<NaiveDateTime> {
+        let mut found = Vec::new();
+        self.walk(dtstart, |start| {
+            if start >= to {
+                return false;
+            }
+            if start + duration > from {
+                found.push(start);
+            }
+            true
+        });
+        found
+    }
+
+    /// How many occurrences start before `at`
+    pub fn count_before(&self, dtstart: NaiveDateTime, at: NaiveDateTime) -> u32 {
+        let mut count = 0;
+        self.walk(dtstart, |start| {
+            if start >= at {
+                return false;
+            }
+            count += 1;
+            true
+        });
+        count
+    }
+
+    /// Whether `at` is one of the series' occurrences
+    pub fn includes(&self, dtstart: NaiveDateTime, at: NaiveDateTime) -> bool {
+        let mut hit = false;
+        self.walk(dtstart, |start| {
+            hit = start == at;
+            start < at
+        });
+        hit
+    }
+
+    /// Feed every occurrence, in order, to `visit` until it returns false or
+    /// the rule runs out. The series' own start always counts as the first
+    /// occurrence, even when it does not fit the rule, as in RFC 5545.
+    fn walk(&self, dtstart: NaiveDateTime, mut visit: impl FnMut(NaiveDateTime) -> bool) {
+        if self.until.is_some_and(|until| dtstart > until) || !visit(dtstart) {
+            return;
+        }
+        let time = dtstart.time();
+        let mut seen = 1;
+        for period in 0..MAX_PERIODS {
+            let Some(days) = period
+                .checked_mul(self.interval)
+                .and_then(|offset| self.period_days(dtstart.date(), offset))
+            else {
+                return;
+            };
+            for day in days {
+                let start = day.and_time(time);
+                if start <= dtstart {
+                    continue;
+                }
+                if self.until.is_some_and(|until| start > until)
+                    || self.count.is_some_and(|count| seen >= count)
+                    || !visit(start)
+                {
+                    return;
+                }
+                seen += 1;
+            }
+        }
+    }
+
+    /// Candidate days, in order, of the period `offset` periods after the
+    /// one containing `start`
+    fn period_days(&self, start: NaiveDate, offset: u32) -> Option<Vec<NaiveDate>> {
+        let mut days = match self.frequency {
+            Frequency::Daily => {
+                let day = start.checked_add_signed(Duration::days(i64::from(offset)))?;
+                let weekday_ok = self.by_day.is_empty()
+                    || self.by_day.iter().any(|d| d.weekday == day.weekday());
+                let month_day_ok = self.by_month_day.is_empty()
+                    || self.by_month_day.contains(&month_day_index(day, true))
+                    || self.by_month_day.contains(&month_day_index(day, false));
+                if weekday_ok && month_day_ok {
+                    vec![day]
+                } else {
+                    Vec::new()
+                }
+            }
+            Frequency::Weekly => {
+                let monday = start
+                    .checked_sub_signed(Duration::days(
+                        start.weekday().num_days_from_monday().into(),
+                    ))?
+                    .checked_add_signed(Duration::weeks(i64::from(offset)))?;
+                let mut weekdays: Vec<Weekday> = if self.by_day.is_empty() {
+                    vec![start.weekday()]
+                } else {
+                    self.by_day.iter().map(|d| d.weekday).collect()
+                };
+                weekdays.sort_by_key(|w| w.num_days_from_monday());
+                weekdays.dedup();
+                weekdays
+                    .into_iter()
+                    .map(|w| monday + Duration::days(w.num_days_from_monday().into()))
+                    .collect()
+            }
+            Frequency::Monthly => {
+                let first = start.with_day(1)?.checked_add_months(Months::new(offset))?;
+                self.month_days(first, start.day())
+            }
+            Frequency::Yearly => {
+                let year = start.year().checked_add(i32::try_from(offset).ok()?)?;
+                let months = if self.by_month.is_empty() {
+                    vec![start.month()]
+                } else {
+                    self.by_month.clone()
+                };
+                let mut days = Vec::new();
+                for month in months {
+                    let first = NaiveDate::from_ymd_opt(year, month, 1)?;
+                    days.extend(self.month_days(first, start.day()));
+                }
+                days
+            }
+        };
+        if !self.by_month.is_empty() {
+            days.retain(|d| self.by_month.contains(&d.month()));
+        }
+        days.sort();
+        days.dedup();
+        Some(days)
+    }
+
+    /// Days of the month starting at `first` picked out by BYMONTHDAY and
+    /// BYDAY, or the series' own day of the month when neither is given
+    fn month_days(&self, first: NaiveDate, default_day: u32) -> Vec<NaiveDate> {
+        let all: Vec<NaiveDate> = first
+            .iter_days()
+            .take_while(|d| d.month() == first.month())
+            .collect();
+        if self.by_day.is_empty() && self.by_month_day.is_empty() {
+            return all.into_iter().filter(|d| d.day() == default_day).collect();
+        }
+        all.into_iter()
+            .filter(|d| {
+                self.by_month_day.is_empty()
+                    || self.by_month_day.contains(&month_day_index(*d, true))
+                    || self.by_month_day.contains(&month_day_index(*d, false))
+            })
+            .filter(|d| {
+                self.by_day.is_empty()
+                    || self.by_day.iter().any(|spec| {
+                        spec.weekday == d.weekday()
+                            && match spec.ordinal {
+                                None => true,
+                                Some(n) if n > 0 => (d.day() as i32 - 1) / 7 + 1 == n,
+                                Some(n) => -((month_length(*d) - d.day() as i32) / 7 + 1) == n,
+                            }
+                    })
+            })
+            .collect()
+    }
+}
+
+/// Day of the month counted from the start (1..) or from the end (..-1)
+fn month_day_index(day: NaiveDate, from_start: bool) -> i32 {
+    if from_start {
+        day.day() as i32
+    } else {
+        day.day() as i32 - month_length(day) - 1
+    }
+}
+
+fn month_length(day: NaiveDate) -> i32 {
+    let first = day.with_day(1).unwrap();
+    let next = first.checked_add_months(Months::new(1)).unwrap();
+    (next - first).num_days() as i32
+}
+
+fn parse_weekday(value: &str) -> Option<Weekday> {
+    Some(match value {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => return None,
+    })
+}
+
+fn weekday_code(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+/// UNTIL may be a date, a local date-time or a UTC date-time. All of them
+/// are taken as floating times like the events themselves.
+fn parse_until(value: &str) -> Option<NaiveDateTime> {
+    let value = value.trim_end_matches('Z');
+    if let Ok(at) = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S") {
+        return Some(at);
+    }
+    NaiveDate::parse_from_str(value, "%Y%m%d")
+        .ok()
+        .map(|d| d.and_time(NaiveTime::from_hms_opt(23, 59, 59).unwrap()))
+}
+
+fn invalid(message: String) -> HGError {
+    HGError::Invalid(format!("invalid RRULE: {}", message))
+}
+
+fn parse_list<T>(value: &str, parse: impl Fn(&str) -> Option<T>) -> Result<Vec<T>, HGError> {
+    value
+        .split(',')
+        .map(|v| parse(v).ok_or_else(|| invalid(format!("bad value {}", v))))
+        .collect()
+}
+
+impl FromStr for RecurrenceRule {
+    type Err = HGError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        let value = value.strip_prefix("RRULE:").unwrap_or(value);
+
+        let mut frequency = None;
+        let mut rule = RecurrenceRule {
+            frequency: Frequency::Daily,
+            interval: 1,
+            count: None,
+            until: None,
+            by_day: Vec::new(),
+            by_month_day: Vec::new(),
+            by_month: Vec::new(),
+        };
+        for part in value.split(';').filter(|p| !p.is_empty()) {
+            let (key, val) = part
+                .split_once('=')
+                .ok_or_else(|| invalid(format!("expected KEY=VALUE, got {}", part)))?;
+            match key.to_ascii_uppercase().as_str() {
+                "FREQ" => {
+                    frequency = Some(match val.to_ascii_uppercase().as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        other => return Err(invalid(format!("unsupported FREQ {}", other))),
+                    })
+                }
+                "INTERVAL" => {
+                    rule.interval = val
+                        .parse()
+                        .ok()
+                        .filter(|i| *i > 0)
+                        .ok_or_else(|| invalid(format!("bad INTERVAL {}", val)))?
+                }
+                "COUNT" => {
+                    rule.count = Some(
+                        val.parse()
+                            .ok()
+                            .filter(|c| *c > 0)
+                            .ok_or_else(|| invalid(format!("bad COUNT {}", val)))?,
+                    )
+                }
+                "UNTIL" => {
+                    rule.until = Some(
+                        parse_until(val).ok_or_else(|| invalid(format!("bad UNTIL {}", val)))?,
+                    )
+                }
+                "BYDAY" => {
+                    rule.by_day = parse_list(&val.to_ascii_uppercase(), |v| {
+                        let split = v.len().checked_sub(2)?;
+                        let weekday = parse_weekday(v.get(split..)?)?;
+                        let ordinal = match &v[..split] {
+                            "" => None,
+                            n => Some(
+                                n.trim_start_matches('+')
+                                    .parse()
+                                    .ok()
+                                    .filter(|n: &i32| *n != 0 && n.abs() <= 5)?,
+                            ),
+                        };
+                        Some(DaySpec { ordinal, weekday })
+                    })?
+                }
+                "BYMONTHDAY" => {
+                    rule.by_month_day = parse_list(val, |v| {
+                        v.parse().ok().filter(|d: &i32| *d != 0 && d.abs() <= 31)
+                    })?
+                }
+                "BYMONTH" => {
+                    rule.by_month =
+                        parse_list(val, |v| v.parse().ok().filter(|m| (1..=12).contains(m)))?
+                }
+                // Weeks always start on Monday here
+                "WKST" => {}
+                other => return Err(invalid(format!("{} is not supported", other))),
+            }
+        }
+
+        rule.frequency = frequency.ok_or_else(|| invalid("FREQ is required".to_string()))?;
+        if rule.count.is_some() && rule.until.is_some() {
+            return Err(invalid("COUNT and UNTIL cannot both be set".to_string()));
+        }
+        if rule.frequency == Frequency::Weekly && rule.by_day.iter().any(|d| d.ordinal.is_some()) {
+            return Err(invalid(
+                "numbered BYDAY only makes sense for monthly and yearly rules".to_string(),
+            ));
+        }
+        Ok(rule)
+    }
+}
+
+impl fmt::Display for RecurrenceRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let frequency = match self.frequency {
+            Frequency::Daily => "DAILY",
+            Frequency::Weekly => "WEEKLY",
+            Frequency::Monthly => "MONTHLY",
+            Frequency::Yearly => "YEARLY",
+        };
+        write!(f, "FREQ={}", frequency)?;
+        if self.interval != 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+        if let Some(count) = self.count {
+            write!(f, ";COUNT={}", count)?;
+        }
+        if let Some(until) = self.until {
+            write!(f, ";UNTIL={}", until.format("%Y%m%dT%H%M%S"))?;
+        }
+        if !self.by_day.is_empty() {
+            let days: Vec<String> = self
+                .by_day
+                .iter()
+                .map(|d| match d.ordinal {
+                    Some(n) => format!("{}{}", n, weekday_code(d.weekday)),
+                    None => weekday_code(d.weekday).to_string(),
+                })
+                .collect();
+            write!(f, ";BYDAY={}", days.join(","))?;
+        }
+        if !self.by_month_day.is_empty() {
+            let days: Vec<String> = self.by_month_day.iter().map(|d| d.to_string()).collect();
+            write!(f, ";BYMONTHDAY={}", days.join(","))?;
+        }
+        if !self.by_month.is_empty() {
+            let months: Vec<String> = self.by_month.iter().map(|m| m.to_string()).collect();
+            write!(f, ";BYMONTH={}", months.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(value: &str) -> NaiveDateTime {
+        parse_event_time(value).unwrap()
+    }
+
+    fn rule(value: &str) -> RecurrenceRule {
+        value.parse().unwrap()
+    }
+
+    /// Every occurrence of `rrule` from `dtstart` through the end of 2030
+    fn all(rrule: &str, dtstart: &str) -> Vec<String> {
+        rule(rrule)
+            .occurrences(
+                at(dtstart),
+                Duration::hours(1),
+                at(dtstart),
+                at("2031-01-01"),
+            )
+            .into_iter()
+            .map(format_event_time)
+            .collect()
+    }
+
+    #[test]
+    fn weekly_by_day_with_count() {
+        assert_eq!(
+            all("FREQ=WEEKLY;BYDAY=TU,TH;COUNT=4", "2026-03-03T17:00"),
+            [
+                "2026-03-03T17:00:00",
+                "2026-03-05T17:00:00",
+                "2026-03-10T17:00:00",
+                "2026-03-12T17:00:00",
+            ]
+        );
+    }
+
+    #[test]
+    fn start_off_the_rule_counts_as_first_occurrence() {
+        // 2026-03-04 is a Wednesday
+        assert_eq!(
+            all("FREQ=WEEKLY;BYDAY=MO;COUNT=3", "2026-03-04T09:00"),
+            [
+                "2026-03-04T09:00:00",
+                "2026-03-09T09:00:00",
+                "2026-03-16T09:00:00",
+            ]
+        );
+        let monday = rule("FREQ=WEEKLY;BYDAY=MO");
+        assert!(monday.includes(at("2026-03-04T09:00"), at("2026-03-04T09:00")));
+        assert!(!monday.includes(at("2026-03-04T09:00"), at("2026-03-11T09:00")));
+        assert_eq!(
+            monday.count_before(at("2026-03-04T09:00"), at("2026-03-16T09:00")),
+            2
+        );
+    }
+
+    #[test]
+    fn until_before_start_has_no_occurrences() {
+        assert!(all("FREQ=DAILY;UNTIL=20260301", "2026-03-04T09:00").is_empty());
+    }
+
+    #[test]
+    fn until_is_inclusive() {
+        assert_eq!(
+            all(
+                "FREQ=DAILY;INTERVAL=2;UNTIL=20260308T090000",
+                "2026-03-04T09:00"
+            ),
+            [
+                "2026-03-04T09:00:00",
+                "2026-03-06T09:00:00",
+                "2026-03-08T09:00:00",
+            ]
+        );
+    }
+
+    #[test]
+    fn monthly_skips_months_without_the_day() {
+        assert_eq!(
+            all("FREQ=MONTHLY;COUNT=3", "2026-01-31T08:00"),
+            [
+                "2026-01-31T08:00:00",
+                "2026-03-31T08:00:00",
+                "2026-05-31T08:00:00",
+            ]
+        );
+    }
+
+    #[test]
+    fn monthly_last_friday() {
+        assert_eq!(
+            all("FREQ=MONTHLY;BYDAY=-1FR;COUNT=3", "2026-01-30T18:00"),
+            [
+                "2026-01-30T18:00:00",
+                "2026-02-27T18:00:00",
+                "2026-03-27T18:00:00",
+            ]
+        );
+    }
+
+    #[test]
+    fn yearly_by_month() {
+        assert_eq!(
+            all("FREQ=YEARLY;BYMONTH=6,12;COUNT=3", "2026-06-15T12:00"),
+            [
+                "2026-06-15T12:00:00",
+                "2026-12-15T12:00:00",
+                "2027-06-15T12:00:00",
+            ]
+        );
+    }
+
+    #[test]
+    fn occurrences_overlapping_the_window_start() {
+        let daily = rule("FREQ=DAILY");
+        let found = daily.occurrences(
+            at("2026-03-01T22:00"),
+            Duration::hours(3),
+            at("2026-03-03T00:00"),
+            at("2026-03-04T00:00"),
+        );
+        assert_eq!(found, [at("2026-03-02T22:00"), at("2026-03-03T22:00")]);
+    }
+
+    #[test]
+    fn huge_intervals_end_the_series() {
+        for rrule in [
+            "FREQ=DAILY;INTERVAL=2147483647",
+            "FREQ=WEEKLY;INTERVAL=2147483647",
+            "FREQ=MONTHLY;INTERVAL=2147483647",
+            "FREQ=YEARLY;INTERVAL=2147483647",
+            "FREQ=YEARLY;INTERVAL=4294967295",
+        ] {
+            assert_eq!(all(rrule, "2026-03-04T09:00"), ["2026-03-04T09:00:00"]);
+        }
+    }
+
+    #[test]
+    fn parse_and_display_round_trip() {
+        let value = "FREQ=MONTHLY;INTERVAL=2;COUNT=5;BYDAY=2SA,-1FR;BYMONTH=1,7";
+        assert_eq!(rule(value).to_string(), value);
+        assert_eq!(
+            rule("RRULE:freq=weekly;wkst=SU").frequency,
+            Frequency::Weekly
+        );
+    }
+
+    #[test]
+    fn parse_rejects_bad_rules() {
+        for value in [
+            "INTERVAL=2",
+            "FREQ=HOURLY",
+            "FREQ=DAILY;INTERVAL=0",
+            "FREQ=DAILY;COUNT=2;UNTIL=20260101",
+            "FREQ=WEEKLY;BYDAY=1MO",
+            "FREQ=MONTHLY;BYMONTHDAY=32",
+            "FREQ=DAILY;BYSETPOS=1",
+        ] {
+            assert!(value.parse::<RecurrenceRule>().is_err(), "{}", value);
+        }
+    }
+}
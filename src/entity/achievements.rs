@@ -0,0 +1,36 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+/// A badge a person has unlocked. Unique per person and badge.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "achievements")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    #[sea_orm(unique_key = "person_badge")]
+    pub person_id: i32,
+
+    /// Key of the badge under [achievements] in the configuration
+    #[sea_orm(unique_key = "person_badge")]
+    pub badge_key: String,
+    pub unlocked_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::entity::people::Entity",
+        from = "Column::PersonId",
+        to = "crate::entity::people::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    People,
+}
+
+impl Related<crate::entity::people::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::People.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
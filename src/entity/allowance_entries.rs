@@ -0,0 +1,56 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::None)")]
+#[serde(rename_all = "lowercase")]
+pub enum EntryKind {
+    /// Money earned from stars
+    #[sea_orm(string_value = "credit")]
+    Credit,
+    /// Money handed over to the person
+    #[sea_orm(string_value = "payout")]
+    Payout,
+    /// Manual correction by a parent
+    #[sea_orm(string_value = "adjustment")]
+    Adjustment,
+}
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "allowance_entries")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub person_id: i32,
+    pub kind: EntryKind,
+
+    /// Signed amount in cents, payouts are negative
+    pub amount_cents: i64,
+
+    /// Chart the stars were earned on, kept after the chart is deleted
+    pub star_chart_id: Option<i32>,
+    pub stars: Option<i32>,
+    pub note: Option<String>,
+    pub actor: Option<String>,
+    pub created_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::entity::people::Entity",
+        from = "Column::PersonId",
+        to = "crate::entity::people::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    People,
+}
+
+impl Related<crate::entity::people::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::People.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,60 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// open -> claimed -> done -> verified, each step taken at most once
+#[derive(Clone, Debug, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::None)")]
+#[serde(rename_all = "lowercase")]
+pub enum BountyStatus {
+    #[sea_orm(string_value = "open")]
+    Open,
+    #[sea_orm(string_value = "claimed")]
+    Claimed,
+    #[sea_orm(string_value = "done")]
+    Done,
+    #[sea_orm(string_value = "verified")]
+    Verified,
+}
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "bounties")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub title: String,
+    pub description: Option<String>,
+
+    /// Stars credited to the claimant once a parent verifies the work
+    pub reward: i32,
+    pub status: BountyStatus,
+    pub posted_by: String,
+    pub created_at: DateTimeUtc,
+
+    pub claimed_by: Option<i32>,
+    /// Chart of the claimant that receives the reward
+    pub star_chart_id: Option<i32>,
+    pub claimed_at: Option<DateTimeUtc>,
+    pub completed_at: Option<DateTimeUtc>,
+    pub verified_by: Option<String>,
+    pub verified_at: Option<DateTimeUtc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::entity::people::Entity",
+        from = "Column::ClaimedBy",
+        to = "crate::entity::people::Column::Id",
+        on_update = "Cascade",
+        on_delete = "SetNull"
+    )]
+    People,
+}
+
+impl Related<crate::entity::people::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::People.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
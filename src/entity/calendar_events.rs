@@ -1,6 +1,7 @@
 use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
 #[sea_orm(table_name = "calendar_events")]
 pub struct Model {
     #[sea_orm(primary_key)]
@@ -9,6 +10,18 @@ pub struct Model {
     pub title: String,
     pub start_time: String,
     pub end_time: String,
+
+    /// RFC 5545 RRULE for a repeating series
+    pub rrule: Option<String>,
+    /// Comma separated start times of occurrences left out of the series
+    pub exdates: Option<String>,
+    /// Set when this event replaces one occurrence of another series
+    pub parent_event_id: Option<i32>,
+    /// Original start time of the occurrence it replaces
+    pub recurrence_id: Option<String>,
+    /// UID the event had in the calendar file it was imported from
+    pub import_uid: Option<String>,
+    pub updated_at: Option<DateTimeUtc>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
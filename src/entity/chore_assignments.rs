@@ -0,0 +1,49 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "chore_assignments")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub chore_id: i32,
+    pub person_id: i32,
+
+    /// Local date the chore is due
+    pub due_on: Date,
+    pub completed_at: Option<DateTimeUtc>,
+    pub completed_by: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::entity::chores::Entity",
+        from = "Column::ChoreId",
+        to = "crate::entity::chores::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Chores,
+    #[sea_orm(
+        belongs_to = "crate::entity::people::Entity",
+        from = "Column::PersonId",
+        to = "crate::entity::people::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    People,
+}
+
+impl Related<crate::entity::chores::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Chores.def()
+    }
+}
+
+impl Related<crate::entity::people::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::People.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
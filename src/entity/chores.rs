@@ -0,0 +1,197 @@
+use chrono::NaiveDate;
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// How a chore moves between its assignees from one day to the next
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::None)")]
+#[serde(rename_all = "snake_case")]
+pub enum Rotation {
+    /// A different person every day
+    #[sea_orm(string_value = "round_robin")]
+    RoundRobin,
+    /// The same person for a whole week, starting on Monday
+    #[sea_orm(string_value = "weekly")]
+    Weekly,
+    /// Older people take proportionally more days
+    #[sea_orm(string_value = "weighted")]
+    Weighted,
+}
+
+impl Rotation {
+    /// Who does the chore on `day`, given the assignees in rotation order with
+    /// their weights. Depends only on the dates so re-running is stable.
+    pub fn assignee(
+        &self,
+        assignees: &[(i32, u32)],
+        starts_on: NaiveDate,
+        day: NaiveDate,
+    ) -> Option<i32> {
+        if assignees.is_empty() || day < starts_on {
+            return None;
+        }
+        let days = (day - starts_on).num_days() as usize;
+        let turn = match self {
+            Rotation::RoundRobin => days % assignees.len(),
+            Rotation::Weekly => {
+                let first_monday = starts_on.week(chrono::Weekday::Mon).first_day();
+                let weeks = (day - first_monday).num_days() as usize / 7;
+                weeks % assignees.len()
+            }
+            Rotation::Weighted => weighted_turn(assignees, days),
+        };
+        Some(assignees[turn].0)
+    }
+}
+
+/// Smooth weighted round robin, so heavier assignees get more turns without
+/// doing them all back to back
+fn weighted_turn(assignees: &[(i32, u32)], days: usize) -> usize {
+    let weights: Vec<i64> = assignees
+        .iter()
+        .map(|(_, w)| i64::from((*w).max(1)))
+        .collect();
+    let total: i64 = weights.iter().sum();
+    let mut current = vec![0i64; weights.len()];
+    let mut picked = 0;
+    for _ in 0..=(days % total as usize) {
+        for (c, w) in current.iter_mut().zip(&weights) {
+            *c += w;
+        }
+        picked = (0..current.len())
+            .max_by_key(|i| (current[*i], -(*i as i64)))
+            .unwrap_or_default();
+        current[picked] -= total;
+    }
+    picked
+}
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "chores")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub name: String,
+    pub description: Option<String>,
+    pub rotation: Rotation,
+
+    /// Comma separated people ids, in rotation order
+    pub assignee_ids: String,
+    pub starts_on: Date,
+    pub created_at: DateTimeUtc,
+}
+
+impl Model {
+    pub fn assignees(&self) -> Vec<i32> {
+        self.assignee_ids
+            .split(',')
+            .filter_map(|id| id.trim().parse().ok())
+            .collect()
+    }
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        has_many = "crate::entity::chore_assignments::Entity",
+        from = "Column::Id",
+        to = "crate::entity::chore_assignments::Column::ChoreId"
+    )]
+    ChoreAssignments,
+}
+
+impl Related<crate::entity::chore_assignments::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ChoreAssignments.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    const EVEN: [(i32, u32); 3] = [(1, 1), (2, 1), (3, 1)];
+
+    #[test]
+    fn nobody_before_the_start_or_without_assignees() {
+        let start = date(2026, 3, 2);
+        assert_eq!(
+            Rotation::RoundRobin.assignee(&EVEN, start, date(2026, 3, 1)),
+            None
+        );
+        assert_eq!(Rotation::RoundRobin.assignee(&[], start, start), None);
+    }
+
+    #[test]
+    fn round_robin_moves_on_every_day() {
+        let start = date(2026, 3, 2);
+        let order: Vec<_> = (0..5)
+            .filter_map(|d| {
+                Rotation::RoundRobin.assignee(&EVEN, start, start + chrono::Duration::days(d))
+            })
+            .collect();
+        assert_eq!(order, vec![1, 2, 3, 1, 2]);
+    }
+
+    #[test]
+    fn weekly_changes_hands_on_monday() {
+        // Starts on a Wednesday, so the first week is a short one
+        let start = date(2026, 3, 4);
+        let pair = [(1, 1), (2, 1)];
+        assert_eq!(Rotation::Weekly.assignee(&pair, start, start), Some(1));
+        assert_eq!(
+            Rotation::Weekly.assignee(&pair, start, date(2026, 3, 8)),
+            Some(1)
+        );
+        assert_eq!(
+            Rotation::Weekly.assignee(&pair, start, date(2026, 3, 9)),
+            Some(2)
+        );
+        assert_eq!(
+            Rotation::Weekly.assignee(&pair, start, date(2026, 3, 16)),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn weighted_interleaves_the_heavier_assignee() {
+        let turns: Vec<_> = (0..6)
+            .map(|d| weighted_turn(&[(1, 2), (2, 1)], d))
+            .collect();
+        assert_eq!(turns, vec![0, 1, 0, 0, 1, 0]);
+    }
+
+    #[test]
+    fn weighted_shares_days_by_weight() {
+        let assignees = [(1, 3), (2, 1), (3, 2)];
+        let mut counts = [0; 3];
+        for d in 0..12 {
+            counts[weighted_turn(&assignees, d)] += 1;
+        }
+        assert_eq!(counts, [6, 2, 4]);
+    }
+
+    #[test]
+    fn weighted_treats_a_zero_weight_as_one() {
+        let turns: Vec<_> = (0..4)
+            .map(|d| weighted_turn(&[(1, 0), (2, 0)], d))
+            .collect();
+        assert_eq!(turns, vec![0, 1, 0, 1]);
+    }
+
+    #[test]
+    fn weighted_assignee_follows_the_turns() {
+        let start = date(2026, 3, 2);
+        let assignees = [(7, 2), (9, 1)];
+        assert_eq!(
+            Rotation::Weighted.assignee(&assignees, start, date(2026, 3, 3)),
+            Some(9)
+        );
+    }
+}
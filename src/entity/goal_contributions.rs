@@ -0,0 +1,54 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "goal_contributions")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub goal_id: i32,
+    pub person_id: i32,
+
+    /// Stars actually added after clamping to the goal's total
+    pub delta: i32,
+
+    /// Chart the stars came out of, or went back to
+    pub star_chart_id: Option<i32>,
+    pub note: Option<String>,
+    pub created_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::entity::goals::Entity",
+        from = "Column::GoalId",
+        to = "crate::entity::goals::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Goals,
+
+    #[sea_orm(
+        belongs_to = "crate::entity::people::Entity",
+        from = "Column::PersonId",
+        to = "crate::entity::people::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    People,
+}
+
+impl Related<crate::entity::goals::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Goals.def()
+    }
+}
+
+impl Related<crate::entity::people::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::People.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
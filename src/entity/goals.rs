@@ -0,0 +1,39 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::entity::star_charts::ChartStatus;
+
+/// A household chart that belongs to nobody in particular; anyone can add
+/// stars to it and each addition is kept in goal_contributions
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "goals")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub name: String,
+    pub description: Option<String>,
+    pub star_count: i32,
+    pub star_total: i32,
+    pub color: Option<String>,
+    pub status: ChartStatus,
+    pub created_at: DateTimeUtc,
+    pub completed_at: Option<DateTimeUtc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        has_many = "crate::entity::goal_contributions::Entity",
+        from = "Column::Id",
+        to = "crate::entity::goal_contributions::Column::GoalId"
+    )]
+    GoalContributions,
+}
+
+impl Related<crate::entity::goal_contributions::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::GoalContributions.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -1,6 +1,9 @@
 use anyhow::Context;
 
-use sea_orm::{ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, Set};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait, QueryFilter,
+    Set, sea_query::Expr,
+};
 
 pub async fn children_of(
     db: &DatabaseConnection,
@@ -65,6 +68,75 @@ pub async fn add_parent_child(
     Ok(())
 }
 
+/// XP lives on the person so it outlasts the chart it was earned on
+pub async fn add_xp<C: ConnectionTrait>(db: &C, person_id: i32, stars: i32) -> anyhow::Result<()> {
+    use crate::entity::people;
+    use sea_orm::sea_query::ExprTrait;
+
+    if stars <= 0 {
+        return Ok(());
+    }
+    people::Entity::update_many()
+        .col_expr(people::Column::Xp, Expr::col(people::Column::Xp).add(stars))
+        .filter(people::Column::Id.eq(person_id))
+        .exec(db)
+        .await
+        .context("failed to add xp")?;
+    Ok(())
+}
+
+/// Take back XP for stars that turned out not to be earned, never going
+/// below zero
+pub async fn remove_xp<C: ConnectionTrait>(
+    db: &C,
+    person_id: i32,
+    stars: i32,
+) -> anyhow::Result<()> {
+    use crate::entity::people;
+
+    if stars <= 0 {
+        return Ok(());
+    }
+    people::Entity::update_many()
+        .col_expr(
+            people::Column::Xp,
+            Expr::cust_with_values("MAX(\"xp\" - ?, 0)", [stars]),
+        )
+        .filter(people::Column::Id.eq(person_id))
+        .exec(db)
+        .await
+        .context("failed to remove xp")?;
+    Ok(())
+}
+
+pub async fn record_star_event<C: ConnectionTrait>(
+    db: &C,
+    star_chart_id: i32,
+    delta: i32,
+    kind: crate::entity::star_events::StarEventKind,
+    note: Option<String>,
+    actor: Option<String>,
+) -> anyhow::Result<()> {
+    use crate::entity::star_events;
+
+    let event = star_events::ActiveModel {
+        star_chart_id: Set(star_chart_id),
+        delta: Set(delta),
+        kind: Set(kind),
+        created_at: Set(chrono::Utc::now()),
+        note: Set(note),
+        actor: Set(actor),
+        ..Default::default()
+    };
+
+    event
+        .insert(db)
+        .await
+        .context("failed to insert star event")?;
+    Ok(())
+}
+
+#[allow(dead_code)]
 pub async fn create_star_chart(db: &DatabaseConnection, person_id: i32) -> anyhow::Result<()> {
     use crate::entity::star_charts;
 
@@ -0,0 +1,38 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+/// A level a person holds and when they reached it. Unique per person and
+/// level, and removed again if their XP drops back below it.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "level_ups")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    #[sea_orm(unique_key = "person_level")]
+    pub person_id: i32,
+    #[sea_orm(unique_key = "person_level")]
+    pub level: i32,
+
+    /// Lifetime XP when the level was reached
+    pub xp: i32,
+    pub reached_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::entity::people::Entity",
+        from = "Column::PersonId",
+        to = "crate::entity::people::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    People,
+}
+
+impl Related<crate::entity::people::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::People.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
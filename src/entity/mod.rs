@@ -1,5 +1,24 @@
+pub(crate) mod achievements;
+pub(crate) mod allowance_entries;
+pub(crate) mod bonus_windows;
+pub(crate) mod bounties;
+pub(crate) mod calendar_changes;
 pub(crate) mod calendar_events;
+pub(crate) mod chore_assignments;
+pub(crate) mod chores;
+pub(crate) mod goal_contributions;
+pub(crate) mod goals;
 pub(crate) mod helpers;
+pub(crate) mod level_ups;
 pub(crate) mod people;
 pub(crate) mod person_parent;
+pub(crate) mod reward_redemptions;
+pub(crate) mod rewards;
+pub(crate) mod schema_migrations;
+pub(crate) mod star_approvals;
+pub(crate) mod star_chart_periods;
+pub(crate) mod star_chart_prerequisites;
 pub(crate) mod star_charts;
+pub(crate) mod star_events;
+pub(crate) mod star_transfers;
+pub(crate) mod undo_entries;
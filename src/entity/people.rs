@@ -8,6 +8,16 @@ pub struct Model {
     pub first_name: String,
     pub last_name: String,
     pub calendar_color: Option<String>,
+
+    /// Pocket money per star in cents, charts can override it
+    pub cents_per_star: Option<i32>,
+
+    /// One per star ever earned, kept when charts are deleted
+    #[sea_orm(default_value = 0)]
+    pub xp: i32,
+
+    /// Hidden from lists once set, purged after the retention period
+    pub archived_at: Option<DateTimeUtc>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -26,6 +36,27 @@ pub enum Relation {
     )]
     StarCharts,
 
+    #[sea_orm(
+        has_many = "crate::entity::achievements::Entity",
+        from = "Column::Id",
+        to = "crate::entity::achievements::Column::PersonId"
+    )]
+    Achievements,
+
+    #[sea_orm(
+        has_many = "crate::entity::allowance_entries::Entity",
+        from = "Column::Id",
+        to = "crate::entity::allowance_entries::Column::PersonId"
+    )]
+    AllowanceEntries,
+
+    #[sea_orm(
+        has_many = "crate::entity::level_ups::Entity",
+        from = "Column::Id",
+        to = "crate::entity::level_ups::Column::PersonId"
+    )]
+    LevelUps,
+
     // person_parent relations are represented on the person_parent entity
 }
 
@@ -0,0 +1,55 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::None)")]
+#[serde(rename_all = "lowercase")]
+pub enum ApprovalStatus {
+    #[sea_orm(string_value = "pending")]
+    Pending,
+    #[sea_orm(string_value = "approved")]
+    Approved,
+    #[sea_orm(string_value = "rejected")]
+    Rejected,
+}
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "star_approvals")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub star_chart_id: i32,
+
+    // Requested change, applied to star_count only once approved
+    pub delta: i32,
+    pub note: Option<String>,
+    /// Who asked for the stars
+    pub actor: Option<String>,
+    pub status: ApprovalStatus,
+    pub created_at: DateTimeUtc,
+
+    /// The parent who approved or rejected the request
+    pub resolved_by: Option<String>,
+    pub resolved_at: Option<DateTimeUtc>,
+    pub reason: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::entity::star_charts::Entity",
+        from = "Column::StarChartId",
+        to = "crate::entity::star_charts::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    StarCharts,
+}
+
+impl Related<crate::entity::star_charts::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::StarCharts.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
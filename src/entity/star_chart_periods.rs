@@ -0,0 +1,36 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "star_chart_periods")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub star_chart_id: i32,
+    pub period_start: DateTimeUtc,
+    pub period_end: DateTimeUtc,
+
+    // Final count when the period closed
+    pub star_count: i32,
+    // Goal that applied during the period
+    pub star_total: i32,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::entity::star_charts::Entity",
+        from = "Column::StarChartId",
+        to = "crate::entity::star_charts::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    StarCharts,
+}
+
+impl Related<crate::entity::star_charts::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::StarCharts.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,34 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// A quest link: the chart stays locked until the required chart is completed
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "star_chart_prerequisites")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub star_chart_id: i32,
+    pub requires_id: i32,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::entity::star_charts::Entity",
+        from = "Column::StarChartId",
+        to = "crate::entity::star_charts::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    StarCharts,
+    #[sea_orm(
+        belongs_to = "crate::entity::star_charts::Entity",
+        from = "Column::RequiresId",
+        to = "crate::entity::star_charts::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    RequiredStarCharts,
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -1,5 +1,169 @@
+use chrono::{DateTime, Datelike, Local, NaiveDate, TimeZone, Utc};
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
+
+/// How often a chart closes its period and starts again from zero
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::None)")]
+#[serde(rename_all = "lowercase")]
+pub enum Recurrence {
+    #[sea_orm(string_value = "daily")]
+    Daily,
+    #[sea_orm(string_value = "weekly")]
+    Weekly,
+    #[sea_orm(string_value = "monthly")]
+    Monthly,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::None)")]
+#[serde(rename_all = "lowercase")]
+pub enum ChartStatus {
+    #[sea_orm(string_value = "active")]
+    Active,
+    #[sea_orm(string_value = "completed")]
+    Completed,
+    /// Waiting on the charts it requires to be completed first
+    #[sea_orm(string_value = "locked")]
+    Locked,
+    /// Ran out of time under the expire deadline policy
+    #[sea_orm(string_value = "expired")]
+    Expired,
+    /// Ran out of time under the fail deadline policy
+    #[sea_orm(string_value = "failed")]
+    Failed,
+}
+
+impl ChartStatus {
+    /// Only active and completed charts can gain or lose stars
+    pub fn takes_stars(&self) -> bool {
+        matches!(self, ChartStatus::Active | ChartStatus::Completed)
+    }
+}
+
+/// What a chart turns into when its due date passes before it is completed
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::None)")]
+#[serde(rename_all = "lowercase")]
+pub enum DeadlinePolicy {
+    /// Quietly closes, nothing lost
+    #[sea_orm(string_value = "expire")]
+    Expire,
+    /// Counts as a missed goal
+    #[sea_orm(string_value = "fail")]
+    Fail,
+}
+
+impl DeadlinePolicy {
+    pub fn status(&self) -> ChartStatus {
+        match self {
+            DeadlinePolicy::Expire => ChartStatus::Expired,
+            DeadlinePolicy::Fail => ChartStatus::Failed,
+        }
+    }
+}
+
+/// What happens to stars added once a chart has reached its goal
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::None)")]
+#[serde(rename_all = "lowercase")]
+pub enum OverflowPolicy {
+    /// Keep the count between zero and star_total
+    #[sea_orm(string_value = "clamp")]
+    Clamp,
+    /// Let the count run past star_total
+    #[sea_orm(string_value = "allow")]
+    Allow,
+}
+
+impl OverflowPolicy {
+    /// Bound a proposed count. Counts never go below zero under any policy.
+    pub fn bound(&self, star_count: i32, star_total: i32) -> i32 {
+        match self {
+            OverflowPolicy::Clamp => star_count.clamp(0, star_total.max(0)),
+            OverflowPolicy::Allow => star_count.max(0),
+        }
+    }
+
+    /// Count after adding `delta`. Only bounds in the direction of the
+    /// change, so adding stars to a chart already past its total (from an
+    /// older policy, say) never takes any away, and vice versa.
+    pub fn add_stars(&self, star_count: i32, delta: i32, star_total: i32) -> i32 {
+        let proposed = star_count.saturating_add(delta);
+        if delta >= 0 {
+            self.bound(proposed, star_total).max(star_count)
+        } else {
+            proposed.max(0).min(star_count)
+        }
+    }
+}
+
+impl Recurrence {
+    /// Start of the period containing `at`, in the household's local time.
+    /// Days start at midnight, weeks on Monday and months on the 1st.
+    pub fn period_start(&self, at: DateTime<Utc>) -> DateTime<Utc> {
+        let today = at.with_timezone(&Local).date_naive();
+        let first_day = match self {
+            Recurrence::Daily => today,
+            Recurrence::Weekly => {
+                today - chrono::Duration::days(today.weekday().num_days_from_monday() as i64)
+            }
+            Recurrence::Monthly => {
+                NaiveDate::from_ymd_opt(today.year(), today.month(), 1).unwrap_or(today)
+            }
+        };
+        let midnight = first_day.and_hms_opt(0, 0, 0).unwrap();
+        Local
+            .from_local_datetime(&midnight)
+            .earliest()
+            .map(|d| d.with_timezone(&Utc))
+            .unwrap_or_else(|| midnight.and_utc())
+    }
+
+    /// Start of the period after the one beginning at `start`
+    pub fn next_period_start(&self, start: DateTime<Utc>) -> DateTime<Utc> {
+        // Step far enough to land inside the next period whatever DST does
+        let step = match self {
+            Recurrence::Daily => chrono::Duration::hours(26),
+            Recurrence::Weekly => chrono::Duration::days(7) + chrono::Duration::hours(2),
+            Recurrence::Monthly => chrono::Duration::days(32),
+        };
+        self.period_start(start + step)
+    }
+
+    /// Current and best run of consecutive periods containing at least one of
+    /// `times`. The current run stays alive until a whole period is missed, so
+    /// a streak is not lost just because today has no star yet.
+    pub fn streaks(&self, times: &[DateTime<Utc>], now: DateTime<Utc>) -> (i32, i32) {
+        let mut periods: Vec<DateTime<Utc>> = times.iter().map(|t| self.period_start(*t)).collect();
+        periods.sort();
+        periods.dedup();
+
+        let mut best = 0;
+        let mut run = 0;
+        let mut previous: Option<DateTime<Utc>> = None;
+        for p in &periods {
+            run = match previous {
+                Some(prev) if self.next_period_start(prev) == *p => run + 1,
+                _ => 1,
+            };
+            best = best.max(run);
+            previous = Some(*p);
+        }
+
+        let current_period = self.period_start(now);
+        let current = match previous {
+            Some(last)
+                if last == current_period || self.next_period_start(last) == current_period =>
+            {
+                run
+            }
+            _ => 0,
+        };
+        (current, best)
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
 #[sea_orm(table_name = "star_charts")]
 pub struct Model {
@@ -21,6 +185,47 @@ pub struct Model {
     pub created_at: DateTimeUtc,
     pub updated_at: DateTimeUtc,
     pub color: Option<String>,
+
+    /// None for charts that never reset
+    pub recurrence: Option<Recurrence>,
+    pub period_started_at: Option<DateTimeUtc>,
+
+    /// Increments from anyone but a parent wait in the approval queue
+    #[sea_orm(default_value = false)]
+    pub requires_approval: bool,
+
+    /// Override the configured cooldown between increments, in seconds
+    pub cooldown_seconds: Option<i32>,
+    /// Override the configured maximum stars per day
+    pub daily_cap: Option<i32>,
+
+    #[sea_orm(default_value = "active")]
+    pub status: ChartStatus,
+    /// When star_count last reached star_total
+    pub completed_at: Option<DateTimeUtc>,
+    #[sea_orm(default_value = "clamp")]
+    pub overflow_policy: OverflowPolicy,
+
+    /// Bumped on every write, used as the chart's ETag
+    #[sea_orm(default_value = 1)]
+    pub version: i32,
+
+    /// Key of the [templates] entry this chart was created from
+    pub template_key: Option<String>,
+
+    /// Pocket money per star in cents, overrides the person's rate
+    pub cents_per_star: Option<i32>,
+
+    /// Stars can't be added before this
+    pub starts_at: Option<DateTimeUtc>,
+    /// Charts still short of their total after this are closed
+    pub due_at: Option<DateTimeUtc>,
+    #[sea_orm(default_value = "expire")]
+    pub deadline_policy: DeadlinePolicy,
+
+    /// Soft delete; unlike the archived status this hides the chart
+    /// everywhere and it is purged after the retention period
+    pub archived_at: Option<DateTimeUtc>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -33,6 +238,27 @@ pub enum Relation {
         on_delete = "Cascade"
     )]
     People,
+
+    #[sea_orm(
+        has_many = "crate::entity::star_events::Entity",
+        from = "Column::Id",
+        to = "crate::entity::star_events::Column::StarChartId"
+    )]
+    StarEvents,
+
+    #[sea_orm(
+        has_many = "crate::entity::star_chart_periods::Entity",
+        from = "Column::Id",
+        to = "crate::entity::star_chart_periods::Column::StarChartId"
+    )]
+    StarChartPeriods,
+
+    #[sea_orm(
+        has_many = "crate::entity::star_approvals::Entity",
+        from = "Column::Id",
+        to = "crate::entity::star_approvals::Column::StarChartId"
+    )]
+    StarApprovals,
 }
 
 impl Related<crate::entity::people::Entity> for Entity {
@@ -42,3 +268,111 @@ impl Related<crate::entity::people::Entity> for Entity {
 }
 
 impl ActiveModelBehavior for ActiveModel {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Noon on the given local day, well away from any period boundary
+    fn noon(year: i32, month: u32, day: u32) -> DateTime<Utc> {
+        Local
+            .with_ymd_and_hms(year, month, day, 12, 0, 0)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn clamp_keeps_the_count_between_zero_and_the_total() {
+        assert_eq!(OverflowPolicy::Clamp.bound(12, 10), 10);
+        assert_eq!(OverflowPolicy::Clamp.bound(-3, 10), 0);
+        assert_eq!(OverflowPolicy::Clamp.bound(4, 10), 4);
+        assert_eq!(OverflowPolicy::Clamp.bound(4, -1), 0);
+    }
+
+    #[test]
+    fn allow_only_stops_at_zero() {
+        assert_eq!(OverflowPolicy::Allow.bound(12, 10), 12);
+        assert_eq!(OverflowPolicy::Allow.bound(-3, 10), 0);
+    }
+
+    #[test]
+    fn adding_stars_stops_at_the_total_when_clamped() {
+        assert_eq!(OverflowPolicy::Clamp.add_stars(8, 5, 10), 10);
+        assert_eq!(OverflowPolicy::Allow.add_stars(8, 5, 10), 13);
+        assert_eq!(OverflowPolicy::Clamp.add_stars(3, -5, 10), 0);
+    }
+
+    #[test]
+    fn adding_stars_never_takes_any_away() {
+        // Past the total from when overflow was allowed
+        assert_eq!(OverflowPolicy::Clamp.add_stars(13, 1, 10), 13);
+        assert_eq!(OverflowPolicy::Clamp.add_stars(13, 0, 10), 13);
+    }
+
+    #[test]
+    fn taking_stars_away_never_adds_any() {
+        assert_eq!(OverflowPolicy::Clamp.add_stars(13, -1, 10), 12);
+        assert_eq!(OverflowPolicy::Clamp.add_stars(-2, -1, 10), -2);
+    }
+
+    #[test]
+    fn adding_stars_saturates() {
+        assert_eq!(OverflowPolicy::Allow.add_stars(i32::MAX, 1, 10), i32::MAX);
+        assert_eq!(OverflowPolicy::Allow.add_stars(1, i32::MIN, 10), 0);
+    }
+
+    #[test]
+    fn no_stars_means_no_streak() {
+        assert_eq!(Recurrence::Daily.streaks(&[], noon(2026, 3, 10)), (0, 0));
+    }
+
+    #[test]
+    fn several_stars_in_one_day_count_once() {
+        let times = [noon(2026, 3, 9), noon(2026, 3, 9), noon(2026, 3, 10)];
+        assert_eq!(Recurrence::Daily.streaks(&times, noon(2026, 3, 10)), (2, 2));
+    }
+
+    #[test]
+    fn current_streak_survives_until_a_whole_day_is_missed() {
+        let times = [noon(2026, 3, 8), noon(2026, 3, 9)];
+        assert_eq!(Recurrence::Daily.streaks(&times, noon(2026, 3, 10)), (2, 2));
+        assert_eq!(Recurrence::Daily.streaks(&times, noon(2026, 3, 11)), (0, 2));
+    }
+
+    #[test]
+    fn best_streak_outlives_a_gap() {
+        let times = [
+            noon(2026, 3, 1),
+            noon(2026, 3, 2),
+            noon(2026, 3, 3),
+            noon(2026, 3, 5),
+            noon(2026, 3, 6),
+        ];
+        assert_eq!(Recurrence::Daily.streaks(&times, noon(2026, 3, 6)), (2, 3));
+    }
+
+    #[test]
+    fn order_of_times_does_not_matter() {
+        let times = [noon(2026, 3, 3), noon(2026, 3, 1), noon(2026, 3, 2)];
+        assert_eq!(Recurrence::Daily.streaks(&times, noon(2026, 3, 3)), (3, 3));
+    }
+
+    #[test]
+    fn weekly_streaks_count_weeks() {
+        // The weeks of 2 and 9 March, then a missed week before Sunday 29 March
+        let times = [noon(2026, 3, 2), noon(2026, 3, 13), noon(2026, 3, 29)];
+        assert_eq!(
+            Recurrence::Weekly.streaks(&times, noon(2026, 3, 30)),
+            (1, 2)
+        );
+    }
+
+    #[test]
+    fn monthly_streaks_run_across_the_year_end() {
+        let times = [noon(2025, 11, 30), noon(2025, 12, 1), noon(2026, 1, 31)];
+        assert_eq!(
+            Recurrence::Monthly.streaks(&times, noon(2026, 2, 27)),
+            (3, 3)
+        );
+    }
+}
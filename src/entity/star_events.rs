@@ -0,0 +1,81 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// What moved the stars. Only increments count towards cooldowns, daily caps
+/// and streaks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::None)")]
+#[serde(rename_all = "lowercase")]
+pub enum StarEventKind {
+    /// Earned through the increment endpoint, directly or once approved
+    #[sea_orm(string_value = "increment")]
+    Increment,
+    /// The count edited by hand, or by a template changing its goal
+    #[sea_orm(string_value = "adjustment")]
+    Adjustment,
+    /// Cleared when a recurring chart started a new period
+    #[sea_orm(string_value = "reset")]
+    Reset,
+    /// Spent on a reward
+    #[sea_orm(string_value = "redemption")]
+    Redemption,
+    /// Given to or received from someone else
+    #[sea_orm(string_value = "gift")]
+    Gift,
+    /// Paid for a verified bounty
+    #[sea_orm(string_value = "bounty")]
+    Bounty,
+    /// Put into or taken back out of a household goal
+    #[sea_orm(string_value = "goal")]
+    Goal,
+    /// An earlier change undone or redone
+    #[sea_orm(string_value = "undo")]
+    Undo,
+}
+
+impl StarEventKind {
+    /// Stars that only moved between charts and goals were earned once
+    /// already, so they leave XP and allowance alone
+    pub fn earned(&self) -> bool {
+        !matches!(self, StarEventKind::Gift | StarEventKind::Goal)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "star_events")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub star_chart_id: i32,
+
+    // Change applied to star_count, negative when stars are removed
+    pub delta: i32,
+    pub created_at: DateTimeUtc,
+
+    /// Free text reason, e.g. "tidied without being asked"
+    pub note: Option<String>,
+    /// Who made the change, usually a first name
+    pub actor: Option<String>,
+    #[sea_orm(default_value = "increment")]
+    pub kind: StarEventKind,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::entity::star_charts::Entity",
+        from = "Column::StarChartId",
+        to = "crate::entity::star_charts::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    StarCharts,
+}
+
+impl Related<crate::entity::star_charts::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::StarCharts.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,61 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::None)")]
+#[serde(rename_all = "lowercase")]
+pub enum TransferStatus {
+    /// Waiting for a parent, no stars have moved yet
+    #[sea_orm(string_value = "pending")]
+    Pending,
+    #[sea_orm(string_value = "completed")]
+    Completed,
+    #[sea_orm(string_value = "rejected")]
+    Rejected,
+}
+
+/// Stars gifted from one person's chart to another's
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "star_transfers")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub from_star_chart_id: i32,
+    pub to_star_chart_id: i32,
+    pub from_person_id: i32,
+    pub to_person_id: i32,
+    pub amount: i32,
+    pub note: Option<String>,
+    /// Who asked for the transfer
+    pub actor: Option<String>,
+    pub status: TransferStatus,
+    pub created_at: DateTimeUtc,
+
+    /// The parent who approved or rejected it
+    pub resolved_by: Option<String>,
+    pub resolved_at: Option<DateTimeUtc>,
+    pub reason: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::entity::star_charts::Entity",
+        from = "Column::FromStarChartId",
+        to = "crate::entity::star_charts::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    FromStarChart,
+
+    #[sea_orm(
+        belongs_to = "crate::entity::star_charts::Entity",
+        from = "Column::ToStarChartId",
+        to = "crate::entity::star_charts::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    ToStarChart,
+}
+
+impl ActiveModelBehavior for ActiveModel {}
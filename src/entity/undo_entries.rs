@@ -0,0 +1,48 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::None)")]
+#[serde(rename_all = "snake_case")]
+pub enum UndoAction {
+    #[sea_orm(string_value = "increment")]
+    Increment,
+    #[sea_orm(string_value = "update_chart")]
+    UpdateChart,
+    #[sea_orm(string_value = "create_event")]
+    CreateEvent,
+    #[sea_orm(string_value = "update_event")]
+    UpdateEvent,
+    #[sea_orm(string_value = "delete_event")]
+    DeleteEvent,
+}
+
+/// One step in a client's undo history. Undone steps stay behind as the
+/// redo stack until the client makes a new change.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "undo_entries")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub client_id: String,
+    pub action: UndoAction,
+
+    /// Star chart or calendar event the action touched
+    pub target_id: i32,
+    /// Stars actually applied by an increment
+    pub delta: Option<i32>,
+    /// JSON snapshots of the row before and after the action. Calendar
+    /// actions keep every event they touched, a series with its replaced
+    /// occurrences.
+    pub before: Option<String>,
+    pub after: Option<String>,
+
+    #[sea_orm(default_value = false)]
+    pub undone: bool,
+    pub created_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -1,24 +1,44 @@
 use crate::data::configuration::Configuration;
 use crate::data::dbconnector::HGDBConnection;
 use crate::data::dbconnector::SQLConnector;
+use crate::data::errors::HGError;
+use crate::entity::star_transfers::TransferStatus;
 use crate::server::types::CreatePersonResponse;
 use crate::server::types::GetPersonResponse;
 pub(crate) mod types;
-use axum::extract::Path;
+use axum::extract::{Path, Query};
 use axum::routing::patch;
 use axum::{
     Json, Router,
     extract::State,
-    http::StatusCode,
-    response::IntoResponse,
-    routing::{delete, get, post},
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::{delete, get, post, put},
 };
-use log::debug;
+use log::{debug, error, info};
 use std::sync::Arc;
+use std::time::Duration;
 use types::{
-    CalendarEventResponse, CalendarPersonResponse, CreateCalendarEventRequest,
-    CreateCalendarEventResponse, CreatePersonRequest, CreateStarChartRequest,
-    CreateStarChartResponse, IncrementStarChartRequest, UpdateStarChartRequest,
+    AllowanceBalanceResponse, AllowanceEntryRequest, AllowanceEntryResponse,
+    AllowanceStatementResponse, ArchivedQuery, BonusWindowResponse, BountyResponse,
+    CalendarEventResponse, CalendarFeed, CalendarPersonResponse, Caller, ChartStreakResponse,
+    ChoreAssignmentResponse, ChoreAssignmentsQuery, ChoreResponse, ClaimBountyRequest,
+    CompleteBountyRequest, CompleteChoreRequest, ContributeGoalRequest, CreateBonusWindowRequest,
+    CreateBountyRequest, CreateCalendarEventRequest, CreateCalendarEventResponse,
+    CreateChoreRequest, CreateGoalRequest, CreatePersonRequest, CreateRewardRequest,
+    CreateRewardResponse, CreateStarChartRequest, CreateStarChartResponse, CreateTransferRequest,
+    DeleteCalendarEventQuery, DueSoonResponse, GetApprovalResponse, GetRedemptionResponse,
+    GetRewardResponse, GoalResponse, ImportCalendarQuery, ImportCalendarResponse,
+    IncrementStarChartRequest, IncrementStarChartResponse, InstantiateTemplateRequest,
+    InstantiateTemplateResponse, LeaderboardEntryResponse, LeaderboardQuery, LevelUpResponse,
+    ListApprovalsQuery, ListBonusWindowsQuery, ListBountiesQuery, ListCalendarEventsQuery,
+    ListRedemptionsQuery, ListStarChartsQuery, ListTransfersQuery, LockedBadgeResponse,
+    PersonStatsResponse, PropagateTemplateResponse, PurgeResponse, QuestResponse,
+    RedeemRewardRequest, RedeemRewardResponse, ResolveApprovalRequest, SetPrerequisitesRequest,
+    StarChartPeriodResponse, StarEventResponse, StarsPerPeriodResponse, StatsQuery,
+    TemplateResponse, TransferResponse, UndoResponse, UpdateAllowanceRequest,
+    UpdateCalendarEventRequest, UpdateCalendarEventResponse, UpdateRewardRequest,
+    UpdateStarChartRequest, VerifyBountyRequest,
 };
 
 #[derive(Clone)]
@@ -37,18 +57,96 @@ pub async fn run(config: Configuration, database_connection: SQLConnector, port:
         .route("/people/:first_name", get(get_person))
         .route("/people", post(create_person))
         .route("/admin/people", get(admin_list_people))
-        .route("/admin/people/:id", delete(admin_delete_person))
-        .route("/admin/stars/:id", delete(admin_delete_star))
+        .route("/admin/people/:id", delete(admin_archive_person))
+        .route("/admin/people/:id/restore", post(admin_restore_person))
+        .route("/admin/stars/:id", delete(admin_archive_star))
+        .route("/admin/stars/:id/restore", post(admin_restore_star))
+        .route("/admin/purge", post(admin_purge))
         .route("/calendar/people", get(list_calendar_people))
+        .route("/calendar/family.ics", get(family_calendar_feed))
+        .route("/calendar/people/:file", get(person_calendar_feed))
+        .route("/calendar/import", post(import_calendar))
         .route(
             "/calendar/events",
             get(list_calendar_events).post(create_calendar_event),
         )
+        .route(
+            "/calendar/events/:id",
+            patch(update_calendar_event).delete(delete_calendar_event),
+        )
+        .route("/undo", post(undo))
+        .route("/redo", post(redo))
         .route("/stars", get(get_star_charts))
         .route("/stars", post(create_star_chart))
+        .route("/stars/due-soon", get(list_due_soon))
         .route("/stars/:id", get(get_star_chart))
         .route("/stars/:id", patch(update_star_chart))
         .route("/stars/:id/increment", post(increment_star_chart))
+        .route("/stars/:id/history", get(get_star_chart_history))
+        .route("/stars/:id/periods", get(list_star_chart_periods))
+        .route(
+            "/stars/:id/prerequisites",
+            put(set_star_chart_prerequisites),
+        )
+        .route("/goals", get(list_goals).post(create_goal))
+        .route("/goals/:id", get(get_goal).delete(delete_goal))
+        .route("/goals/:id/contribute", post(contribute_to_goal))
+        .route("/bounties", get(list_bounties).post(create_bounty))
+        .route("/bounties/:id", delete(delete_bounty))
+        .route("/bounties/:id/claim", post(claim_bounty))
+        .route("/bounties/:id/complete", post(complete_bounty))
+        .route("/bounties/:id/verify", post(verify_bounty))
+        .route("/people/:first_name/history", get(get_person_history))
+        .route("/people/:first_name/streaks", get(get_person_streaks))
+        .route("/people/:first_name/quests", get(get_person_quests))
+        .route("/people/:first_name/levels", get(list_level_ups))
+        .route("/people/:first_name/badges/locked", get(list_locked_badges))
+        .route("/rewards", get(list_rewards).post(create_reward))
+        .route(
+            "/rewards/:id",
+            get(get_reward).patch(update_reward).delete(delete_reward),
+        )
+        .route("/rewards/:id/redeem", post(redeem_reward))
+        .route("/redemptions", get(list_redemptions))
+        .route("/redemptions/:id/deliver", post(deliver_redemption))
+        .route("/transfers", get(list_transfers).post(create_transfer))
+        .route(
+            "/bonuses",
+            get(list_bonus_windows).post(create_bonus_window),
+        )
+        .route("/bonuses/:id", delete(delete_bonus_window))
+        .route("/transfers/:id/approve", post(approve_transfer))
+        .route("/transfers/:id/reject", post(reject_transfer))
+        .route("/approvals", get(list_approvals))
+        .route("/approvals/:id/approve", post(approve_approval))
+        .route("/approvals/:id/reject", post(reject_approval))
+        .route("/templates", get(list_templates))
+        .route("/allowance", get(get_allowance_balances))
+        .route("/chores", get(list_chores).post(create_chore))
+        .route("/chores/:id", delete(delete_chore))
+        .route("/chores/today", get(list_chore_assignments))
+        .route(
+            "/chores/assignments/:id/done",
+            post(complete_chore_assignment),
+        )
+        .route(
+            "/people/:first_name/allowance",
+            get(get_allowance_statement).patch(update_allowance),
+        )
+        .route(
+            "/people/:first_name/allowance/statement.csv",
+            get(export_allowance_statement),
+        )
+        .route("/people/:first_name/allowance/payouts", post(record_payout))
+        .route(
+            "/people/:first_name/allowance/adjustments",
+            post(record_adjustment),
+        )
+        .route("/stats/people", get(get_person_stats))
+        .route("/stats/timeline", get(get_stars_per_period))
+        .route("/stats/leaderboard", get(get_leaderboard))
+        .route("/templates/:key/instantiate", post(instantiate_template))
+        .route("/templates/:key/propagate", post(propagate_template))
         .route("/app.js", get(serve_app_js))
         .route("/styles.css", get(serve_styles))
         .route("/logo.png", get(serve_logo))
@@ -59,6 +157,11 @@ pub async fn run(config: Configuration, database_connection: SQLConnector, port:
             configuration: config.clone(),
         });
 
+    tokio::spawn(run_recurrence_task(shared_db.clone()));
+    tokio::spawn(run_chore_task(shared_db.clone()));
+    tokio::spawn(run_purge_task(shared_db.clone()));
+    tokio::spawn(run_deadline_task(shared_db.clone()));
+
     let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{port}"))
         .await
         .unwrap();
@@ -68,8 +171,83 @@ pub async fn run(config: Configuration, database_connection: SQLConnector, port:
         .unwrap();
 
     // NOTE: this only runs after the server stops.
-    // Also: you can’t call close() on Arc<T> unless SQLConnector::close takes &self.
-    // shared_db.close().await.unwrap();
+    if let Err(e) = shared_db.close().await {
+        log::error!("Failed to close the database: {}", e);
+    }
+}
+
+/// Map domain errors onto status codes; anything else is a 500.
+fn error_response(e: anyhow::Error) -> (StatusCode, String) {
+    match e.downcast_ref::<HGError>() {
+        Some(HGError::NotFound(msg)) => (StatusCode::NOT_FOUND, msg.clone()),
+        Some(HGError::Invalid(msg)) => (StatusCode::BAD_REQUEST, msg.clone()),
+        Some(HGError::Forbidden(msg)) => (StatusCode::FORBIDDEN, msg.clone()),
+        Some(HGError::Conflict(msg)) | Some(HGError::VersionMismatch(msg)) => {
+            (StatusCode::CONFLICT, msg.clone())
+        }
+        Some(HGError::RateLimited { message, .. }) => {
+            (StatusCode::TOO_MANY_REQUESTS, message.clone())
+        }
+        None => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+/// Periodically close out recurring star charts whose period has ended.
+async fn run_recurrence_task(database_connection: Arc<SQLConnector>) {
+    let mut interval = tokio::time::interval(Duration::from_secs(60));
+    loop {
+        interval.tick().await;
+        match database_connection.close_recurring_periods().await {
+            Ok(0) => {}
+            Ok(n) => info!("Reset {} recurring star chart(s)", n),
+            Err(e) => error!("Failed to reset recurring star charts: {}", e),
+        }
+    }
+}
+
+/// Keep chore assignments generated ahead of time.
+async fn run_chore_task(database_connection: Arc<SQLConnector>) {
+    let mut interval = tokio::time::interval(Duration::from_secs(60 * 60));
+    loop {
+        interval.tick().await;
+        match database_connection.schedule_chores().await {
+            Ok(0) => {}
+            Ok(n) => info!("Scheduled {} chore assignment(s)", n),
+            Err(e) => error!("Failed to schedule chores: {}", e),
+        }
+    }
+}
+
+/// Close charts whose due date has passed.
+async fn run_deadline_task(database_connection: Arc<SQLConnector>) {
+    let mut interval = tokio::time::interval(Duration::from_secs(60));
+    loop {
+        interval.tick().await;
+        match database_connection.close_overdue_charts().await {
+            Ok(0) => {}
+            Ok(n) => info!("Closed {} overdue star chart(s)", n),
+            Err(e) => error!("Failed to close overdue star charts: {}", e),
+        }
+    }
+}
+
+/// Delete whatever has been archived for longer than the retention period.
+async fn run_purge_task(database_connection: Arc<SQLConnector>) {
+    let mut interval = tokio::time::interval(Duration::from_secs(24 * 60 * 60));
+    loop {
+        interval.tick().await;
+        match database_connection.purge_archived().await {
+            Ok(PurgeResponse {
+                people: 0,
+                star_charts: 0,
+            }) => {}
+            Ok(p) => info!(
+                "Purged {} archived person(s) and {} archived star chart(s)",
+                p.people, p.star_charts
+            ),
+            Err(e) => error!("Failed to purge archived rows: {}", e),
+        }
+    }
 }
 
 async fn serve_index() -> Result<impl IntoResponse, (StatusCode, String)> {
@@ -176,7 +354,7 @@ async fn create_star_chart(
         .as_ref()
         .create_star_chart(&payload)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(error_response)?;
 
     Ok((StatusCode::CREATED, Json(resp)))
 }
@@ -184,37 +362,120 @@ async fn create_star_chart(
 async fn update_star_chart(
     State(state): State<ServerConfig>,
     Path(id): Path<i32>,
+    headers: HeaderMap,
     Json(payload): Json<UpdateStarChartRequest>,
-) -> Result<StatusCode, (StatusCode, String)> {
-    let _star_chart = state
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    // If-Match: * (or no header) means the caller doesn't care which version it overwrites
+    let expected_version = match headers.get(header::IF_MATCH) {
+        Some(value) => match value.to_str().ok().map(str::trim) {
+            Some("*") => None,
+            Some(tag) => Some(parse_etag(tag).ok_or((
+                StatusCode::BAD_REQUEST,
+                format!("Invalid If-Match header {}", tag),
+            ))?),
+            None => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    "Invalid If-Match header".to_string(),
+                ));
+            }
+        },
+        None => None,
+    };
+
+    let star_chart = state
         .database_connection
         .as_ref()
-        .update_star_chart(id, &payload)
+        .update_star_chart(id, &payload, expected_version, client_id(&headers))
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(error_response)?;
 
-    Ok(StatusCode::OK)
+    Ok((
+        StatusCode::OK,
+        [(header::ETAG, etag(star_chart.version))],
+        Json(star_chart),
+    ))
+}
+
+/// Which device made a change, so each one gets its own undo history
+fn client_id(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get("x-client-id")
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+}
+
+/// Who sent a request, from the PIN in the X-Pin header. No header means an
+/// anonymous caller, a PIN nobody has is refused outright
+fn caller(
+    config: &Configuration,
+    headers: &HeaderMap,
+) -> Result<Option<Caller>, (StatusCode, String)> {
+    let Some(pin) = headers
+        .get("x-pin")
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+    else {
+        return Ok(None);
+    };
+    let member = config
+        .member_with_pin(pin)
+        .ok_or((StatusCode::UNAUTHORIZED, "unknown PIN".to_string()))?;
+    Ok(Some(Caller {
+        first_name: member.first_name.clone(),
+        is_parent: !member.children.is_empty(),
+    }))
+}
+
+fn etag(version: i32) -> String {
+    format!("\"{}\"", version)
+}
+
+fn parse_etag(tag: &str) -> Option<i32> {
+    tag.trim_start_matches("W/").trim_matches('"').parse().ok()
 }
 
 async fn increment_star_chart(
     State(state): State<ServerConfig>,
     Path(id): Path<i32>,
+    headers: HeaderMap,
     Json(payload): Json<IncrementStarChartRequest>,
-) -> Result<StatusCode, (StatusCode, String)> {
-    state
+) -> Result<(StatusCode, Json<IncrementStarChartResponse>), Response> {
+    let caller = caller(&state.configuration, &headers).map_err(IntoResponse::into_response)?;
+    let resp = state
         .database_connection
         .as_ref()
-        .increment_star_chart(id, payload.delta)
+        .increment_star_chart(id, &payload, client_id(&headers), caller.as_ref())
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(|e| match e.downcast_ref::<HGError>() {
+            // Tell the client when to come back
+            Some(HGError::RateLimited {
+                message,
+                retry_after_secs,
+            }) => (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, retry_after_secs.to_string())],
+                message.clone(),
+            )
+                .into_response(),
+            _ => error_response(e).into_response(),
+        })?;
 
-    Ok(StatusCode::OK)
+    // Waiting on a parent rather than applied
+    let status = if resp.approval_id.is_some() {
+        StatusCode::ACCEPTED
+    } else {
+        StatusCode::OK
+    };
+    Ok((status, Json(resp)))
 }
 
 async fn get_star_chart(
     State(state): State<ServerConfig>,
     Path(id): Path<i32>,
-) -> Result<Json<crate::server::types::GetStarChartResponse>, (StatusCode, String)> {
+) -> Result<impl IntoResponse, (StatusCode, String)> {
     let star_chart = state
         .database_connection
         .as_ref()
@@ -223,7 +484,64 @@ async fn get_star_chart(
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     match star_chart {
-        Some(s) => Ok(Json(s)),
+        Some(s) => Ok(([(header::ETAG, etag(s.version))], Json(s))),
+        None => Err((
+            StatusCode::NOT_FOUND,
+            format!("No star chart with id {}", id),
+        )),
+    }
+}
+
+async fn get_star_chart_history(
+    State(state): State<ServerConfig>,
+    Path(id): Path<i32>,
+) -> Result<Json<Vec<StarEventResponse>>, (StatusCode, String)> {
+    let history = state
+        .database_connection
+        .as_ref()
+        .get_star_chart_history(id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    match history {
+        Some(h) => Ok(Json(h)),
+        None => Err((
+            StatusCode::NOT_FOUND,
+            format!("No star chart with id {}", id),
+        )),
+    }
+}
+
+async fn get_person_history(
+    State(state): State<ServerConfig>,
+    Path(name): Path<String>,
+) -> Result<Json<Vec<StarEventResponse>>, (StatusCode, String)> {
+    let history = state
+        .database_connection
+        .as_ref()
+        .get_person_history(&name)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    match history {
+        Some(h) => Ok(Json(h)),
+        None => Err((StatusCode::NOT_FOUND, format!("No person named {}", &name))),
+    }
+}
+
+async fn list_star_chart_periods(
+    State(state): State<ServerConfig>,
+    Path(id): Path<i32>,
+) -> Result<Json<Vec<StarChartPeriodResponse>>, (StatusCode, String)> {
+    let periods = state
+        .database_connection
+        .as_ref()
+        .list_star_chart_periods(id)
+        .await
+        .map_err(error_response)?;
+
+    match periods {
+        Some(p) => Ok(Json(p)),
         None => Err((
             StatusCode::NOT_FOUND,
             format!("No star chart with id {}", id),
@@ -231,60 +549,194 @@ async fn get_star_chart(
     }
 }
 
+async fn get_person_streaks(
+    State(state): State<ServerConfig>,
+    Path(name): Path<String>,
+) -> Result<Json<Vec<ChartStreakResponse>>, (StatusCode, String)> {
+    let streaks = state
+        .database_connection
+        .as_ref()
+        .get_person_streaks(&name)
+        .await
+        .map_err(error_response)?;
+
+    match streaks {
+        Some(s) => Ok(Json(s)),
+        None => Err((StatusCode::NOT_FOUND, format!("No person named {}", &name))),
+    }
+}
+
+async fn list_locked_badges(
+    State(state): State<ServerConfig>,
+    Path(name): Path<String>,
+) -> Result<Json<Vec<LockedBadgeResponse>>, (StatusCode, String)> {
+    let badges = state
+        .database_connection
+        .as_ref()
+        .list_locked_badges(&name)
+        .await
+        .map_err(error_response)?;
+
+    match badges {
+        Some(b) => Ok(Json(b)),
+        None => Err((StatusCode::NOT_FOUND, format!("No person named {}", &name))),
+    }
+}
+
 async fn get_star_charts(
     State(state): State<ServerConfig>,
+    Query(query): Query<ListStarChartsQuery>,
 ) -> Result<Json<Vec<crate::server::types::GetStarChartResponse>>, (StatusCode, String)> {
     let charts = state
         .database_connection
         .as_ref()
-        .get_star_charts()
+        .get_star_charts(&query)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     Ok(Json(charts))
 }
 
+async fn list_bonus_windows(
+    State(state): State<ServerConfig>,
+    Query(query): Query<ListBonusWindowsQuery>,
+) -> Result<Json<Vec<BonusWindowResponse>>, (StatusCode, String)> {
+    let windows = state
+        .database_connection
+        .as_ref()
+        .list_bonus_windows(&query)
+        .await
+        .map_err(error_response)?;
+
+    Ok(Json(windows))
+}
+
+async fn create_bonus_window(
+    State(state): State<ServerConfig>,
+    headers: HeaderMap,
+    Json(payload): Json<CreateBonusWindowRequest>,
+) -> Result<(StatusCode, Json<BonusWindowResponse>), (StatusCode, String)> {
+    let caller = caller(&state.configuration, &headers)?;
+    let window = state
+        .database_connection
+        .as_ref()
+        .create_bonus_window(&payload, caller.as_ref())
+        .await
+        .map_err(error_response)?;
+
+    Ok((StatusCode::CREATED, Json(window)))
+}
+
+async fn delete_bonus_window(
+    State(state): State<ServerConfig>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    state
+        .database_connection
+        .as_ref()
+        .delete_bonus_window(id)
+        .await
+        .map_err(error_response)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn list_due_soon(
+    State(state): State<ServerConfig>,
+) -> Result<Json<Vec<DueSoonResponse>>, (StatusCode, String)> {
+    let charts = state
+        .database_connection
+        .as_ref()
+        .list_due_soon()
+        .await
+        .map_err(error_response)?;
+
+    Ok(Json(charts))
+}
+
 async fn admin_list_people(
     State(state): State<ServerConfig>,
+    Query(query): Query<ArchivedQuery>,
 ) -> Result<Json<Vec<crate::server::types::PersonListItem>>, (StatusCode, String)> {
     let people = state
         .database_connection
         .as_ref()
-        .get_all_people()
+        .get_all_people(query.include_archived)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     Ok(Json(people))
 }
 
-async fn admin_delete_person(
+async fn admin_archive_person(
     State(state): State<ServerConfig>,
     Path(id): Path<i32>,
 ) -> Result<StatusCode, (StatusCode, String)> {
     state
         .database_connection
         .as_ref()
-        .delete_person(id)
+        .archive_person(id)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(error_response)?;
 
     Ok(StatusCode::OK)
 }
 
-async fn admin_delete_star(
+async fn admin_restore_person(
     State(state): State<ServerConfig>,
     Path(id): Path<i32>,
 ) -> Result<StatusCode, (StatusCode, String)> {
     state
         .database_connection
         .as_ref()
-        .delete_star_chart(id)
+        .restore_person(id)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(error_response)?;
+
+    Ok(StatusCode::OK)
+}
+
+async fn admin_archive_star(
+    State(state): State<ServerConfig>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    state
+        .database_connection
+        .as_ref()
+        .archive_star_chart(id)
+        .await
+        .map_err(error_response)?;
+
+    Ok(StatusCode::OK)
+}
+
+async fn admin_restore_star(
+    State(state): State<ServerConfig>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    state
+        .database_connection
+        .as_ref()
+        .restore_star_chart(id)
+        .await
+        .map_err(error_response)?;
 
     Ok(StatusCode::OK)
 }
 
+async fn admin_purge(
+    State(state): State<ServerConfig>,
+) -> Result<Json<PurgeResponse>, (StatusCode, String)> {
+    let purged = state
+        .database_connection
+        .as_ref()
+        .purge_archived()
+        .await
+        .map_err(error_response)?;
+
+    Ok(Json(purged))
+}
+
 async fn list_calendar_people(
     State(state): State<ServerConfig>,
 ) -> Result<Json<Vec<CalendarPersonResponse>>, (StatusCode, String)> {
@@ -298,43 +750,987 @@ async fn list_calendar_people(
     Ok(Json(people))
 }
 
+async fn family_calendar_feed(
+    State(state): State<ServerConfig>,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, String)> {
+    let feed = state
+        .database_connection
+        .as_ref()
+        .calendar_feed(None)
+        .await
+        .map_err(error_response)?;
+
+    Ok(calendar_feed_response(feed, &headers))
+}
+
+async fn person_calendar_feed(
+    State(state): State<ServerConfig>,
+    Path(file): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, String)> {
+    // The route captures the whole "<id>.ics" segment
+    let Some(id) = file
+        .strip_suffix(".ics")
+        .and_then(|id| id.parse::<i32>().ok())
+    else {
+        return Err((StatusCode::NOT_FOUND, format!("no calendar {}", file)));
+    };
+    let feed = state
+        .database_connection
+        .as_ref()
+        .calendar_feed(Some(id))
+        .await
+        .map_err(error_response)?;
+
+    Ok(calendar_feed_response(feed, &headers))
+}
+
+/// Serve a feed with validators so subscribed apps can poll cheaply,
+/// answering 304 when their copy is still current
+fn calendar_feed_response(feed: CalendarFeed, headers: &HeaderMap) -> Response {
+    use axum::http::HeaderValue;
+
+    let tag = format!("\"{:016x}\"", fnv1a(feed.body.as_bytes()));
+
+    // If-None-Match wins over If-Modified-Since when both are sent
+    let not_modified = match headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+    {
+        Some(tags) => tags
+            .split(',')
+            .map(str::trim)
+            .any(|t| t == "*" || t.trim_start_matches("W/") == tag),
+        None => match (
+            feed.last_modified,
+            headers
+                .get(header::IF_MODIFIED_SINCE)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| chrono::DateTime::parse_from_rfc2822(v).ok()),
+        ) {
+            (Some(modified), Some(since)) => modified.timestamp() <= since.timestamp(),
+            _ => false,
+        },
+    };
+
+    let mut response = if not_modified {
+        StatusCode::NOT_MODIFIED.into_response()
+    } else {
+        (
+            [(header::CONTENT_TYPE, "text/calendar; charset=utf-8")],
+            feed.body,
+        )
+            .into_response()
+    };
+    let response_headers = response.headers_mut();
+    if let Ok(tag) = HeaderValue::from_str(&tag) {
+        response_headers.insert(header::ETAG, tag);
+    }
+    if let Some(modified) = feed.last_modified
+        && let Ok(modified) =
+            HeaderValue::from_str(&modified.format("%a, %d %b %Y %H:%M:%S GMT").to_string())
+    {
+        response_headers.insert(header::LAST_MODIFIED, modified);
+    }
+    response_headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    response
+}
+
+/// 64-bit FNV-1a, so a feed keeps its ETag across restarts and builds,
+/// which the standard library's hasher doesn't promise
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+        (hash ^ u64::from(*b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+async fn import_calendar(
+    State(state): State<ServerConfig>,
+    Query(query): Query<ImportCalendarQuery>,
+    body: String,
+) -> Result<Json<ImportCalendarResponse>, (StatusCode, String)> {
+    let report = state
+        .database_connection
+        .as_ref()
+        .import_calendar(query.person_id, &body)
+        .await
+        .map_err(error_response)?;
+
+    Ok(Json(report))
+}
+
 async fn list_calendar_events(
     State(state): State<ServerConfig>,
+    Query(query): Query<ListCalendarEventsQuery>,
 ) -> Result<Json<Vec<CalendarEventResponse>>, (StatusCode, String)> {
     let events = state
         .database_connection
         .as_ref()
-        .list_calendar_events()
+        .list_calendar_events(&query)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(error_response)?;
 
     Ok(Json(events))
 }
 
 async fn create_calendar_event(
     State(state): State<ServerConfig>,
+    headers: HeaderMap,
     Json(payload): Json<CreateCalendarEventRequest>,
 ) -> Result<(StatusCode, Json<CreateCalendarEventResponse>), (StatusCode, String)> {
     let resp = state
         .database_connection
         .as_ref()
-        .create_calendar_event(&payload)
+        .create_calendar_event(&payload, client_id(&headers))
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(error_response)?;
 
     Ok((StatusCode::CREATED, Json(resp)))
 }
 
-async fn initialize_db(
+async fn update_calendar_event(
+    State(state): State<ServerConfig>,
+    Path(id): Path<i32>,
+    headers: HeaderMap,
+    Json(payload): Json<UpdateCalendarEventRequest>,
+) -> Result<Json<UpdateCalendarEventResponse>, (StatusCode, String)> {
+    let resp = state
+        .database_connection
+        .as_ref()
+        .update_calendar_event(id, &payload, client_id(&headers))
+        .await
+        .map_err(error_response)?;
+
+    Ok(Json(resp))
+}
+
+async fn delete_calendar_event(
     State(state): State<ServerConfig>,
+    Path(id): Path<i32>,
+    Query(query): Query<DeleteCalendarEventQuery>,
+    headers: HeaderMap,
 ) -> Result<StatusCode, (StatusCode, String)> {
-    //  This is a little ineligent
     state
         .database_connection
         .as_ref()
-        .initialize(&state.configuration)
+        .delete_calendar_event(id, &query, client_id(&headers))
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(error_response)?;
 
-    Ok(StatusCode::OK)
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn undo(
+    State(state): State<ServerConfig>,
+    headers: HeaderMap,
+) -> Result<Json<UndoResponse>, (StatusCode, String)> {
+    let client = client_id(&headers).ok_or((
+        StatusCode::BAD_REQUEST,
+        "X-Client-Id header is required".to_string(),
+    ))?;
+    let undone = state
+        .database_connection
+        .as_ref()
+        .undo(client)
+        .await
+        .map_err(error_response)?;
+
+    Ok(Json(undone))
+}
+
+async fn redo(
+    State(state): State<ServerConfig>,
+    headers: HeaderMap,
+) -> Result<Json<UndoResponse>, (StatusCode, String)> {
+    let client = client_id(&headers).ok_or((
+        StatusCode::BAD_REQUEST,
+        "X-Client-Id header is required".to_string(),
+    ))?;
+    let redone = state
+        .database_connection
+        .as_ref()
+        .redo(client)
+        .await
+        .map_err(error_response)?;
+
+    Ok(Json(redone))
+}
+
+async fn list_templates(
+    State(state): State<ServerConfig>,
+) -> Result<Json<Vec<TemplateResponse>>, (StatusCode, String)> {
+    let templates = state
+        .database_connection
+        .as_ref()
+        .list_templates()
+        .await
+        .map_err(error_response)?;
+
+    Ok(Json(templates))
+}
+
+async fn instantiate_template(
+    State(state): State<ServerConfig>,
+    Path(key): Path<String>,
+    Json(payload): Json<InstantiateTemplateRequest>,
+) -> Result<(StatusCode, Json<InstantiateTemplateResponse>), (StatusCode, String)> {
+    let resp = state
+        .database_connection
+        .as_ref()
+        .instantiate_template(&key, &payload)
+        .await
+        .map_err(error_response)?;
+
+    Ok((StatusCode::CREATED, Json(resp)))
+}
+
+async fn propagate_template(
+    State(state): State<ServerConfig>,
+    Path(key): Path<String>,
+) -> Result<Json<PropagateTemplateResponse>, (StatusCode, String)> {
+    let resp = state
+        .database_connection
+        .as_ref()
+        .propagate_template(&key)
+        .await
+        .map_err(error_response)?;
+
+    Ok(Json(resp))
+}
+
+async fn get_person_stats(
+    State(state): State<ServerConfig>,
+    Query(query): Query<StatsQuery>,
+) -> Result<Json<Vec<PersonStatsResponse>>, (StatusCode, String)> {
+    let stats = state
+        .database_connection
+        .as_ref()
+        .get_person_stats(&query)
+        .await
+        .map_err(error_response)?;
+
+    Ok(Json(stats))
+}
+
+async fn get_stars_per_period(
+    State(state): State<ServerConfig>,
+    Query(query): Query<StatsQuery>,
+) -> Result<Json<Vec<StarsPerPeriodResponse>>, (StatusCode, String)> {
+    let periods = state
+        .database_connection
+        .as_ref()
+        .get_stars_per_period(&query)
+        .await
+        .map_err(error_response)?;
+
+    Ok(Json(periods))
+}
+
+async fn get_leaderboard(
+    State(state): State<ServerConfig>,
+    Query(query): Query<LeaderboardQuery>,
+) -> Result<Json<Vec<LeaderboardEntryResponse>>, (StatusCode, String)> {
+    let entries = state
+        .database_connection
+        .as_ref()
+        .get_leaderboard(&query)
+        .await
+        .map_err(error_response)?;
+
+    Ok(Json(entries))
+}
+
+async fn get_allowance_balances(
+    State(state): State<ServerConfig>,
+) -> Result<Json<Vec<AllowanceBalanceResponse>>, (StatusCode, String)> {
+    let balances = state
+        .database_connection
+        .as_ref()
+        .get_allowance_balances()
+        .await
+        .map_err(error_response)?;
+
+    Ok(Json(balances))
+}
+
+async fn get_allowance_statement(
+    State(state): State<ServerConfig>,
+    Path(name): Path<String>,
+) -> Result<Json<AllowanceStatementResponse>, (StatusCode, String)> {
+    let statement = state
+        .database_connection
+        .as_ref()
+        .get_allowance_statement(&name)
+        .await
+        .map_err(error_response)?;
+
+    match statement {
+        Some(s) => Ok(Json(s)),
+        None => Err((StatusCode::NOT_FOUND, format!("No person named {}", &name))),
+    }
+}
+
+async fn export_allowance_statement(
+    State(state): State<ServerConfig>,
+    Path(name): Path<String>,
+) -> Result<Response, (StatusCode, String)> {
+    use sea_orm::ActiveEnum;
+
+    let statement = state
+        .database_connection
+        .as_ref()
+        .get_allowance_statement(&name)
+        .await
+        .map_err(error_response)?;
+    let Some(statement) = statement else {
+        return Err((StatusCode::NOT_FOUND, format!("No person named {}", &name)));
+    };
+
+    let mut csv = String::from("date,kind,stars,amount,balance,note,actor\n");
+    for e in &statement.entries {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{}\n",
+            e.created_at,
+            e.kind.to_value(),
+            e.stars.map(|s| s.to_string()).unwrap_or_default(),
+            format_cents(e.amount_cents),
+            format_cents(e.balance_cents),
+            csv_field(e.note.as_deref().unwrap_or_default()),
+            csv_field(e.actor.as_deref().unwrap_or_default()),
+        ));
+    }
+    let disposition = format!(
+        "attachment; filename=\"{}-allowance.csv\"",
+        statement.first_name.to_lowercase()
+    );
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        csv,
+    )
+        .into_response())
+}
+
+/// Cents as a decimal amount without going through floating point
+fn format_cents(cents: i64) -> String {
+    let sign = if cents < 0 { "-" } else { "" };
+    let cents = cents.unsigned_abs();
+    format!("{}{}.{:02}", sign, cents / 100, cents % 100)
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+async fn update_allowance(
+    State(state): State<ServerConfig>,
+    Path(name): Path<String>,
+    Json(payload): Json<UpdateAllowanceRequest>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    state
+        .database_connection
+        .as_ref()
+        .update_allowance(&name, &payload)
+        .await
+        .map_err(error_response)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn record_payout(
+    State(state): State<ServerConfig>,
+    Path(name): Path<String>,
+    Json(payload): Json<AllowanceEntryRequest>,
+) -> Result<(StatusCode, Json<AllowanceEntryResponse>), (StatusCode, String)> {
+    let entry = state
+        .database_connection
+        .as_ref()
+        .record_payout(&name, &payload)
+        .await
+        .map_err(error_response)?;
+
+    Ok((StatusCode::CREATED, Json(entry)))
+}
+
+async fn record_adjustment(
+    State(state): State<ServerConfig>,
+    Path(name): Path<String>,
+    Json(payload): Json<AllowanceEntryRequest>,
+) -> Result<(StatusCode, Json<AllowanceEntryResponse>), (StatusCode, String)> {
+    let entry = state
+        .database_connection
+        .as_ref()
+        .record_adjustment(&name, &payload)
+        .await
+        .map_err(error_response)?;
+
+    Ok((StatusCode::CREATED, Json(entry)))
+}
+
+async fn list_chores(
+    State(state): State<ServerConfig>,
+) -> Result<Json<Vec<ChoreResponse>>, (StatusCode, String)> {
+    let chores = state
+        .database_connection
+        .as_ref()
+        .list_chores()
+        .await
+        .map_err(error_response)?;
+
+    Ok(Json(chores))
+}
+
+async fn create_chore(
+    State(state): State<ServerConfig>,
+    Json(payload): Json<CreateChoreRequest>,
+) -> Result<(StatusCode, Json<ChoreResponse>), (StatusCode, String)> {
+    let chore = state
+        .database_connection
+        .as_ref()
+        .create_chore(&payload)
+        .await
+        .map_err(error_response)?;
+
+    Ok((StatusCode::CREATED, Json(chore)))
+}
+
+async fn delete_chore(
+    State(state): State<ServerConfig>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    state
+        .database_connection
+        .as_ref()
+        .delete_chore(id)
+        .await
+        .map_err(error_response)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn list_chore_assignments(
+    State(state): State<ServerConfig>,
+    Query(query): Query<ChoreAssignmentsQuery>,
+) -> Result<Json<Vec<ChoreAssignmentResponse>>, (StatusCode, String)> {
+    let assignments = state
+        .database_connection
+        .as_ref()
+        .list_chore_assignments(&query)
+        .await
+        .map_err(error_response)?;
+
+    Ok(Json(assignments))
+}
+
+async fn complete_chore_assignment(
+    State(state): State<ServerConfig>,
+    Path(id): Path<i32>,
+    Json(payload): Json<CompleteChoreRequest>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    state
+        .database_connection
+        .as_ref()
+        .complete_chore_assignment(id, &payload)
+        .await
+        .map_err(error_response)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn list_goals(
+    State(state): State<ServerConfig>,
+) -> Result<Json<Vec<GoalResponse>>, (StatusCode, String)> {
+    let goals = state
+        .database_connection
+        .as_ref()
+        .list_goals()
+        .await
+        .map_err(error_response)?;
+
+    Ok(Json(goals))
+}
+
+async fn get_goal(
+    State(state): State<ServerConfig>,
+    Path(id): Path<i32>,
+) -> Result<Json<GoalResponse>, (StatusCode, String)> {
+    let goal = state
+        .database_connection
+        .as_ref()
+        .get_goal(id)
+        .await
+        .map_err(error_response)?;
+
+    Ok(Json(goal))
+}
+
+async fn create_goal(
+    State(state): State<ServerConfig>,
+    Json(payload): Json<CreateGoalRequest>,
+) -> Result<(StatusCode, Json<GoalResponse>), (StatusCode, String)> {
+    let goal = state
+        .database_connection
+        .as_ref()
+        .create_goal(&payload)
+        .await
+        .map_err(error_response)?;
+
+    Ok((StatusCode::CREATED, Json(goal)))
+}
+
+async fn delete_goal(
+    State(state): State<ServerConfig>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    state
+        .database_connection
+        .as_ref()
+        .delete_goal(id)
+        .await
+        .map_err(error_response)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn contribute_to_goal(
+    State(state): State<ServerConfig>,
+    Path(id): Path<i32>,
+    headers: HeaderMap,
+    Json(payload): Json<ContributeGoalRequest>,
+) -> Result<Json<GoalResponse>, (StatusCode, String)> {
+    let caller = caller(&state.configuration, &headers)?;
+    let goal = state
+        .database_connection
+        .as_ref()
+        .contribute_to_goal(id, &payload, caller.as_ref())
+        .await
+        .map_err(error_response)?;
+
+    Ok(Json(goal))
+}
+
+async fn list_bounties(
+    State(state): State<ServerConfig>,
+    Query(query): Query<ListBountiesQuery>,
+) -> Result<Json<Vec<BountyResponse>>, (StatusCode, String)> {
+    let bounties = state
+        .database_connection
+        .as_ref()
+        .list_bounties(&query)
+        .await
+        .map_err(error_response)?;
+
+    Ok(Json(bounties))
+}
+
+async fn create_bounty(
+    State(state): State<ServerConfig>,
+    headers: HeaderMap,
+    Json(payload): Json<CreateBountyRequest>,
+) -> Result<(StatusCode, Json<BountyResponse>), (StatusCode, String)> {
+    let caller = caller(&state.configuration, &headers)?;
+    let bounty = state
+        .database_connection
+        .as_ref()
+        .create_bounty(&payload, caller.as_ref())
+        .await
+        .map_err(error_response)?;
+
+    Ok((StatusCode::CREATED, Json(bounty)))
+}
+
+async fn delete_bounty(
+    State(state): State<ServerConfig>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    state
+        .database_connection
+        .as_ref()
+        .delete_bounty(id)
+        .await
+        .map_err(error_response)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn claim_bounty(
+    State(state): State<ServerConfig>,
+    Path(id): Path<i32>,
+    Json(payload): Json<ClaimBountyRequest>,
+) -> Result<Json<BountyResponse>, (StatusCode, String)> {
+    let bounty = state
+        .database_connection
+        .as_ref()
+        .claim_bounty(id, &payload)
+        .await
+        .map_err(error_response)?;
+
+    Ok(Json(bounty))
+}
+
+async fn complete_bounty(
+    State(state): State<ServerConfig>,
+    Path(id): Path<i32>,
+    Json(payload): Json<CompleteBountyRequest>,
+) -> Result<Json<BountyResponse>, (StatusCode, String)> {
+    let bounty = state
+        .database_connection
+        .as_ref()
+        .complete_bounty(id, &payload)
+        .await
+        .map_err(error_response)?;
+
+    Ok(Json(bounty))
+}
+
+async fn verify_bounty(
+    State(state): State<ServerConfig>,
+    Path(id): Path<i32>,
+    headers: HeaderMap,
+    payload: Option<Json<VerifyBountyRequest>>,
+) -> Result<Json<BountyResponse>, (StatusCode, String)> {
+    let caller = caller(&state.configuration, &headers)?;
+    let Json(payload) = payload.unwrap_or_default();
+    let bounty = state
+        .database_connection
+        .as_ref()
+        .verify_bounty(id, &payload, caller.as_ref())
+        .await
+        .map_err(error_response)?;
+
+    Ok(Json(bounty))
+}
+
+async fn set_star_chart_prerequisites(
+    State(state): State<ServerConfig>,
+    Path(id): Path<i32>,
+    Json(payload): Json<SetPrerequisitesRequest>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    state
+        .database_connection
+        .as_ref()
+        .set_star_chart_prerequisites(id, &payload)
+        .await
+        .map_err(error_response)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn get_person_quests(
+    State(state): State<ServerConfig>,
+    Path(name): Path<String>,
+) -> Result<Json<Vec<QuestResponse>>, (StatusCode, String)> {
+    let quests = state
+        .database_connection
+        .as_ref()
+        .get_person_quests(&name)
+        .await
+        .map_err(error_response)?;
+
+    match quests {
+        Some(q) => Ok(Json(q)),
+        None => Err((StatusCode::NOT_FOUND, format!("No person named {}", &name))),
+    }
+}
+
+async fn list_level_ups(
+    State(state): State<ServerConfig>,
+    Path(name): Path<String>,
+) -> Result<Json<Vec<LevelUpResponse>>, (StatusCode, String)> {
+    let level_ups = state
+        .database_connection
+        .as_ref()
+        .list_level_ups(&name)
+        .await
+        .map_err(error_response)?;
+
+    match level_ups {
+        Some(l) => Ok(Json(l)),
+        None => Err((StatusCode::NOT_FOUND, format!("No person named {}", &name))),
+    }
+}
+
+async fn list_rewards(
+    State(state): State<ServerConfig>,
+) -> Result<Json<Vec<GetRewardResponse>>, (StatusCode, String)> {
+    let rewards = state
+        .database_connection
+        .as_ref()
+        .list_rewards()
+        .await
+        .map_err(error_response)?;
+
+    Ok(Json(rewards))
+}
+
+async fn get_reward(
+    State(state): State<ServerConfig>,
+    Path(id): Path<i32>,
+) -> Result<Json<GetRewardResponse>, (StatusCode, String)> {
+    let reward = state
+        .database_connection
+        .as_ref()
+        .get_reward(id)
+        .await
+        .map_err(error_response)?;
+
+    match reward {
+        Some(r) => Ok(Json(r)),
+        None => Err((StatusCode::NOT_FOUND, format!("No reward with id {}", id))),
+    }
+}
+
+async fn create_reward(
+    State(state): State<ServerConfig>,
+    Json(payload): Json<CreateRewardRequest>,
+) -> Result<(StatusCode, Json<CreateRewardResponse>), (StatusCode, String)> {
+    let resp = state
+        .database_connection
+        .as_ref()
+        .create_reward(&payload)
+        .await
+        .map_err(error_response)?;
+
+    Ok((StatusCode::CREATED, Json(resp)))
+}
+
+async fn update_reward(
+    State(state): State<ServerConfig>,
+    Path(id): Path<i32>,
+    Json(payload): Json<UpdateRewardRequest>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    state
+        .database_connection
+        .as_ref()
+        .update_reward(id, &payload)
+        .await
+        .map_err(error_response)?;
+
+    Ok(StatusCode::OK)
+}
+
+async fn delete_reward(
+    State(state): State<ServerConfig>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    state
+        .database_connection
+        .as_ref()
+        .delete_reward(id)
+        .await
+        .map_err(error_response)?;
+
+    Ok(StatusCode::OK)
+}
+
+async fn redeem_reward(
+    State(state): State<ServerConfig>,
+    Path(id): Path<i32>,
+    Json(payload): Json<RedeemRewardRequest>,
+) -> Result<(StatusCode, Json<RedeemRewardResponse>), (StatusCode, String)> {
+    let resp = state
+        .database_connection
+        .as_ref()
+        .redeem_reward(id, &payload)
+        .await
+        .map_err(error_response)?;
+
+    Ok((StatusCode::CREATED, Json(resp)))
+}
+
+async fn list_redemptions(
+    State(state): State<ServerConfig>,
+    Query(query): Query<ListRedemptionsQuery>,
+) -> Result<Json<Vec<GetRedemptionResponse>>, (StatusCode, String)> {
+    let redemptions = state
+        .database_connection
+        .as_ref()
+        .list_redemptions(&query)
+        .await
+        .map_err(error_response)?;
+
+    Ok(Json(redemptions))
+}
+
+async fn deliver_redemption(
+    State(state): State<ServerConfig>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    state
+        .database_connection
+        .as_ref()
+        .deliver_redemption(id)
+        .await
+        .map_err(error_response)?;
+
+    Ok(StatusCode::OK)
+}
+
+async fn list_approvals(
+    State(state): State<ServerConfig>,
+    Query(query): Query<ListApprovalsQuery>,
+) -> Result<Json<Vec<GetApprovalResponse>>, (StatusCode, String)> {
+    let approvals = state
+        .database_connection
+        .as_ref()
+        .list_approvals(&query)
+        .await
+        .map_err(error_response)?;
+
+    Ok(Json(approvals))
+}
+
+async fn approve_approval(
+    State(state): State<ServerConfig>,
+    Path(id): Path<i32>,
+    headers: HeaderMap,
+    Json(payload): Json<ResolveApprovalRequest>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let caller = caller(&state.configuration, &headers)?;
+    state
+        .database_connection
+        .as_ref()
+        .resolve_approval(id, &payload, true, caller.as_ref())
+        .await
+        .map_err(error_response)?;
+
+    Ok(StatusCode::OK)
+}
+
+async fn reject_approval(
+    State(state): State<ServerConfig>,
+    Path(id): Path<i32>,
+    headers: HeaderMap,
+    Json(payload): Json<ResolveApprovalRequest>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let caller = caller(&state.configuration, &headers)?;
+    state
+        .database_connection
+        .as_ref()
+        .resolve_approval(id, &payload, false, caller.as_ref())
+        .await
+        .map_err(error_response)?;
+
+    Ok(StatusCode::OK)
+}
+
+async fn list_transfers(
+    State(state): State<ServerConfig>,
+    Query(query): Query<ListTransfersQuery>,
+) -> Result<Json<Vec<TransferResponse>>, (StatusCode, String)> {
+    let transfers = state
+        .database_connection
+        .as_ref()
+        .list_transfers(&query)
+        .await
+        .map_err(error_response)?;
+
+    Ok(Json(transfers))
+}
+
+async fn create_transfer(
+    State(state): State<ServerConfig>,
+    headers: HeaderMap,
+    Json(payload): Json<CreateTransferRequest>,
+) -> Result<(StatusCode, Json<TransferResponse>), (StatusCode, String)> {
+    let caller = caller(&state.configuration, &headers)?;
+    let transfer = state
+        .database_connection
+        .as_ref()
+        .create_transfer(&payload, caller.as_ref())
+        .await
+        .map_err(error_response)?;
+
+    // Waiting on a parent rather than moved
+    let status = if transfer.status == TransferStatus::Pending {
+        StatusCode::ACCEPTED
+    } else {
+        StatusCode::CREATED
+    };
+    Ok((status, Json(transfer)))
+}
+
+async fn approve_transfer(
+    State(state): State<ServerConfig>,
+    Path(id): Path<i32>,
+    headers: HeaderMap,
+    Json(payload): Json<ResolveApprovalRequest>,
+) -> Result<Json<TransferResponse>, (StatusCode, String)> {
+    let caller = caller(&state.configuration, &headers)?;
+    let transfer = state
+        .database_connection
+        .as_ref()
+        .resolve_transfer(id, &payload, true, caller.as_ref())
+        .await
+        .map_err(error_response)?;
+
+    Ok(Json(transfer))
+}
+
+async fn reject_transfer(
+    State(state): State<ServerConfig>,
+    Path(id): Path<i32>,
+    headers: HeaderMap,
+    Json(payload): Json<ResolveApprovalRequest>,
+) -> Result<Json<TransferResponse>, (StatusCode, String)> {
+    let caller = caller(&state.configuration, &headers)?;
+    let transfer = state
+        .database_connection
+        .as_ref()
+        .resolve_transfer(id, &payload, false, caller.as_ref())
+        .await
+        .map_err(error_response)?;
+
+    Ok(Json(transfer))
+}
+
+async fn initialize_db(
+    State(state): State<ServerConfig>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    //  This is a little ineligent
+    state
+        .database_connection
+        .as_ref()
+        .initialize(&state.configuration)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(StatusCode::OK)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cents_are_written_as_a_decimal_amount() {
+        assert_eq!(format_cents(0), "0.00");
+        assert_eq!(format_cents(5), "0.05");
+        assert_eq!(format_cents(1250), "12.50");
+        assert_eq!(format_cents(-5), "-0.05");
+        assert_eq!(format_cents(-1250), "-12.50");
+        assert_eq!(format_cents(i64::MIN), "-92233720368547758.08");
+    }
+
+    #[test]
+    fn plain_csv_fields_are_left_alone() {
+        assert_eq!(csv_field("Teeth"), "Teeth");
+        assert_eq!(csv_field(""), "");
+    }
+
+    #[test]
+    fn csv_fields_with_separators_are_quoted() {
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+        assert_eq!(csv_field("two\r\nlines"), "\"two\r\nlines\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
 }
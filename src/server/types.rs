@@ -1,3 +1,5 @@
+use crate::entity::star_charts::{ChartStatus, DeadlinePolicy, OverflowPolicy, Recurrence};
+use crate::entity::star_events::StarEventKind;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Deserialize)]
@@ -12,6 +14,7 @@ pub struct CreatePersonResponse {
     pub id: i32,
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Serialize)]
 pub struct GetPersonRequest {
     pub first_name: String,
@@ -24,6 +27,13 @@ pub struct GetPersonResponse {
     pub last_name: String,
     pub children: Vec<GetPersonResponse>,
     pub star_charts: Vec<GetStarChartResponse>,
+    pub badges: Vec<BadgeResponse>,
+    pub level: i32,
+    pub xp: i32,
+    // lifetime XP at which the next level is reached
+    pub next_level_xp: i32,
+    // 0-100 through the current level
+    pub level_progress: i32,
 }
 
 #[derive(Debug, Deserialize)]
@@ -34,6 +44,20 @@ pub struct CreateStarChartRequest {
     pub star_count: i32,
     pub star_total: i32,
     pub color: Option<String>,
+    pub recurrence: Option<Recurrence>,
+    #[serde(default)]
+    pub requires_approval: bool,
+    pub cooldown_seconds: Option<i32>,
+    pub daily_cap: Option<i32>,
+    pub overflow_policy: Option<OverflowPolicy>,
+    pub cents_per_star: Option<i32>,
+    // charts of the same person to complete before this one unlocks
+    #[serde(default)]
+    pub requires: Vec<i32>,
+    // RFC 3339 timestamps or YYYY-MM-DD dates; a due date runs to the end of that day
+    pub starts_at: Option<String>,
+    pub due_at: Option<String>,
+    pub deadline_policy: Option<DeadlinePolicy>,
 }
 
 #[derive(Debug, Serialize)]
@@ -49,6 +73,24 @@ pub struct GetStarChartResponse {
     pub star_count: i32,
     pub star_total: i32,
     pub color: Option<String>,
+    pub recurrence: Option<Recurrence>,
+    // consecutive days, or periods for recurring charts, with at least one star
+    pub current_streak: i32,
+    pub best_streak: i32,
+    pub requires_approval: bool,
+    pub cooldown_seconds: Option<i32>,
+    pub daily_cap: Option<i32>,
+    pub status: ChartStatus,
+    pub completed_at: Option<String>,
+    pub overflow_policy: OverflowPolicy,
+    // changes on every write, also sent as the ETag
+    pub version: i32,
+    pub template: Option<String>,
+    pub cents_per_star: Option<i32>,
+    pub starts_at: Option<String>,
+    pub due_at: Option<String>,
+    pub deadline_policy: DeadlinePolicy,
+    pub archived_at: Option<String>,
     // who this chart belongs to
     pub person_first_name: String,
     pub person_last_name: String,
@@ -60,6 +102,21 @@ pub struct UpdateStarChartRequest {
     pub description: String,
     pub star_count: Option<i32>,
     pub star_total: Option<i32>,
+    // null stops the chart recurring
+    #[serde(default, deserialize_with = "double_option")]
+    pub recurrence: Option<Option<Recurrence>>,
+    pub requires_approval: Option<bool>,
+    pub cooldown_seconds: Option<i32>,
+    pub daily_cap: Option<i32>,
+    pub overflow_policy: Option<OverflowPolicy>,
+    pub cents_per_star: Option<i32>,
+    // "active" reopens an expired or failed chart, or unlocks a quest whose
+    // required charts are completed. Archiving goes through DELETE /admin/stars/:id
+    pub status: Option<ChartStatus>,
+    // an empty string clears the date
+    pub starts_at: Option<String>,
+    pub due_at: Option<String>,
+    pub deadline_policy: Option<DeadlinePolicy>,
 }
 
 #[derive(Debug, Serialize)]
@@ -67,16 +124,61 @@ pub struct PersonListItem {
     pub id: i32,
     pub first_name: String,
     pub last_name: String,
+    pub level: i32,
+    pub xp: i32,
+    pub next_level_xp: i32,
+    pub level_progress: i32,
+    pub archived_at: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct IncrementStarChartRequest {
     pub delta: i32,
+    pub note: Option<String>,
+    pub actor: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StarEventResponse {
+    pub id: i32,
+    pub star_chart_id: i32,
+    pub chart_name: String,
+    pub delta: i32,
+    pub kind: StarEventKind,
+    pub note: Option<String>,
+    pub actor: Option<String>,
+    pub created_at: String,
 }
 
 #[derive(Debug, Serialize)]
 pub struct UpdateStarChartResponse {
     pub id: i32,
+    pub version: i32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IncrementStarChartResponse {
+    pub id: i32,
+    pub star_count: i32,
+    // set when the increment is waiting for a parent instead of being applied
+    pub approval_id: Option<i32>,
+    // seconds before the chart accepts another star
+    pub cooldown_seconds: Option<i32>,
+    // true only for the increment that reached star_total
+    pub completed: bool,
+    // quest charts this completion unlocked
+    pub unlocked: Vec<i32>,
+    // bonus window that multiplied the stars, if any
+    pub bonus: Option<AppliedBonus>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AppliedBonus {
+    pub bonus_window_id: i32,
+    pub name: String,
+    pub multiplier: i32,
+    // stars asked for before the multiplier
+    pub base_delta: i32,
 }
 
 #[derive(Debug, Deserialize)]
@@ -85,6 +187,58 @@ pub struct CreateCalendarEventRequest {
     pub person_id: i32,
     pub start: String,
     pub end: String,
+    // RFC 5545 rule such as FREQ=WEEKLY;BYDAY=TU to repeat the event
+    pub rrule: Option<String>,
+    // start times of occurrences to leave out
+    #[serde(default)]
+    pub exdates: Vec<String>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EditScope {
+    // only the given occurrence
+    This,
+    // the given occurrence and every one after it
+    Future,
+    // the whole series
+    #[default]
+    All,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateCalendarEventRequest {
+    pub title: Option<String>,
+    pub person_id: Option<i32>,
+    pub start: Option<String>,
+    pub end: Option<String>,
+    // new rule for the series, or an empty string to stop repeating
+    pub rrule: Option<String>,
+    #[serde(default)]
+    pub scope: EditScope,
+    // original start of the occurrence, needed for this and future
+    pub occurrence: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UpdateCalendarEventResponse {
+    // the event holding the change, new when part of a series was split off
+    pub id: i32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeleteCalendarEventQuery {
+    #[serde(default)]
+    pub scope: EditScope,
+    pub occurrence: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListCalendarEventsQuery {
+    // window to list, repeating events are expanded a month back and a year
+    // ahead when it is left out
+    pub start: Option<String>,
+    pub end: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -92,6 +246,41 @@ pub struct CreateCalendarEventResponse {
     pub id: i32,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct ImportCalendarQuery {
+    // person the imported events are for
+    pub person_id: i32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportedCalendarEvent {
+    pub id: i32,
+    pub uid: String,
+    pub title: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SkippedCalendarEvent {
+    pub uid: Option<String>,
+    pub title: Option<String>,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportCalendarResponse {
+    pub created: Vec<ImportedCalendarEvent>,
+    pub updated: Vec<ImportedCalendarEvent>,
+    // unchanged since the last import, or unreadable
+    pub skipped: Vec<SkippedCalendarEvent>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UndoResponse {
+    pub action: crate::entity::undo_entries::UndoAction,
+    // star chart or calendar event that was changed back
+    pub target_id: i32,
+}
+
 #[derive(Debug, Serialize)]
 pub struct CalendarEventResponse {
     pub id: i32,
@@ -100,6 +289,17 @@ pub struct CalendarEventResponse {
     pub end: String,
     #[serde(rename = "resourceId")]
     pub resource_id: i32,
+    // repeating series this occurrence belongs to
+    pub series_id: Option<i32>,
+    // original start of the occurrence, used to edit just this one
+    pub occurrence: Option<String>,
+    pub rrule: Option<String>,
+}
+
+/// A rendered iCalendar feed and when any event last changed
+pub struct CalendarFeed {
+    pub body: String,
+    pub last_modified: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 #[derive(Debug, Serialize)]
@@ -111,3 +311,552 @@ pub struct CalendarPersonResponse {
     #[serde(rename = "eventTextColor")]
     pub event_text_color: Option<String>,
 }
+
+#[derive(Debug, Deserialize)]
+pub struct CreateRewardRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub cost: i32,
+    // restrict the reward to a single person
+    pub person_id: Option<i32>,
+    pub stock: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreateRewardResponse {
+    pub id: i32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateRewardRequest {
+    pub name: String,
+    // a missing field keeps the current value, null clears it
+    #[serde(default, deserialize_with = "double_option")]
+    pub description: Option<Option<String>>,
+    pub cost: Option<i32>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub person_id: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub stock: Option<Option<i32>>,
+}
+
+/// Tell a field that was left out (`None`) from one set to null (`Some(None)`)
+fn double_option<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+#[derive(Debug, Serialize)]
+pub struct GetRewardResponse {
+    pub id: i32,
+    pub name: String,
+    pub description: Option<String>,
+    pub cost: i32,
+    pub person_id: Option<i32>,
+    pub stock: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RedeemRewardRequest {
+    pub person_id: i32,
+    // spend from this chart, otherwise from the person's balance across all charts
+    pub star_chart_id: Option<i32>,
+    pub actor: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RedeemRewardResponse {
+    pub id: i32,
+    pub cost: i32,
+    pub status: crate::entity::reward_redemptions::RedemptionStatus,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListRedemptionsQuery {
+    pub status: Option<crate::entity::reward_redemptions::RedemptionStatus>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GetRedemptionResponse {
+    pub id: i32,
+    pub reward_id: i32,
+    pub reward_name: String,
+    pub person_id: i32,
+    pub star_chart_id: Option<i32>,
+    pub cost: i32,
+    pub status: crate::entity::reward_redemptions::RedemptionStatus,
+    pub actor: Option<String>,
+    pub created_at: String,
+    pub delivered_at: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StarChartPeriodResponse {
+    pub id: i32,
+    pub star_chart_id: i32,
+    pub period_start: String,
+    pub period_end: String,
+    pub star_count: i32,
+    pub star_total: i32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChartStreakResponse {
+    pub star_chart_id: i32,
+    pub name: String,
+    // "daily", "weekly" or "monthly"
+    pub unit: Recurrence,
+    pub current_streak: i32,
+    pub best_streak: i32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListApprovalsQuery {
+    pub status: Option<crate::entity::star_approvals::ApprovalStatus>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GetApprovalResponse {
+    pub id: i32,
+    pub star_chart_id: i32,
+    pub chart_name: String,
+    pub delta: i32,
+    pub note: Option<String>,
+    pub actor: Option<String>,
+    pub status: crate::entity::star_approvals::ApprovalStatus,
+    pub created_at: String,
+    pub resolved_by: Option<String>,
+    pub resolved_at: Option<String>,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResolveApprovalRequest {
+    pub reason: Option<String>,
+}
+
+/// The family member a request came from, recognised by the server from
+/// their PIN rather than taken from the body
+#[derive(Debug, Clone)]
+pub struct Caller {
+    pub first_name: String,
+    pub is_parent: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BadgeResponse {
+    pub key: String,
+    pub name: String,
+    pub description: String,
+    pub unlocked_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LockedBadgeResponse {
+    pub key: String,
+    pub name: String,
+    pub description: String,
+    pub progress: i32,
+    pub threshold: i32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TemplateResponse {
+    pub key: String,
+    pub name: String,
+    pub description: String,
+    pub star_total: i32,
+    pub color: Option<String>,
+    pub recurrence: Option<Recurrence>,
+    // charts created from this template that are not archived
+    pub chart_count: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InstantiateTemplateRequest {
+    // create the chart for this person...
+    pub person_id: Option<i32>,
+    // ...or for every child of this parent
+    pub parent_id: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InstantiateTemplateResponse {
+    pub star_chart_ids: Vec<i32>,
+    // people who already have an unarchived chart from this template
+    pub skipped_person_ids: Vec<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PropagateTemplateResponse {
+    pub star_chart_ids: Vec<i32>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum StatsBucket {
+    #[default]
+    Day,
+    Week,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LeaderboardRank {
+    #[default]
+    Stars,
+    // share of each person's combined star_total, fairer across ages
+    Percent,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StatsQuery {
+    // inclusive YYYY-MM-DD dates in local time
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub person_id: Option<i32>,
+    #[serde(default)]
+    pub bucket: StatsBucket,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LeaderboardQuery {
+    #[serde(default)]
+    pub rank_by: LeaderboardRank,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PersonStatsResponse {
+    pub person_id: i32,
+    pub first_name: String,
+    pub last_name: String,
+    // positive star events within the date range
+    pub stars_earned: i64,
+    pub chart_count: i64,
+    pub completed_charts: i64,
+    pub completion_percent: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StarsPerPeriodResponse {
+    // the day, or the Monday starting the week
+    pub period: String,
+    pub person_id: i32,
+    pub stars: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LeaderboardEntryResponse {
+    pub rank: i64,
+    pub person_id: i32,
+    pub first_name: String,
+    pub last_name: String,
+    pub star_count: i64,
+    pub star_total: i64,
+    pub percent_of_goal: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AllowanceBalanceResponse {
+    pub person_id: i32,
+    pub first_name: String,
+    pub last_name: String,
+    pub cents_per_star: Option<i32>,
+    pub balance_cents: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AllowanceEntryResponse {
+    pub id: i32,
+    pub kind: crate::entity::allowance_entries::EntryKind,
+    pub amount_cents: i64,
+    // balance after this entry
+    pub balance_cents: i64,
+    pub star_chart_id: Option<i32>,
+    pub stars: Option<i32>,
+    pub note: Option<String>,
+    pub actor: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AllowanceStatementResponse {
+    pub person_id: i32,
+    pub first_name: String,
+    pub last_name: String,
+    pub cents_per_star: Option<i32>,
+    pub balance_cents: i64,
+    pub entries: Vec<AllowanceEntryResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AllowanceEntryRequest {
+    // payouts are positive, adjustments may be negative
+    pub amount_cents: i64,
+    pub note: Option<String>,
+    pub actor: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateAllowanceRequest {
+    pub cents_per_star: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateChoreRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub rotation: crate::entity::chores::Rotation,
+    // rotate between these people, in this order...
+    pub assignee_ids: Option<Vec<i32>>,
+    // ...or between the children of this parent
+    pub parent_id: Option<i32>,
+    // YYYY-MM-DD, defaults to today
+    pub starts_on: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChoreResponse {
+    pub id: i32,
+    pub name: String,
+    pub description: Option<String>,
+    pub rotation: crate::entity::chores::Rotation,
+    pub assignee_ids: Vec<i32>,
+    pub starts_on: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChoreAssignmentsQuery {
+    // YYYY-MM-DD, defaults to today
+    pub date: Option<String>,
+    pub person_id: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChoreAssignmentResponse {
+    pub id: i32,
+    pub chore_id: i32,
+    pub chore_name: String,
+    pub person_id: i32,
+    pub person_first_name: String,
+    pub due_on: String,
+    pub completed_at: Option<String>,
+    pub completed_by: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CompleteChoreRequest {
+    pub completed_by: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListBountiesQuery {
+    pub status: Option<crate::entity::bounties::BountyStatus>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateBountyRequest {
+    pub title: String,
+    pub description: Option<String>,
+    pub reward: i32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ClaimBountyRequest {
+    pub person_id: i32,
+    // chart of the claimant the reward goes to
+    pub star_chart_id: i32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CompleteBountyRequest {
+    pub person_id: i32,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct VerifyBountyRequest {
+    // another chart of the claimant, when the claimed one can't take the reward
+    pub star_chart_id: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BountyResponse {
+    pub id: i32,
+    pub title: String,
+    pub description: Option<String>,
+    pub reward: i32,
+    pub status: crate::entity::bounties::BountyStatus,
+    pub posted_by: String,
+    pub created_at: String,
+    pub claimed_by: Option<i32>,
+    pub star_chart_id: Option<i32>,
+    pub claimed_at: Option<String>,
+    pub completed_at: Option<String>,
+    pub verified_by: Option<String>,
+    pub verified_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetPrerequisitesRequest {
+    pub requires: Vec<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QuestResponse {
+    pub star_chart_id: i32,
+    pub name: String,
+    pub status: ChartStatus,
+    pub star_count: i32,
+    pub star_total: i32,
+    pub requires: Vec<i32>,
+    pub unlocks: Vec<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LevelUpResponse {
+    pub level: i32,
+    pub xp: i32,
+    pub reached_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateGoalRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub star_total: i32,
+    pub color: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ContributeGoalRequest {
+    // the contributor's own chart, which pays for the stars and gets back any
+    // taken out again
+    pub star_chart_id: i32,
+    pub delta: i32,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GoalContributorResponse {
+    pub person_id: i32,
+    pub first_name: String,
+    // net stars added, after any taken back
+    pub stars: i32,
+    pub contributions: i32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GoalResponse {
+    pub id: i32,
+    pub name: String,
+    pub description: Option<String>,
+    pub star_count: i32,
+    pub star_total: i32,
+    pub color: Option<String>,
+    pub status: ChartStatus,
+    pub created_at: String,
+    pub completed_at: Option<String>,
+    pub contributors: Vec<GoalContributorResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ArchivedQuery {
+    #[serde(default)]
+    pub include_archived: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ListStarChartsQuery {
+    #[serde(default)]
+    pub include_archived: bool,
+    // only active charts due within this many days, soonest first
+    pub due_within_days: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PurgeResponse {
+    pub people: usize,
+    pub star_charts: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListTransfersQuery {
+    pub status: Option<crate::entity::star_transfers::TransferStatus>,
+    // transfers to or from this person
+    pub person_id: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateTransferRequest {
+    pub from_star_chart_id: i32,
+    pub to_star_chart_id: i32,
+    pub amount: i32,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TransferResponse {
+    pub id: i32,
+    pub from_star_chart_id: i32,
+    pub to_star_chart_id: i32,
+    pub from_person_id: i32,
+    pub to_person_id: i32,
+    pub amount: i32,
+    pub note: Option<String>,
+    pub actor: Option<String>,
+    pub status: crate::entity::star_transfers::TransferStatus,
+    pub created_at: String,
+    pub resolved_by: Option<String>,
+    pub resolved_at: Option<String>,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DueSoonResponse {
+    pub star_chart_id: i32,
+    pub name: String,
+    pub person_id: i32,
+    pub person_first_name: String,
+    pub due_at: String,
+    pub hours_left: i64,
+    pub star_count: i32,
+    pub star_total: i32,
+    pub stars_needed: i32,
+    pub deadline_policy: DeadlinePolicy,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListBonusWindowsQuery {
+    // leave out windows that have already ended
+    #[serde(default)]
+    pub upcoming: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateBonusWindowRequest {
+    pub name: String,
+    pub multiplier: i32,
+    pub scope: crate::entity::bonus_windows::BonusScope,
+    // star chart or person ids, depending on the scope
+    #[serde(default)]
+    pub target_ids: Vec<i32>,
+    pub starts_at: String,
+    pub ends_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BonusWindowResponse {
+    pub id: i32,
+    pub name: String,
+    pub multiplier: i32,
+    pub scope: crate::entity::bonus_windows::BonusScope,
+    pub target_ids: Vec<i32>,
+    pub starts_at: String,
+    pub ends_at: String,
+    pub created_by: String,
+    pub created_at: String,
+    pub active: bool,
+}